@@ -0,0 +1,68 @@
+//! Parse throughput over fixture articles of different shapes
+//!
+//! `parse_wikitext` covers the whole pipeline (node tree walk plus the regex
+//! passes of `postprocess_text`); `postprocess_text` times the regex passes
+//! alone on the extracted text, so the two can be compared when moving work
+//! between them.
+//!
+//! Usage:
+//!   cargo bench --bench parse
+
+use std::collections::HashSet;
+use std::hint::black_box;
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use wikitext_parser::configuration;
+use wikitext_parser::parser::{self, ParseOptions};
+
+/// (name, wikitext) pairs: a stub, a long prose article and a table-heavy one
+const FIXTURES: [(&str, &str); 3] = [
+    ("short", include_str!("fixtures/short.wiki")),
+    ("medium", include_str!("fixtures/medium.wiki")),
+    ("tables", include_str!("fixtures/tables.wiki")),
+];
+
+/// The options the binaries start from
+fn binary_options() -> ParseOptions {
+    let strings = |values: &[&str]| values.iter().map(|value| value.to_string()).collect();
+    ParseOptions {
+        drop_templates: strings(parser::DEFAULT_DROP_TEMPLATES),
+        expand_templates: strings(parser::DEFAULT_EXPAND_TEMPLATES),
+        drop_link_namespaces: strings(parser::DEFAULT_DROP_LINK_NAMESPACES),
+        section_names: strings(parser::DEFAULT_SECTION_NAMES),
+        category_prefixes: strings(parser::DEFAULT_CATEGORY_PREFIXES),
+        html_breaks: true,
+        ..Default::default()
+    }
+}
+
+fn bench_parse(c: &mut Criterion) {
+    let options = binary_options();
+    let mut group = c.benchmark_group("parse_wikitext");
+    for (name, wikitext) in FIXTURES {
+        group.throughput(Throughput::Bytes(wikitext.len() as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(name), wikitext, |b, wikitext| {
+            b.iter(|| parser::parse_wikitext(black_box(wikitext), &options))
+        });
+    }
+    group.finish();
+}
+
+fn bench_postprocess(c: &mut Criterion) {
+    let options = binary_options();
+    let headings = HashSet::new();
+    let mut group = c.benchmark_group("postprocess_text");
+    for (name, wikitext) in FIXTURES {
+        // Time the regex passes on the text they actually see
+        let nodes = configuration::configuration(options.language).parse(wikitext).nodes;
+        let extracted = parser::extract_text_from_nodes(&nodes, wikitext, &options).unwrap_or_default();
+        group.throughput(Throughput::Bytes(extracted.len() as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(name), &extracted, |b, text| {
+            b.iter(|| parser::postprocess_text(black_box(text), &headings, &options))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_parse, bench_postprocess);
+criterion_main!(benches);
@@ -61,7 +61,7 @@ if command -v parallel &> /dev/null; then
     ls "$INPUT_DIR"/parsed_* | parallel -j "$PARALLEL_JOBS" --bar \
         'basename=$(basename {}); echo "Processing: $basename"; \
          export PATH="$HOME/.cargo/bin:$PATH"; \
-         cargo run --release --bin export_parsed -- {} "'"$OUTPUT_DIR_OFFICIAL"'" "'"$OUTPUT_DIR_CLONE"'" 2>&1 | grep -q "Export complete" && \
+         cargo run --release --bin export_parsed -- --input {} --output-dir "'"$OUTPUT_DIR_OFFICIAL"'" --column-dir clone_text_paragraphs="'"$OUTPUT_DIR_CLONE"'" 2>&1 | grep -q "Export complete" && \
          echo "✓ Complete: $basename" || echo "⚠ Warning: $basename"'
 
 else
@@ -79,7 +79,7 @@ else
 
         # Run export
         export PATH="$HOME/.cargo/bin:$PATH"
-        if cargo run --release --bin export_parsed -- "$input_file" "$output_dir_official" "$output_dir_clone" 2>&1 | grep -q "Export complete"; then
+        if cargo run --release --bin export_parsed -- --input "$input_file" --output-dir "$output_dir_official" --column-dir clone_text_paragraphs="$output_dir_clone" 2>&1 | grep -q "Export complete"; then
             echo "✓ Complete: $basename"
             exit 0
         else
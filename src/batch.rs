@@ -0,0 +1,123 @@
+//! In-memory Arrow API: parse the text columns of a `RecordBatch`
+//!
+//! Applies the `parse_single` transformation to batches built by the caller,
+//! without reading or writing files: every input column is kept in place,
+//! and each text column is replaced by `<col>_parsed` followed by its parse
+//! status (`parse_status`, or `<col>_parse_status` with several text columns).
+//!
+//! ```no_run
+//! use wikitext_parser::batch::{BatchOptions, BatchProcessor};
+//! # fn run(batches: Vec<arrow::array::RecordBatch>) -> anyhow::Result<()> {
+//! let processor = BatchProcessor::new(BatchOptions { text_columns: vec!["text".into()], ..Default::default() });
+//! for batch in &batches {
+//!     let parsed = processor.process(batch)?;
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::columns;
+use crate::decompress::{self, Decompress, OnInvalid};
+use crate::parser::{self, ParseOptions};
+use crate::pool::ParsePool;
+use crate::status;
+use anyhow::Result;
+use arrow::array::{ArrayRef, RecordBatch, StringArray};
+use arrow::datatypes::{DataType, Field, Schema};
+use std::sync::Arc;
+
+/// What `process_record_batch` parses and how
+#[derive(Clone, Debug)]
+pub struct BatchOptions {
+    /// Text columns to parse (empty = the auto-detected text column)
+    pub text_columns: Vec<String>,
+    /// Extraction options applied to every article
+    pub parse: ParseOptions,
+    /// Per-article timeout in seconds (0 = no timeout)
+    pub timeout_secs: u64,
+    /// Worker threads (0 = one per CPU core)
+    pub threads: usize,
+}
+
+impl Default for BatchOptions {
+    fn default() -> Self {
+        BatchOptions { text_columns: Vec::new(), parse: ParseOptions::default(), timeout_secs: 30, threads: 0 }
+    }
+}
+
+/// Parses batch after batch with one worker pool
+pub struct BatchProcessor {
+    text_columns: Vec<String>,
+    pool: ParsePool,
+}
+
+impl BatchProcessor {
+    pub fn new(options: BatchOptions) -> Self {
+        let parse = options.parse;
+        let pool = ParsePool::new(options.threads, options.timeout_secs, move |text| {
+            parser::try_parse_wikitext_with_warnings(text, &parse)
+        });
+        BatchProcessor { text_columns: options.text_columns, pool }
+    }
+
+    /// Parse the text columns of one batch; skipped articles get a null text and their reason as status
+    pub fn process(&self, batch: &RecordBatch) -> Result<RecordBatch> {
+        let schema = batch.schema();
+        let text_columns = match self.text_columns.as_slice() {
+            [] => vec![columns::detect_text_column(&schema)
+                .ok_or_else(|| anyhow::anyhow!("Could not auto-detect a text column; set BatchOptions::text_columns"))?],
+            names => names.to_vec(),
+        };
+        for name in &text_columns {
+            if schema.field_with_name(name).is_err() {
+                anyhow::bail!("Text column '{}' not found in batch", name);
+            }
+        }
+        // Binary, LargeUtf8 and Utf8View text columns become Utf8
+        let names: Vec<&str> = text_columns.iter().map(String::as_str).collect();
+        let batch = &decompress::decode_text_columns(batch, &names, Decompress::Auto, OnInvalid::Skip)?;
+        let schema = batch.schema();
+
+        let mut fields = Vec::new();
+        let mut output_columns: Vec<ArrayRef> = Vec::new();
+        for (field, column) in schema.fields().iter().zip(batch.columns()) {
+            let name = field.name();
+            if !text_columns.contains(name) {
+                fields.push(field.as_ref().clone());
+                output_columns.push(Arc::clone(column));
+                continue;
+            }
+            let texts = column
+                .as_any()
+                .downcast_ref::<StringArray>()
+                .ok_or_else(|| anyhow::anyhow!("Text column '{}' is not a StringArray", name))?;
+            let inputs: Vec<Option<&str>> = texts.iter().collect();
+            let (results, _) = self.pool.parse_all(&inputs, |i| format!("{} row {}", name, i));
+
+            fields.push(Field::new(format!("{}_parsed", name), DataType::Utf8, true));
+            fields.push(Field::new(derived_column_name(&text_columns, name, "parse_status"), DataType::Utf8, true));
+            let statuses = status::status_array(&results, None);
+            output_columns.push(Arc::new(StringArray::from(status::texts(results))));
+            output_columns.push(statuses);
+        }
+
+        let schema = Schema::new_with_metadata(fields, schema.metadata().clone());
+        Ok(RecordBatch::try_new(Arc::new(schema), output_columns)?)
+    }
+}
+
+/// Parse the text columns of one batch
+/// Starts a worker pool per call; use `BatchProcessor` for a stream of batches
+pub fn process_record_batch(batch: &RecordBatch, options: &BatchOptions) -> Result<RecordBatch> {
+    BatchProcessor::new(options.clone()).process(batch)
+}
+
+/// Name of a per-text-column output such as `parse_status` or `simhash`
+/// A single text column keeps the plain name; several get `<col>_<name>`
+pub fn derived_column_name(text_columns: &[String], text_column: &str, name: &str) -> String {
+    if text_columns.len() == 1 {
+        name.to_string()
+    } else {
+        format!("{}_{}", text_column, name)
+    }
+}
@@ -0,0 +1,149 @@
+//! Compare parsing with lists kept vs. skipped over a fixed corpus
+//!
+//! Parses every article of a parquet file twice — once with lists flattened
+//! and once with `ListStyle::Skip` — and reports total parse time and output size
+//! for both modes. Useful for checking that list-handling changes don't
+//! regress performance and for deciding whether `--skip-lists` is worth it.
+//!
+//! Usage:
+//!   cargo run --release --bin bench_lists -- --input data/sample_wikitext.parquet
+
+use anyhow::Result;
+use clap::Parser as ClapParser;
+use std::fs::File;
+use std::time::{Duration, Instant};
+use wikitext_parser::parser;
+
+use arrow::array::{Array, StringArray};
+use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+#[derive(ClapParser, Debug)]
+#[command(author, version, about = "Compare parse time and output size with lists kept vs. skipped", long_about = None)]
+struct Args {
+    /// Input parquet file used as the benchmark corpus
+    #[arg(short, long)]
+    input: String,
+
+    /// Text column to parse (default: every known wikitext column present)
+    #[arg(long)]
+    text_column: Option<String>,
+
+    /// Number of times to parse the corpus in each mode
+    #[arg(long, default_value_t = 1)]
+    iterations: u32,
+}
+
+/// Wikitext columns used when no --text-column is given
+const TEXT_COLUMNS: [&str; 4] = ["official_text", "clone_text", "text", "content"];
+
+/// Accumulated timing and size for one list mode
+#[derive(Default)]
+struct ModeStats {
+    elapsed: Duration,
+    output_bytes: usize,
+}
+
+fn main() -> Result<()> {
+    let args = Args::parse();
+
+    println!("Reading corpus: {}", args.input);
+
+    let file = File::open(&args.input)?;
+    let builder = ParquetRecordBatchReaderBuilder::try_new(file)?;
+    let schema = builder.schema().clone();
+    let reader = builder.build()?;
+
+    let columns: Vec<String> = match &args.text_column {
+        Some(col) => vec![col.clone()],
+        None => TEXT_COLUMNS
+            .iter()
+            .filter(|name| schema.field_with_name(name).is_ok())
+            .map(|name| name.to_string())
+            .collect(),
+    };
+    if columns.is_empty() {
+        anyhow::bail!("No wikitext column found. Use --text-column to specify.");
+    }
+    println!("Using text column(s): {:?}", columns);
+
+    // Load the corpus up front so I/O is excluded from the timings
+    let mut articles: Vec<String> = Vec::new();
+    for batch in reader {
+        let batch = batch?;
+        for col in &columns {
+            let array = batch
+                .column_by_name(col)
+                .ok_or_else(|| anyhow::anyhow!("Column '{}' not found", col))?
+                .as_any()
+                .downcast_ref::<StringArray>()
+                .ok_or_else(|| anyhow::anyhow!("Column '{}' is not a StringArray", col))?;
+            articles.extend(array.iter().flatten().map(|text| text.to_string()));
+        }
+    }
+
+    if articles.is_empty() {
+        println!("No articles found in input file");
+        return Ok(());
+    }
+
+    println!("Parsing {} articles x {} iteration(s) per mode...", articles.len(), args.iterations);
+
+    let keep = run_mode(&articles, parser::ListStyle::Flatten, args.iterations);
+    let skip = run_mode(&articles, parser::ListStyle::Skip, args.iterations);
+
+    println!();
+    println!("{:<24}{:>16}{:>16}", "", "lists kept", "lists skipped");
+    println!(
+        "{:<24}{:>15.3}s{:>15.3}s",
+        "Total parse time:",
+        keep.elapsed.as_secs_f64(),
+        skip.elapsed.as_secs_f64()
+    );
+    println!(
+        "{:<24}{:>16}{:>16}",
+        "Output size (bytes):", keep.output_bytes, skip.output_bytes
+    );
+    println!();
+    println!(
+        "Time difference:  {:+.1}%",
+        percent_change(keep.elapsed.as_secs_f64(), skip.elapsed.as_secs_f64())
+    );
+    println!(
+        "Size difference:  {:+.1}%",
+        percent_change(keep.output_bytes as f64, skip.output_bytes as f64)
+    );
+
+    Ok(())
+}
+
+/// Parse the whole corpus with the given list mode
+fn run_mode(articles: &[String], list_style: parser::ListStyle, iterations: u32) -> ModeStats {
+    let options = parser::ParseOptions {
+        list_style,
+        section_names: parser::DEFAULT_SECTION_NAMES.iter().map(|n| n.to_string()).collect(),
+        category_prefixes: parser::DEFAULT_CATEGORY_PREFIXES.iter().map(|p| p.to_string()).collect(),
+        ..Default::default()
+    };
+    let mut stats = ModeStats::default();
+
+    for _ in 0..iterations {
+        let start = Instant::now();
+        let output_bytes: usize = articles
+            .iter()
+            .map(|article| parser::parse_wikitext(article, &options).len())
+            .sum();
+        stats.elapsed += start.elapsed();
+        stats.output_bytes = output_bytes;
+    }
+
+    stats
+}
+
+/// Relative change from `base` to `value` in percent
+fn percent_change(base: f64, value: f64) -> f64 {
+    if base == 0.0 {
+        0.0
+    } else {
+        (value - base) / base * 100.0
+    }
+}
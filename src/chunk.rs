@@ -0,0 +1,92 @@
+//! Overlapping fixed-size chunks of extracted text for embedding pipelines
+//!
+//! Chunk sizes are measured in characters. Windows always break at word
+//! boundaries: a chunk holds as many whole words as fit in `size`, and the
+//! next chunk starts with the trailing words of the previous one that fit in
+//! `overlap`. A single word longer than `size` becomes its own chunk.
+//! `paragraphs` splits a text at its blank-line paragraph breaks instead.
+
+/// Chunk window size and overlap, in characters
+#[derive(Clone, Copy, Debug)]
+pub struct ChunkOptions {
+    pub size: usize,
+    pub overlap: usize,
+}
+
+/// A word's position in the text, in bytes (for slicing) and chars (for sizing)
+struct WordSpan {
+    byte_start: usize,
+    byte_end: usize,
+    char_start: usize,
+    char_end: usize,
+}
+
+/// Split a text into overlapping word-aligned chunks
+/// The original text between words (including paragraph breaks) is kept
+pub fn chunk_text(text: &str, options: ChunkOptions) -> Vec<String> {
+    let words = word_spans(text);
+    if words.is_empty() {
+        return Vec::new();
+    }
+
+    // Characters covered by words[from..to], including the gaps between them
+    let span_chars = |from: usize, to: usize| words[to - 1].char_end - words[from].char_start;
+
+    let mut chunks = Vec::new();
+    let mut start = 0;
+    loop {
+        let mut end = start + 1;
+        while end < words.len() && span_chars(start, end + 1) <= options.size {
+            end += 1;
+        }
+        chunks.push(text[words[start].byte_start..words[end - 1].byte_end].to_string());
+
+        if end == words.len() {
+            break;
+        }
+
+        // Step back over trailing words that fit in the overlap, always making
+        // progress and leaving room for at least one new word in the next chunk
+        let mut next = end;
+        while next - 1 > start
+            && span_chars(next - 1, end) <= options.overlap
+            && span_chars(next - 1, end + 1) <= options.size
+        {
+            next -= 1;
+        }
+        start = next;
+    }
+
+    chunks
+}
+
+/// Split a text into its non-empty paragraphs (separated by blank lines), trimmed
+pub fn paragraphs(text: &str) -> Vec<String> {
+    text.split("\n\n")
+        .map(str::trim)
+        .filter(|paragraph| !paragraph.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+fn word_spans(text: &str) -> Vec<WordSpan> {
+    let mut spans = Vec::new();
+    let mut current: Option<(usize, usize)> = None;
+    let mut char_count = 0;
+
+    for (byte_index, c) in text.char_indices() {
+        if c.is_whitespace() {
+            if let Some((byte_start, char_start)) = current.take() {
+                spans.push(WordSpan { byte_start, byte_end: byte_index, char_start, char_end: char_count });
+            }
+        } else if current.is_none() {
+            current = Some((byte_index, char_count));
+        }
+        char_count += 1;
+    }
+    if let Some((byte_start, char_start)) = current {
+        spans.push(WordSpan { byte_start, byte_end: text.len(), char_start, char_end: char_count });
+    }
+
+    spans
+}
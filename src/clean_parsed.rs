@@ -1,12 +1,13 @@
 use anyhow::Result;
 use arrow::array::{Array, ArrayRef, RecordBatch, StringArray};
+use arrow::compute::cast;
+use arrow::datatypes::{DataType, Field, Schema};
 use clap::Parser as ClapParser;
 use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
 use parquet::arrow::ArrowWriter;
 use parquet::file::properties::WriterProperties;
-use regex::Regex;
-use std::fs::File;
 use std::sync::Arc;
+use wikitext_parser::{output, parser, schema, storage};
 
 #[derive(ClapParser, Debug)]
 #[command(author, version, about, long_about = None)]
@@ -18,15 +19,49 @@ struct Args {
     /// Output parquet file path (clean)
     #[arg(short, long)]
     output: String,
+
+    /// Write cleaned text to new `<column><suffix>` columns (e.g. "_clean")
+    /// instead of replacing the dirty columns
+    #[arg(long)]
+    suffix: Option<String>,
+
+    /// Parquet compression codec for the output
+    #[arg(long, value_enum, default_value_t = output::OutputCompression::None)]
+    compression: output::OutputCompression,
+
+    /// Compression level for --compression gzip (0-10) or zstd (1-22)
+    #[arg(long)]
+    compression_level: Option<i32>,
+
+    /// Leaked template names expanded to their text instead of removed, comma-separated
+    /// (default: date, num, convert and nowrap templates; pass "" to remove all)
+    #[arg(long, value_delimiter = ',')]
+    expand_templates: Option<Vec<String>>,
+
+    /// Wiki language for month names in expanded date templates (ru, en, de)
+    #[arg(long, value_enum, default_value_t = parser::Language::Ru)]
+    language: parser::Language,
+
+    /// Re-read the written output and fail if its row count or schema don't match
+    #[arg(long, default_value_t = false)]
+    verify_output: bool,
 }
 
 fn main() -> Result<()> {
     let args = Args::parse();
+    let compression = args.compression.codec(args.compression_level)?;
+    let options = CleanOptions {
+        expand_templates: match &args.expand_templates {
+            Some(names) => names.iter().filter(|n| !n.is_empty()).cloned().collect(),
+            None => parser::DEFAULT_EXPAND_TEMPLATES.iter().map(|n| n.to_string()).collect(),
+        },
+        language: args.language,
+    };
 
     println!("Reading input file: {}", args.input);
 
     // Read input parquet file
-    let file = File::open(&args.input)?;
+    let file = storage::open_input(&args.input)?;
     let builder = ParquetRecordBatchReaderBuilder::try_new(file)?;
     let schema = builder.schema().clone();
     let mut reader = builder.build()?;
@@ -72,65 +107,106 @@ fn main() -> Result<()> {
         .enumerate()
         .map(|(i, batch)| {
             println!("  Cleaning batch {}/{}", i + 1, batches.len());
-            clean_batch(batch, &text_columns)
+            clean_batch(batch, &text_columns, args.suffix.as_deref(), &options)
         })
         .collect::<Result<Vec<_>>>()?;
 
     // Write output parquet file
     println!("Writing output file: {}", args.output);
-    let output_file = File::create(&args.output)?;
-    let out_schema = cleaned_batches[0].schema();
+    let output_file = storage::create_output(&args.output)?;
+    let out_schema = schema::nullable_schema(&cleaned_batches[0].schema());
+    let written_rows: usize = cleaned_batches.iter().map(|batch| batch.num_rows()).sum();
 
-    let props = WriterProperties::builder().build();
-    let mut writer = ArrowWriter::try_new(output_file, out_schema, Some(props))?;
+    let props = WriterProperties::builder().set_compression(compression).build();
+    let mut writer = ArrowWriter::try_new(output_file, Arc::clone(&out_schema), Some(props))?;
 
     for batch in cleaned_batches {
-        writer.write(&batch)?;
+        writer.write(&schema::coerce_batch(&batch, &out_schema)?)?;
     }
 
-    writer.close()?;
+    writer.into_inner()?.finish()?;
+
+    if args.verify_output {
+        storage::verify_output(&args.output, written_rows, &out_schema)?;
+    }
     println!("Cleaning complete!");
 
     Ok(())
 }
 
-fn clean_batch(batch: &RecordBatch, text_columns: &[(usize, String)]) -> Result<RecordBatch> {
+/// How leaked templates are treated
+struct CleanOptions {
+    expand_templates: Vec<String>,
+    language: parser::Language,
+}
+
+fn clean_batch(
+    batch: &RecordBatch,
+    text_columns: &[(usize, String)],
+    suffix: Option<&str>,
+    options: &CleanOptions,
+) -> Result<RecordBatch> {
     let schema = batch.schema();
 
     // Build new column vector
+    let mut new_fields: Vec<Field> = Vec::new();
     let mut new_columns: Vec<ArrayRef> = Vec::new();
+    // Cleaned columns appended after the originals when a suffix is set
+    let mut appended: Vec<(Field, ArrayRef)> = Vec::new();
 
-    for (i, _field) in schema.fields().iter().enumerate() {
+    for (i, field) in schema.fields().iter().enumerate() {
         // Check if this column is a text column to clean
         let is_text_column = text_columns.iter().any(|(idx, _)| *idx == i);
 
         if is_text_column {
-            // Clean this text column
-            let text_array = batch
-                .column(i)
+            // Clean this text column; LargeUtf8/Utf8View columns are read as Utf8
+            let column = match batch.column(i).data_type() {
+                DataType::LargeUtf8 | DataType::Utf8View => cast(batch.column(i), &DataType::Utf8)?,
+                _ => Arc::clone(batch.column(i)),
+            };
+            let text_array = column
                 .as_any()
                 .downcast_ref::<StringArray>()
                 .ok_or_else(|| anyhow::anyhow!("Column {} is not a StringArray", i))?;
 
-            let cleaned = clean_text_array(text_array)?;
-            new_columns.push(cleaned);
+            let cleaned = clean_text_array(text_array, options)?;
+            match suffix {
+                Some(suffix) => {
+                    // Keep the dirty column and add the cleaned one as a new field
+                    new_fields.push(field.as_ref().clone());
+                    new_columns.push(Arc::clone(batch.column(i)));
+                    let name = format!("{}{}", field.name(), suffix);
+                    appended.push((Field::new(name, DataType::Utf8, true), cleaned));
+                }
+                None => {
+                    new_fields.push(field.as_ref().clone().with_data_type(DataType::Utf8));
+                    new_columns.push(cleaned);
+                }
+            }
         } else {
             // Keep other columns as-is
+            new_fields.push(field.as_ref().clone());
             new_columns.push(Arc::clone(batch.column(i)));
         }
     }
 
-    Ok(RecordBatch::try_new(schema, new_columns)?)
+    for (field, column) in appended {
+        new_fields.push(field);
+        new_columns.push(column);
+    }
+
+    let new_schema = Arc::new(Schema::new_with_metadata(new_fields, schema.metadata().clone()));
+    Ok(RecordBatch::try_new(new_schema, new_columns)?)
 }
 
-fn clean_text_array(array: &StringArray) -> Result<ArrayRef> {
+fn clean_text_array(array: &StringArray, options: &CleanOptions) -> Result<ArrayRef> {
     // Process each string in the array
     let cleaned: Vec<Option<String>> = (0..array.len())
         .map(|i| {
             if array.is_null(i) {
                 None
             } else {
-                Some(clean_text(array.value(i)))
+                Some(clean_text(array.value(i), options))
             }
         })
         .collect();
@@ -138,69 +214,12 @@ fn clean_text_array(array: &StringArray) -> Result<ArrayRef> {
     Ok(Arc::new(StringArray::from(cleaned)))
 }
 
-fn clean_text(text: &str) -> String {
-    let mut result = text.to_string();
-
-    // Step 1: Remove templates iteratively (handles nested templates)
-    let simple_template_re = Regex::new(r"\{\{[^{}]*\}\}").unwrap();
-    let max_iterations = 10;
-    let mut prev_len = result.len();
-
-    for _ in 0..max_iterations {
-        result = simple_template_re.replace_all(&result, "").to_string();
-        if result.len() == prev_len {
-            break;
-        }
-        prev_len = result.len();
-    }
-
-    // Step 2: Handle remaining complex templates with bounded quantifier
-    let complex_template_re = Regex::new(r"\{\{[^}]{0,500}\}\}").unwrap();
-    result = complex_template_re.replace_all(&result, "").to_string();
-
-    // Step 3: Clean up orphaned braces
-    let orphan_braces_re = Regex::new(r"[\{\}]").unwrap();
-    result = orphan_braces_re.replace_all(&result, "").to_string();
-
-    // Step 4: Remove image fragments
-    result = remove_image_fragments(&result);
-
-    // Step 5: Clean up multiple consecutive newlines
-    let multi_newline_re = Regex::new(r"\n{3,}").unwrap();
-    result = multi_newline_re.replace_all(&result, "\n\n").to_string();
-
-    result
-}
-
-fn remove_image_fragments(text: &str) -> String {
-    let mut result = text.to_string();
-
-    // Remove [[Файл:...]] and [[File:...]] markup
-    let file_re = Regex::new(r"\[\[(?:Файл|File):[^\]]{0,500}\]\]").unwrap();
-    result = file_re.replace_all(&result, "").to_string();
-
-    // Remove image size/position parameters
-    let image_params_re =
-        Regex::new(r"(?m)^\d+px\|(?:мини|thumb|миниатюра|left|right|center|слева|справа|центр)\|.{0,200}$")
-            .unwrap();
-    let lines: Vec<String> = result
-        .lines()
-        .filter(|line| !image_params_re.is_match(line.trim()))
-        .map(|s| s.to_string())
-        .collect();
-    result = lines.join("\n");
-
-    // Remove standalone image parameter fragments
-    let fragment_patterns = vec![
-        r"(?m)^\s*\d+px\|мини\|(?:слева|справа|центр)?.{0,200}$",
-        r"(?m)^\s*альт=.{0,100}\|мини\|.{0,200}$",
-        r"(?m)^\s*\d+px\|мини$",
-    ];
-
-    for pattern in fragment_patterns {
-        let re = Regex::new(pattern).unwrap();
-        result = re.replace_all(&result, "").to_string();
-    }
+fn clean_text(text: &str, options: &CleanOptions) -> String {
+    // Step 1: Remove leaked templates (balanced, so nesting is handled);
+    // allowlisted ones are expanded to their text. Unmatched braces are left
+    // alone: without a partner there is no telling template from content
+    let result = parser::remove_leaked_templates(text, &options.expand_templates, options.language);
 
-    result
+    // Step 2: Remove image fragments and the runs of blank lines removals leave behind
+    parser::remove_image_fragments(&result)
 }
@@ -0,0 +1,238 @@
+//! Command-line arguments shared by the `wikitext_parser` and `parse_single` binaries
+
+use crate::parser::{self, ParseOptions};
+use arrow::array::{ArrayRef, StringArray};
+use std::sync::Arc;
+
+/// Parse options, flattened into both binaries' arguments
+#[derive(clap::Args, Debug)]
+pub struct ParseArgs {
+    /// Skip lists (remove all bullet/numbered lists from output); same as --list-style skip
+    #[arg(long, default_value_t = false, conflicts_with = "list_style")]
+    pub skip_lists: bool,
+
+    /// How lists are rendered: flatten (into the paragraph), markdown (one item per line) or skip
+    #[arg(long, value_enum, default_value_t = parser::ListStyle::Flatten)]
+    pub list_style: parser::ListStyle,
+
+    /// How headings are rendered: plain (text only) or markdown ("## Section", "### Subsection")
+    #[arg(long, value_enum, default_value_t = parser::HeadingStyle::Plain)]
+    pub heading_style: parser::HeadingStyle,
+
+    /// How tables are rendered: skip, text (one tab-separated line per row) or markdown (pipe table)
+    #[arg(long, value_enum, default_value_t = parser::TableStyle::Skip)]
+    pub tables: parser::TableStyle,
+
+    /// What happens to <ref> citations: drop, inline (text kept in place) or footnote (numbered, appended at the end)
+    #[arg(long, value_enum, default_value_t = parser::RefStyle::Drop)]
+    pub keep_refs: parser::RefStyle,
+
+    /// What internal links become: text (display text), target (linked article title) or wiki ("[[target|text]]")
+    #[arg(long, value_enum, default_value_t = parser::LinkStyle::Text)]
+    pub links: parser::LinkStyle,
+
+    /// Namespaces whose links are dropped with their text, comma-separated
+    /// (default: Категория/Category, Файл/File, Шаблон/Template, Справка/Help, wikt, ...; pass "" to disable)
+    #[arg(long, value_delimiter = ',')]
+    pub drop_link_namespaces: Option<Vec<String>>,
+
+    /// Decode &nbsp; as a non-breaking space (U+00A0) instead of a regular space
+    #[arg(long, default_value_t = false)]
+    pub keep_nbsp: bool,
+
+    /// Treat <br> as a line break and <p> as a paragraph break (pass `--html-breaks false` to drop the tags)
+    #[arg(long, default_value_t = true, action = clap::ArgAction::Set)]
+    pub html_breaks: bool,
+
+    /// Keep <poem> line breaks and prefix <blockquote> lines with "> "
+    #[arg(long, default_value_t = false)]
+    pub block_formatting: bool,
+
+    /// Prefix the text of {{lang|en|...}} templates with their language code ("[en] Hello")
+    #[arg(long, default_value_t = false)]
+    pub lang_codes: bool,
+
+    /// Abort an article once its extracted text exceeds this many bytes (0 = no limit)
+    #[arg(long, default_value_t = 0)]
+    pub max_output_bytes: usize,
+
+    /// Truncate each article's text to this many characters, cutting at a paragraph,
+    /// sentence or word boundary and appending "…" (0 = no limit)
+    #[arg(long, default_value_t = 0)]
+    pub max_output_chars: usize,
+
+    /// Drop paragraphs shorter than N characters after cleanup, headings included (0 = keep all)
+    #[arg(long, default_value_t = 0)]
+    pub min_paragraph_chars: usize,
+
+    /// Collapse consecutive identical heading paragraphs into one
+    #[arg(long, default_value_t = false)]
+    pub collapse_repeated_headings: bool,
+
+    /// Drop repeated paragraphs within an article, keeping the first occurrence
+    #[arg(long, default_value_t = false)]
+    pub dedup_paragraphs: bool,
+
+    /// Like --dedup-paragraphs, but paragraphs differing only in whitespace also count as repeats
+    #[arg(long, default_value_t = false)]
+    pub dedup_fuzzy: bool,
+
+    /// Normalize standalone punctuation dashes to the given form
+    #[arg(long, value_enum)]
+    pub normalize_dashes: Option<parser::DashStyle>,
+
+    /// Collapse doubled spaces, tabs and non-breaking spaces inside lines and trim line ends
+    /// (paragraph breaks are kept; non-breaking spaces become regular ones)
+    #[arg(long, default_value_t = false, conflicts_with = "keep_nbsp")]
+    pub normalize_whitespace: bool,
+
+    /// Unicode normalization of the final text: none, nfc or nfkc (also folds ligatures and full-width forms)
+    #[arg(long, value_enum, default_value_t = parser::UnicodeForm::None)]
+    pub normalize_unicode: parser::UnicodeForm,
+
+    /// Lowercase the final text
+    #[arg(long, default_value_t = false)]
+    pub lowercase: bool,
+
+    /// Template names to drop entirely, comma-separated (`*` wildcard at either end;
+    /// default: common ruwiki maintenance templates; pass "" to disable)
+    #[arg(long, value_delimiter = ',')]
+    pub drop_templates: Option<Vec<String>>,
+
+    /// Template names expanded from their arguments, comma-separated (default: date, num,
+    /// convert and nowrap templates; pass "" to disable)
+    #[arg(long, value_delimiter = ',')]
+    pub expand_templates: Option<Vec<String>>,
+
+    /// Keep only the lead and the first N top-level sections (level-2 headings)
+    #[arg(long)]
+    pub sections: Option<usize>,
+
+    /// Structural section headings removed when nothing follows them, comma-separated
+    /// (default: Russian names such as "Примечания", "Ссылки"; pass "" to disable)
+    #[arg(long, value_delimiter = ',')]
+    pub section_names: Option<Vec<String>>,
+
+    /// Prefixes of leaked category lines, comma-separated (default: "Категория:"; pass "" to disable)
+    #[arg(long, value_delimiter = ',')]
+    pub category_prefixes: Option<Vec<String>>,
+
+    /// Wiki language edition (ru, en, de): category/file namespaces for the parser and month names in expanded dates
+    #[arg(long, visible_alias = "wiki-lang", value_enum, default_value_t = parser::Language::Ru)]
+    pub language: parser::Language,
+
+    /// Remove a leading disambiguation sentence ("X — ...; может означать:") and its list
+    #[arg(long, default_value_t = false)]
+    pub trim_leading_disambiguation: bool,
+
+    /// Marker phrases for --trim-leading-disambiguation, comma-separated
+    /// (default: "может означать", "может относиться")
+    #[arg(long, value_delimiter = ',')]
+    pub disambiguation_markers: Option<Vec<String>>,
+}
+
+/// Metadata columns extracted from the raw wikitext, flattened into both binaries' arguments
+#[derive(clap::Args, Debug)]
+pub struct ExtractArgs {
+    /// Add a `sortkey` column (`official_sortkey`/`clone_sortkey` in the two-text binary)
+    /// from {{DEFAULTSORT:...}} / {{сортировка|...}}
+    #[arg(long, default_value_t = false)]
+    pub extract_sortkey: bool,
+
+    /// Add a `display_title` column (`official_display_title`/`clone_display_title` in the two-text binary)
+    /// from {{DISPLAYTITLE:...}}, the title the page shows if it differs
+    #[arg(long, default_value_t = false)]
+    pub extract_display_title: bool,
+
+    /// Add a `categories` column (`official_categories`/`clone_categories` in the two-text binary)
+    /// listing the page's categories ([[Категория:...]]), one per line
+    #[arg(long, default_value_t = false)]
+    pub extract_categories: bool,
+
+    /// Add a `redirect_target` column (`official_redirect_target`/`clone_redirect_target` in the two-text binary)
+    /// with the target title of redirect pages (#REDIRECT [[...]])
+    #[arg(long, default_value_t = false)]
+    pub extract_redirect: bool,
+}
+
+/// A metadata column: its name suffix, the flag requesting it and its extractor
+type ExtractedColumn = (&'static str, fn(&ExtractArgs) -> bool, fn(&str) -> Option<String>);
+
+const EXTRACTED_COLUMNS: [ExtractedColumn; 4] = [
+    ("sortkey", |args| args.extract_sortkey, parser::extract_sortkey),
+    ("display_title", |args| args.extract_display_title, parser::extract_display_title),
+    ("categories", |args| args.extract_categories, parser::extract_categories),
+    ("redirect_target", |args| args.extract_redirect, parser::extract_redirect_target),
+];
+
+impl ExtractArgs {
+    /// Name suffixes of the requested metadata columns, in output order
+    pub fn suffixes(&self) -> Vec<&'static str> {
+        EXTRACTED_COLUMNS.iter().filter(|(_, requested, _)| requested(self)).map(|(suffix, _, _)| *suffix).collect()
+    }
+
+    /// The requested metadata columns of `inputs` with their name suffixes, in output order
+    pub fn columns(&self, inputs: &[Option<&str>]) -> Vec<(&'static str, ArrayRef)> {
+        EXTRACTED_COLUMNS
+            .iter()
+            .filter(|(_, requested, _)| requested(self))
+            .map(|(suffix, _, extract)| (*suffix, extracted_array(inputs, *extract)))
+            .collect()
+    }
+}
+
+/// Build a metadata column (sort key, display title, categories, redirect target) from the raw wikitext
+/// with `extract` (null where the article declares none)
+pub fn extracted_array(inputs: &[Option<&str>], extract: fn(&str) -> Option<String>) -> ArrayRef {
+    Arc::new(StringArray::from(inputs.iter().map(|text| text.and_then(extract)).collect::<Vec<_>>()))
+}
+
+impl From<&ParseArgs> for ParseOptions {
+    fn from(args: &ParseArgs) -> Self {
+        ParseOptions {
+            list_style: if args.skip_lists { parser::ListStyle::Skip } else { args.list_style },
+            heading_style: args.heading_style,
+            tables: args.tables,
+            keep_refs: args.keep_refs,
+            links: args.links,
+            drop_link_namespaces: list_or_default(&args.drop_link_namespaces, parser::DEFAULT_DROP_LINK_NAMESPACES),
+            keep_nbsp: args.keep_nbsp,
+            html_breaks: args.html_breaks,
+            block_formatting: args.block_formatting,
+            lang_codes: args.lang_codes,
+            max_output_bytes: args.max_output_bytes,
+            max_output_chars: args.max_output_chars,
+            min_paragraph_chars: args.min_paragraph_chars,
+            collapse_repeated_headings: args.collapse_repeated_headings,
+            dedup_paragraphs: match (args.dedup_paragraphs, args.dedup_fuzzy) {
+                (_, true) => Some(parser::ParagraphDedup::Whitespace),
+                (true, false) => Some(parser::ParagraphDedup::Exact),
+                (false, false) => None,
+            },
+            normalize_dashes: args.normalize_dashes,
+            normalize_whitespace: args.normalize_whitespace,
+            normalize_unicode: args.normalize_unicode,
+            lowercase: args.lowercase,
+            drop_templates: list_or_default(&args.drop_templates, parser::DEFAULT_DROP_TEMPLATES),
+            expand_templates: list_or_default(&args.expand_templates, parser::DEFAULT_EXPAND_TEMPLATES),
+            disambiguation_markers: if args.trim_leading_disambiguation {
+                list_or_default(&args.disambiguation_markers, parser::DEFAULT_DISAMBIGUATION_MARKERS)
+            } else {
+                Vec::new()
+            },
+            max_sections: args.sections,
+            section_names: list_or_default(&args.section_names, parser::DEFAULT_SECTION_NAMES),
+            category_prefixes: list_or_default(&args.category_prefixes, parser::DEFAULT_CATEGORY_PREFIXES),
+            language: args.language,
+        }
+    }
+}
+
+/// A comma-separated list flag: the given values without empty entries (so "" disables the
+/// list), or the defaults when the flag is absent
+fn list_or_default(values: &Option<Vec<String>>, defaults: &[&str]) -> Vec<String> {
+    match values {
+        Some(values) => values.iter().filter(|v| !v.is_empty()).cloned().collect(),
+        None => defaults.iter().map(|v| v.to_string()).collect(),
+    }
+}
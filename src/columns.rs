@@ -0,0 +1,258 @@
+//! Input column detection shared by the binaries
+//!
+//! Each role (text, page ID, title, timestamp) is found by an exact candidate
+//! name first, then by keywords contained in the column name, unless the user
+//! names the column explicitly.
+
+use anyhow::Result;
+use arrow::array::{ArrayRef, AsArray, StringArray};
+use arrow::compute::cast;
+use arrow::datatypes::{DataType, Schema};
+use std::collections::HashSet;
+
+/// Find a column by exact candidate names in priority order, then the first
+/// column whose lowercased name contains every keyword (no keywords = no fallback)
+pub fn detect_column(schema: &Schema, candidates: &[&str], keywords: &[&str]) -> Option<String> {
+    for candidate in candidates {
+        if schema.field_with_name(candidate).is_ok() {
+            return Some(candidate.to_string());
+        }
+    }
+
+    if keywords.is_empty() {
+        return None;
+    }
+    schema
+        .fields()
+        .iter()
+        .find(|field| {
+            let name = field.name().to_lowercase();
+            keywords.iter().all(|keyword| name.contains(keyword))
+        })
+        .map(|field| field.name().clone())
+}
+
+/// Use the column named by the user if it exists, otherwise the detected one
+/// `label` describes the column in the error message ("page ID", "title", ...)
+pub fn resolve_column<F>(schema: &Schema, specified: Option<&str>, label: &str, detect: F) -> Result<Option<String>>
+where
+    F: FnOnce(&Schema) -> Option<String>,
+{
+    match specified {
+        Some(col) => {
+            if schema.field_with_name(col).is_err() {
+                anyhow::bail!("Specified {} column '{}' not found in schema", label, col);
+            }
+            Ok(Some(col.to_string()))
+        }
+        None => Ok(detect(schema)),
+    }
+}
+
+/// What a column has to hold, checked up front by `SchemaCheck`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ColumnKind {
+    /// Wikitext: strings, or binary blobs decoded by `--decompress`
+    Text,
+    /// Page IDs and titles: strings or integers (`string_values`)
+    Id,
+    /// `--since` timestamps: Arrow timestamps or RFC 3339 strings
+    Timestamp,
+}
+
+impl ColumnKind {
+    fn accepts(self, data_type: &DataType) -> bool {
+        match self {
+            ColumnKind::Text => matches!(
+                data_type,
+                DataType::Utf8 | DataType::LargeUtf8 | DataType::Utf8View | DataType::Binary | DataType::LargeBinary
+            ),
+            ColumnKind::Id => matches!(
+                data_type,
+                DataType::Utf8
+                    | DataType::LargeUtf8
+                    | DataType::Utf8View
+                    | DataType::Int8
+                    | DataType::Int16
+                    | DataType::Int32
+                    | DataType::Int64
+                    | DataType::UInt8
+                    | DataType::UInt16
+                    | DataType::UInt32
+                    | DataType::UInt64
+            ),
+            ColumnKind::Timestamp => {
+                matches!(data_type, DataType::Timestamp(_, _) | DataType::Utf8 | DataType::LargeUtf8)
+            }
+        }
+    }
+
+    fn expected(self) -> &'static str {
+        match self {
+            ColumnKind::Text => "a string or binary column",
+            ColumnKind::Id => "a string or integer column",
+            ColumnKind::Timestamp => "a timestamp or string column",
+        }
+    }
+}
+
+/// Every column problem of a run, collected before any batch is read so they
+/// are reported together, with the columns the input does have
+pub struct SchemaCheck<'a> {
+    schema: &'a Schema,
+    problems: Vec<String>,
+    /// Roles (and role/column pairs) already reported, so a column is never reported twice
+    reported: HashSet<String>,
+}
+
+impl<'a> SchemaCheck<'a> {
+    pub fn new(schema: &'a Schema) -> Self {
+        SchemaCheck { schema, problems: Vec::new(), reported: HashSet::new() }
+    }
+
+    fn report(&mut self, key: String, problem: String) {
+        if self.reported.insert(key) {
+            self.problems.push(problem);
+        }
+    }
+
+    /// `resolve_column`, recording a named column that does not exist instead of failing
+    pub fn resolve<F>(&mut self, specified: Option<&str>, label: &str, detect: F) -> Option<String>
+    where
+        F: FnOnce(&Schema) -> Option<String>,
+    {
+        match specified {
+            Some(col) if self.schema.field_with_name(col).is_err() => {
+                self.report(label.to_string(), format!("{} column '{}' not found", label, col));
+                None
+            }
+            Some(col) => Some(col.to_string()),
+            None => detect(self.schema),
+        }
+    }
+
+    /// Record a column that is needed but was not found; `flag` names the option setting it
+    pub fn require(&mut self, column: Option<&str>, label: &str, flag: &str) {
+        if column.is_none() {
+            self.report(label.to_string(), format!("{} column could not be auto-detected (use {})", label, flag));
+        }
+    }
+
+    /// Record a named column that does not exist
+    pub fn require_existing(&mut self, column: &str, label: &str) {
+        if self.schema.field_with_name(column).is_err() {
+            self.report(format!("{}:{}", label, column), format!("{} column '{}' not found", label, column));
+        }
+    }
+
+    /// Record a copied column whose name is already taken by one of the run's output columns
+    pub fn require_unique(&mut self, column: &str, label: &str, output_columns: &[String]) {
+        if output_columns.iter().any(|output| output == column) {
+            self.report(
+                format!("{}:{}:output", label, column),
+                format!("{} column '{}' would duplicate an output column", label, column),
+            );
+        }
+    }
+
+    /// Record a column that is missing or holds the wrong kind of data
+    pub fn check_type(&mut self, column: Option<&str>, label: &str, kind: ColumnKind) {
+        let Some(column) = column else {
+            return;
+        };
+        let problem = match self.schema.field_with_name(column) {
+            Err(_) => format!("{} column '{}' not found", label, column),
+            Ok(field) if !kind.accepts(field.data_type()) => {
+                format!("{} column '{}' has type {}, expected {}", label, column, field.data_type(), kind.expected())
+            }
+            Ok(_) => return,
+        };
+        self.report(format!("{}:{}", label, column), problem);
+    }
+
+    /// One error listing every recorded problem and the available columns, if there are any
+    pub fn finish(self) -> Result<()> {
+        if self.problems.is_empty() {
+            return Ok(());
+        }
+        let available: Vec<String> = self
+            .schema
+            .fields()
+            .iter()
+            .map(|field| format!("{} ({})", field.name(), field.data_type()))
+            .collect();
+        anyhow::bail!(
+            "Input schema does not fit this run:\n  - {}\nAvailable columns: {}",
+            self.problems.join("\n  - "),
+            available.join(", ")
+        )
+    }
+}
+
+/// Detect the text column of a single-text input
+/// Priority order: text, content, official_text, clone_text, then any column with "text" in its name
+pub fn detect_text_column(schema: &Schema) -> Option<String> {
+    detect_column(schema, &["text", "content", "official_text", "clone_text"], &["text"])
+}
+
+/// Detect the page ID column
+pub fn detect_pageid_column(schema: &Schema) -> Option<String> {
+    detect_column(schema, &["page_id", "pageid"], &[])
+}
+
+/// Detect the title column
+pub fn detect_title_column(schema: &Schema) -> Option<String> {
+    detect_column(schema, &["page_title", "title"], &[])
+}
+
+/// Detect the timestamp column of a single-text input
+pub fn detect_timestamp_column(schema: &Schema) -> Option<String> {
+    detect_column(schema, &["timestamp", "official_timestamp"], &[])
+}
+
+/// Detect the official (Wikipedia) text column of a dual-text input
+pub fn detect_official_text_column(schema: &Schema) -> Option<String> {
+    detect_column(schema, &["official_text"], &["official", "text"])
+}
+
+/// Detect the clone (Ruwiki) text column of a dual-text input
+pub fn detect_clone_text_column(schema: &Schema) -> Option<String> {
+    detect_column(schema, &["clone_text"], &["clone", "text"])
+}
+
+/// Detect the official timestamp column of a dual-text input
+pub fn detect_official_timestamp_column(schema: &Schema) -> Option<String> {
+    detect_column(schema, &["official_timestamp"], &["official", "timestamp"])
+}
+
+/// Detect the clone title column of a dual-text input
+pub fn detect_clone_title_column(schema: &Schema) -> Option<String> {
+    detect_column(schema, &["clone_page_title", "clone_title"], &["clone", "title"])
+}
+
+/// Detect the clone timestamp column of a dual-text input
+pub fn detect_clone_timestamp_column(schema: &Schema) -> Option<String> {
+    detect_column(schema, &["clone_timestamp"], &["clone", "timestamp"])
+}
+
+/// An ID or title column as strings for labels, file names and comparisons
+/// Integer columns (many ruwiki dumps store page IDs as Int64) are cast to their decimal form
+pub fn string_values(column: &ArrayRef, name: &str) -> Result<StringArray> {
+    match column.data_type() {
+        DataType::Utf8 => Ok(column.as_string::<i32>().clone()),
+        data_type if ColumnKind::Id.accepts(data_type) => Ok(cast(column, &DataType::Utf8)?.as_string::<i32>().clone()),
+        other => anyhow::bail!("Column '{}' has type {}, expected {}", name, other, ColumnKind::Id.expected()),
+    }
+}
+
+/// Parsed text columns of a parsing binary's output: names ending in `_parsed`
+/// (`parse_single`) or `_paragraphs` (`wikitext_parser_rust`), in schema order
+pub fn detect_parsed_text_columns(schema: &Schema) -> Vec<String> {
+    schema
+        .fields()
+        .iter()
+        .map(|field| field.name())
+        .filter(|name| name.ends_with("_parsed") || name.ends_with("_paragraphs"))
+        .cloned()
+        .collect()
+}
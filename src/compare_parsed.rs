@@ -0,0 +1,215 @@
+//! Compare two parsed parquet files page by page
+//!
+//! Input: Two outputs of `wikitext_parser_rust`, `parse_single` or
+//! `clean_parsed` (e.g. before and after a parser change)
+//! Output: A unified diff of every text column that changed per page, the
+//! IDs of pages only one file has, and counts of changed/added/removed rows
+//!
+//! Both files are held in memory, keyed by page ID.
+
+use anyhow::Result;
+use arrow::array::{Array, AsArray};
+use arrow::compute::cast;
+use arrow::datatypes::{DataType, Schema};
+use clap::Parser as ClapParser;
+use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+use std::collections::HashMap;
+use wikitext_parser::{columns, diff, storage};
+
+#[derive(ClapParser, Debug)]
+#[command(author, version, about = "Report pages whose parsed text differs between two parquet files", long_about = None)]
+struct Args {
+    /// Parsed parquet file of the earlier run
+    old: String,
+
+    /// Parsed parquet file of the later run
+    new: String,
+
+    /// Text columns to compare, comma-separated
+    /// (auto-detected: columns ending in _parsed or _paragraphs present in both files)
+    #[arg(long, value_delimiter = ',')]
+    text_columns: Option<Vec<String>>,
+
+    /// Page ID column joining the files (auto-detected: page_id, pageid)
+    #[arg(long)]
+    page_id_column: Option<String>,
+
+    /// Unchanged lines shown around each change
+    #[arg(long, default_value_t = 3)]
+    context: usize,
+
+    /// Show diffs of at most this many changed pages (0 = all)
+    #[arg(long, default_value_t = 50)]
+    max_pages: usize,
+
+    /// Print only the counts and added/removed page IDs, no diffs
+    #[arg(long, default_value_t = false)]
+    summary_only: bool,
+}
+
+/// Texts of one file: page ID → one value per compared column, plus file order
+struct ParsedFile {
+    texts: HashMap<String, Vec<Option<String>>>,
+    order: Vec<String>,
+    duplicates: usize,
+}
+
+fn main() -> Result<()> {
+    let args = Args::parse();
+
+    let old_schema = read_schema(&args.old)?;
+    let new_schema = read_schema(&args.new)?;
+    let text_columns = match &args.text_columns {
+        Some(cols) => cols.iter().filter(|col| !col.is_empty()).cloned().collect(),
+        None => {
+            let new_columns = columns::detect_parsed_text_columns(&new_schema);
+            columns::detect_parsed_text_columns(&old_schema)
+                .into_iter()
+                .filter(|col| new_columns.contains(col))
+                .collect::<Vec<_>>()
+        }
+    };
+    if text_columns.is_empty() {
+        anyhow::bail!("No text columns found in both files (columns ending with _parsed or _paragraphs); use --text-columns");
+    }
+    let pageid_column = match &args.page_id_column {
+        Some(col) => col.clone(),
+        None => columns::detect_pageid_column(&old_schema)
+            .ok_or_else(|| anyhow::anyhow!("Could not auto-detect the page ID column. Use --page-id-column to specify."))?,
+    };
+    for (path, schema) in [(&args.old, &old_schema), (&args.new, &new_schema)] {
+        for col in text_columns.iter().chain([&pageid_column]) {
+            if schema.field_with_name(col).is_err() {
+                anyhow::bail!("Column '{}' not found in {}", col, path);
+            }
+        }
+    }
+
+    println!("Old:          {}", args.old);
+    println!("New:          {}", args.new);
+    println!("Text columns: {}", text_columns.join(", "));
+    println!();
+
+    let old = read_texts(&args.old, &pageid_column, &text_columns)?;
+    let new = read_texts(&args.new, &pageid_column, &text_columns)?;
+    for (path, file) in [(&args.old, &old), (&args.new, &new)] {
+        if file.duplicates > 0 {
+            println!("Warning: {} duplicate page IDs in {}; the first row of each is compared", file.duplicates, path);
+        }
+    }
+
+    let mut changed = 0;
+    let mut unchanged = 0;
+    let mut shown = 0;
+    for id in &new.order {
+        let Some(old_texts) = old.texts.get(id) else {
+            continue;
+        };
+        let new_texts = &new.texts[id];
+        if old_texts == new_texts {
+            unchanged += 1;
+            continue;
+        }
+        changed += 1;
+        if args.summary_only || (args.max_pages > 0 && shown >= args.max_pages) {
+            continue;
+        }
+        shown += 1;
+        for ((col, old_text), new_text) in text_columns.iter().zip(old_texts).zip(new_texts) {
+            if old_text == new_text {
+                continue;
+            }
+            println!("=== page {} ({}) ===", id, col);
+            match (old_text, new_text) {
+                (Some(old_text), Some(new_text)) => {
+                    println!("--- old");
+                    println!("+++ new");
+                    print!("{}", diff::unified_diff(old_text, new_text, args.context));
+                }
+                (None, _) => println!("old value is null"),
+                (_, None) => println!("new value is null"),
+            }
+            println!();
+        }
+    }
+    if shown < changed && !args.summary_only {
+        println!("... {} more changed pages not shown (--max-pages)", changed - shown);
+        println!();
+    }
+
+    let added: Vec<&String> = new.order.iter().filter(|id| !old.texts.contains_key(*id)).collect();
+    let removed: Vec<&String> = old.order.iter().filter(|id| !new.texts.contains_key(*id)).collect();
+    print_ids("Added pages (only in new)", &added);
+    print_ids("Removed pages (only in old)", &removed);
+
+    println!("=================================================");
+    println!("Changed:   {}", changed);
+    println!("Unchanged: {}", unchanged);
+    println!("Added:     {}", added.len());
+    println!("Removed:   {}", removed.len());
+    println!("=================================================");
+
+    Ok(())
+}
+
+fn read_schema(path: &str) -> Result<Schema> {
+    let builder = ParquetRecordBatchReaderBuilder::try_new(storage::open_input(path)?)?;
+    Ok(builder.schema().as_ref().clone())
+}
+
+/// Read the page IDs and text columns of one file
+fn read_texts(path: &str, pageid_column: &str, text_columns: &[String]) -> Result<ParsedFile> {
+    let mut file = ParsedFile { texts: HashMap::new(), order: Vec::new(), duplicates: 0 };
+    let reader = ParquetRecordBatchReaderBuilder::try_new(storage::open_input(path)?)?.build()?;
+    for batch in reader {
+        let batch = batch?;
+        let ids = batch
+            .column_by_name(pageid_column)
+            .ok_or_else(|| anyhow::anyhow!("{} column not found in {}", pageid_column, path))?;
+        let ids = columns::string_values(ids, pageid_column)?;
+        let texts = text_columns
+            .iter()
+            .map(|col| {
+                let column = batch
+                    .column_by_name(col)
+                    .ok_or_else(|| anyhow::anyhow!("{} column not found in {}", col, path))?;
+                Ok(cast(column, &DataType::Utf8)?.as_string::<i32>().clone())
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        for row in 0..batch.num_rows() {
+            // Rows without an ID cannot be joined
+            if ids.is_null(row) {
+                continue;
+            }
+            let id = ids.value(row).to_string();
+            if file.texts.contains_key(&id) {
+                file.duplicates += 1;
+                continue;
+            }
+            let values = texts
+                .iter()
+                .map(|texts| texts.is_valid(row).then(|| texts.value(row).to_string()))
+                .collect();
+            file.texts.insert(id.clone(), values);
+            file.order.push(id);
+        }
+    }
+    Ok(file)
+}
+
+/// Up to `MAX_LISTED_IDS` IDs under a heading
+fn print_ids(heading: &str, ids: &[&String]) {
+    const MAX_LISTED_IDS: usize = 20;
+    if ids.is_empty() {
+        return;
+    }
+    println!("{}: {}", heading, ids.len());
+    for id in ids.iter().take(MAX_LISTED_IDS) {
+        println!("  {}", id);
+    }
+    if ids.len() > MAX_LISTED_IDS {
+        println!("  ... and {} more", ids.len() - MAX_LISTED_IDS);
+    }
+    println!();
+}
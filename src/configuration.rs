@@ -0,0 +1,102 @@
+//! Per-language `parse_wiki_text` configurations
+//!
+//! `Configuration::default()` only knows English Wikipedia's namespaces, so
+//! `[[Категория:...]]` and `[[Файл:...]]` would come out of the parser as
+//! ordinary links. Each language edition gets its own namespace aliases,
+//! magic words, redirect words and link trail (the letters glued onto a link,
+//! as in `[[город]]а`), always alongside the English names, which every
+//! edition accepts.
+
+use std::sync::OnceLock;
+
+use parse_wiki_text::{Configuration, ConfigurationSource};
+
+use crate::parser::Language;
+
+/// Extension tags of a standard Wikipedia install (same on every edition)
+const EXTENSION_TAGS: &[&str] = &[
+    "categorytree", "ce", "charinsert", "chem", "gallery", "graph", "hiero", "imagemap", "indicator",
+    "inputbox", "mapframe", "maplink", "math", "nowiki", "poem", "pre", "ref", "references", "score",
+    "section", "source", "syntaxhighlight", "templatedata", "timeline",
+];
+
+/// URL schemes recognized in external links
+const PROTOCOLS: &[&str] = &[
+    "//", "bitcoin:", "ftp://", "ftps://", "geo:", "git://", "gopher://", "http://", "https://", "irc://",
+    "ircs://", "magnet:", "mailto:", "mms://", "news:", "nntp://", "redis://", "sftp://", "sip:", "sips:",
+    "sms:", "ssh://", "svn://", "tel:", "telnet://", "urn:", "worldwind://", "xmpp:",
+];
+
+/// English behavior switches (`__NOTOC__` etc.)
+const MAGIC_WORDS: &[&str] = &[
+    "DISAMBIG", "EXPECTUNUSEDCATEGORY", "FORCETOC", "HIDDENCAT", "INDEX", "NEWSECTIONLINK", "NOCC",
+    "NOCOLLABORATIONHUBTOC", "NOCONTENTCONVERT", "NOEDITSECTION", "NOGALLERY", "NOGLOBAL", "NOINDEX",
+    "NONEWSECTIONLINK", "NOTC", "NOTITLECONVERT", "NOTOC", "STATICREDIRECT", "TOC",
+];
+
+const LATIN_LINK_TRAIL: &str = "abcdefghijklmnopqrstuvwxyz";
+
+/// The parser configuration for a wiki language edition, built once per language
+pub fn configuration(language: Language) -> &'static Configuration {
+    static RU: OnceLock<Configuration> = OnceLock::new();
+    static EN: OnceLock<Configuration> = OnceLock::new();
+    static DE: OnceLock<Configuration> = OnceLock::new();
+
+    let cell = match language {
+        Language::Ru => &RU,
+        Language::En => &EN,
+        Language::De => &DE,
+    };
+    cell.get_or_init(|| build(language))
+}
+
+fn build(language: Language) -> Configuration {
+    // Namespace aliases are lowercase; magic and redirect words keep their canonical case
+    let (category_namespaces, file_namespaces, magic_words, redirect_magic_words, link_trail): (
+        &[&str],
+        &[&str],
+        &[&str],
+        &[&str],
+        String,
+    ) = match language {
+        Language::Ru => (
+            &["категория", "category"],
+            &["файл", "изображение", "file", "image"],
+            &[
+                "БЕЗ_ОГЛАВЛЕНИЯ",
+                "ОГЛАВЛЕНИЕ",
+                "ПРИНУДИТЕЛЬНОЕ_ОГЛАВЛЕНИЕ",
+                "СКРЫТАЯ_КАТЕГОРИЯ",
+                "ИНДЕКСИРОВАТЬ",
+                "НЕ_ИНДЕКСИРОВАТЬ",
+                "БЕЗ_ГАЛЕРЕИ",
+            ],
+            &["ПЕРЕНАПРАВЛЕНИЕ", "ПЕРЕНАПР", "REDIRECT"],
+            format!("{}абвгдеёжзийклмнопрстуфхцчшщъыьэюя", LATIN_LINK_TRAIL),
+        ),
+        Language::En => (&["category"], &["file", "image"], &[], &["REDIRECT"], LATIN_LINK_TRAIL.to_string()),
+        Language::De => (
+            &["kategorie", "category"],
+            &["datei", "bild", "file", "image"],
+            &[
+                "KEIN_INHALTSVERZEICHNIS",
+                "INHALTSVERZEICHNIS",
+                "INHALTSVERZEICHNIS_ERZWINGEN",
+                "VERSTECKTE_KATEGORIE",
+            ],
+            &["WEITERLEITUNG", "REDIRECT"],
+            format!("{}äöüß", LATIN_LINK_TRAIL),
+        ),
+    };
+    let magic_words: Vec<&str> = MAGIC_WORDS.iter().chain(magic_words).copied().collect();
+
+    Configuration::new(&ConfigurationSource {
+        category_namespaces,
+        extension_tags: EXTENSION_TAGS,
+        file_namespaces,
+        link_trail: &link_trail,
+        magic_words: &magic_words,
+        protocols: PROTOCOLS,
+        redirect_magic_words,
+    })
+}
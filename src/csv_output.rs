@@ -0,0 +1,49 @@
+//! CSV output for quick inspection in spreadsheets
+//!
+//! Writes the same page ID, title and parsed text fields as JSON Lines output,
+//! with a header row of their keys. Fields with commas, quotes or the `\n\n`
+//! paragraph breaks are quoted by the `csv` crate; null values are empty fields.
+
+use crate::jsonl;
+use crate::storage::{self, OutputFile};
+use anyhow::Result;
+use arrow::array::{Array, AsArray, RecordBatch};
+use arrow::compute::cast;
+use arrow::datatypes::DataType;
+
+/// CSV writer keeping only the given `(output column, header)` pairs
+pub struct CsvWriter {
+    writer: csv::Writer<OutputFile>,
+    columns: Vec<(String, String)>,
+}
+
+impl CsvWriter {
+    pub fn create(path: &str, columns: &[(String, String)]) -> Result<Self> {
+        let mut writer = csv::Writer::from_writer(storage::create_output(path)?);
+        writer.write_record(columns.iter().map(|(_, key)| key))?;
+        Ok(CsvWriter { writer, columns: columns.to_vec() })
+    }
+
+    pub fn write(&mut self, batch: &RecordBatch) -> Result<()> {
+        let batch = jsonl::select_columns(batch, &self.columns)?;
+        // Non-string columns (integer IDs, chunk indexes) are written as their string form
+        let columns = batch
+            .columns()
+            .iter()
+            .map(|column| cast(column, &DataType::Utf8))
+            .collect::<Result<Vec<_>, _>>()?;
+        let columns: Vec<_> = columns.iter().map(|column| column.as_string::<i32>()).collect();
+
+        for row in 0..batch.num_rows() {
+            let record = columns
+                .iter()
+                .map(|column| if column.is_null(row) { "" } else { column.value(row) });
+            self.writer.write_record(record)?;
+        }
+        Ok(())
+    }
+
+    pub fn finish(self) -> Result<()> {
+        self.writer.into_inner().map_err(|e| e.into_error())?.finish()
+    }
+}
@@ -0,0 +1,162 @@
+//! Decoding of binary text columns (raw, gzip or zstd) into UTF-8 strings
+//!
+//! Some dumps store wikitext as compressed blobs in a Binary/LargeBinary
+//! column. Such columns are converted to Utf8 before parsing; Utf8 columns
+//! pass through untouched. A row that fails to decompress is reported and
+//! becomes null instead of failing the batch. LargeUtf8 and Utf8View columns
+//! (written by recent pandas/polars) are cast to Utf8 here too, so the
+//! parsing code only ever sees `StringArray`s.
+//!
+//! Utf8 columns are validated by the parquet reader, so invalid byte sequences
+//! (mojibake, encoded lone surrogates) can only arrive through binary columns.
+//! What happens to such a row is set by `--on-invalid` (`OnInvalid`).
+
+use anyhow::Result;
+use arrow::array::{Array, ArrayRef, AsArray, RecordBatch, StringArray};
+use arrow::compute::cast;
+use arrow::datatypes::{DataType, Field, Schema};
+use std::io::Read;
+use std::sync::Arc;
+
+/// How values of a binary text column are decoded
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum Decompress {
+    /// Bytes are UTF-8 text as is
+    None,
+    /// Detect gzip or zstd by its magic bytes, otherwise treat as UTF-8
+    #[default]
+    Auto,
+    /// Every value is gzip-compressed
+    Gzip,
+    /// Every value is zstd-compressed
+    Zstd,
+}
+
+/// What happens to a binary text value that isn't valid UTF-8 after decoding
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum OnInvalid {
+    /// Report the row and treat it as a null input
+    #[default]
+    Skip,
+    /// Report the row and parse it with invalid sequences replaced by U+FFFD
+    Replace,
+    /// Stop with an error naming the column and row (failed decompression too)
+    Error,
+}
+
+const GZIP_MAGIC: &[u8] = &[0x1f, 0x8b];
+const ZSTD_MAGIC: &[u8] = &[0x28, 0xb5, 0x2f, 0xfd];
+
+/// Decode one value to a string
+pub fn decode_value(bytes: &[u8], mode: Decompress) -> Result<String> {
+    String::from_utf8(decompress_value(bytes, mode)?)
+        .map_err(|e| anyhow::anyhow!("invalid UTF-8 after decoding: {}", e.utf8_error()))
+}
+
+/// Decompressed bytes of one value
+fn decompress_value(bytes: &[u8], mode: Decompress) -> Result<Vec<u8>> {
+    let mode = match mode {
+        Decompress::Auto if bytes.starts_with(GZIP_MAGIC) => Decompress::Gzip,
+        Decompress::Auto if bytes.starts_with(ZSTD_MAGIC) => Decompress::Zstd,
+        Decompress::Auto => Decompress::None,
+        mode => mode,
+    };
+    Ok(match mode {
+        Decompress::Gzip => {
+            let mut decoded = Vec::new();
+            flate2::read::MultiGzDecoder::new(bytes)
+                .read_to_end(&mut decoded)
+                .map_err(|e| anyhow::anyhow!("gzip: {}", e))?;
+            decoded
+        }
+        Decompress::Zstd => zstd::stream::decode_all(bytes).map_err(|e| anyhow::anyhow!("zstd: {}", e))?,
+        Decompress::None | Decompress::Auto => bytes.to_vec(),
+    })
+}
+
+/// Convert the named binary columns of a batch to Utf8; other columns are kept as they are
+/// Failed rows are reported on stderr with their column and row number and handled per `on_invalid`
+pub fn decode_text_columns(
+    batch: &RecordBatch,
+    columns: &[&str],
+    mode: Decompress,
+    on_invalid: OnInvalid,
+) -> Result<RecordBatch> {
+    let schema = batch.schema();
+    let mut fields = Vec::with_capacity(schema.fields().len());
+    let mut arrays = Vec::with_capacity(batch.num_columns());
+
+    for (field, array) in schema.fields().iter().zip(batch.columns()) {
+        let decoded = if columns.contains(&field.name().as_str()) {
+            decode_column(field.name(), array, mode, on_invalid)?
+        } else {
+            None
+        };
+        match decoded {
+            Some(decoded) => {
+                fields.push(Field::new(field.name(), DataType::Utf8, true).with_metadata(field.metadata().clone()));
+                arrays.push(decoded);
+            }
+            None => {
+                fields.push(field.as_ref().clone());
+                arrays.push(Arc::clone(array));
+            }
+        }
+    }
+
+    let schema = Schema::new_with_metadata(fields, schema.metadata().clone());
+    Ok(RecordBatch::try_new(Arc::new(schema), arrays)?)
+}
+
+/// Decoded Utf8 array for a binary column, cast one for a LargeUtf8/Utf8View
+/// column; None for any other type
+fn decode_column(name: &str, array: &ArrayRef, mode: Decompress, on_invalid: OnInvalid) -> Result<Option<ArrayRef>> {
+    let values: Vec<Option<&[u8]>> = match array.data_type() {
+        DataType::Binary => array.as_binary::<i32>().iter().collect(),
+        DataType::LargeBinary => array.as_binary::<i64>().iter().collect(),
+        DataType::LargeUtf8 | DataType::Utf8View => {
+            let cast = cast(array, &DataType::Utf8).map_err(|e| anyhow::anyhow!("Could not read {} as Utf8: {}", name, e))?;
+            return Ok(Some(cast));
+        }
+        _ => return Ok(None),
+    };
+
+    let decoded: StringArray = values
+        .into_iter()
+        .enumerate()
+        .map(|(row, bytes)| {
+            let Some(bytes) = bytes else {
+                return Ok(None);
+            };
+            let decoded = match decompress_value(bytes, mode) {
+                Ok(decoded) => decoded,
+                Err(e) if on_invalid == OnInvalid::Error => {
+                    anyhow::bail!("Could not decode {} in row {}: {}", name, row + 1, e)
+                }
+                Err(e) => {
+                    eprintln!("WARNING: Could not decode {} in row {}: {}", name, row + 1, e);
+                    return Ok(None);
+                }
+            };
+            let error = match String::from_utf8(decoded) {
+                Ok(text) => return Ok(Some(text)),
+                Err(e) => e,
+            };
+            let message = format!("Invalid UTF-8 in {} row {}: {}", name, row + 1, error.utf8_error());
+            match on_invalid {
+                OnInvalid::Skip => {
+                    eprintln!("WARNING: {}; row skipped", message);
+                    Ok(None)
+                }
+                OnInvalid::Replace => {
+                    eprintln!("WARNING: {}; invalid sequences replaced", message);
+                    Ok(Some(String::from_utf8_lossy(error.as_bytes()).into_owned()))
+                }
+                OnInvalid::Error => anyhow::bail!("{} (--on-invalid error)", message),
+            }
+        })
+        .collect::<Result<_>>()?;
+    Ok(Some(Arc::new(decoded)))
+}
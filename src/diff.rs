@@ -0,0 +1,145 @@
+//! Line diffs of parsed texts in unified-diff style (used by `compare_parsed`)
+//!
+//! Lines common to the start and end of both texts are matched first; the
+//! rest is aligned with a longest-common-subsequence table. When that table
+//! would exceed `MAX_TABLE_CELLS`, the differing middle is reported as
+//! removed and re-added whole instead.
+
+/// Largest LCS table (old lines × new lines) built for one diff
+const MAX_TABLE_CELLS: usize = 4_000_000;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Op {
+    Equal,
+    Delete,
+    Insert,
+}
+
+/// Lines of `old` and `new` in unified-diff form: `@@ -a,b +c,d @@` hunk
+/// headers, then lines prefixed with ' ', '-' or '+', with `context`
+/// unchanged lines around each change. Empty when the texts are equal.
+pub fn unified_diff(old: &str, new: &str, context: usize) -> String {
+    let old_lines: Vec<&str> = old.lines().collect();
+    let new_lines: Vec<&str> = new.lines().collect();
+    let ops = diff_ops(&old_lines, &new_lines);
+
+    // (op, old line index, new line index) before each op
+    let mut positions = Vec::with_capacity(ops.len());
+    let (mut old_at, mut new_at) = (0, 0);
+    for &op in &ops {
+        positions.push((op, old_at, new_at));
+        match op {
+            Op::Equal => {
+                old_at += 1;
+                new_at += 1;
+            }
+            Op::Delete => old_at += 1,
+            Op::Insert => new_at += 1,
+        }
+    }
+
+    let mut out = String::new();
+    let mut i = 0;
+    while i < positions.len() {
+        let Some(first_change) = positions[i..].iter().position(|(op, ..)| *op != Op::Equal).map(|p| p + i) else {
+            break;
+        };
+        // Extend the hunk while the next change is within two contexts
+        let mut last_change = first_change;
+        let mut j = first_change + 1;
+        while j < positions.len() && j <= last_change + 2 * context + 1 {
+            if positions[j].0 != Op::Equal {
+                last_change = j;
+            }
+            j += 1;
+        }
+        let start = first_change.saturating_sub(context).max(i);
+        let end = (last_change + context + 1).min(positions.len());
+        let hunk = &positions[start..end];
+
+        let old_count = hunk.iter().filter(|(op, ..)| *op != Op::Insert).count();
+        let new_count = hunk.iter().filter(|(op, ..)| *op != Op::Delete).count();
+        let (_, old_start, new_start) = hunk[0];
+        out.push_str(&format!(
+            "@@ -{} +{} @@\n",
+            hunk_range(old_start, old_count),
+            hunk_range(new_start, new_count)
+        ));
+        for &(op, old_index, new_index) in hunk {
+            match op {
+                Op::Equal => out.push_str(&format!(" {}\n", old_lines[old_index])),
+                Op::Delete => out.push_str(&format!("-{}\n", old_lines[old_index])),
+                Op::Insert => out.push_str(&format!("+{}\n", new_lines[new_index])),
+            }
+        }
+        i = end;
+    }
+    out
+}
+
+/// `start,count` with 1-based lines; an empty range names the line before it
+fn hunk_range(start: usize, count: usize) -> String {
+    match count {
+        0 => format!("{},0", start),
+        1 => format!("{}", start + 1),
+        _ => format!("{},{}", start + 1, count),
+    }
+}
+
+/// Edit script turning `old` into `new`
+fn diff_ops(old: &[&str], new: &[&str]) -> Vec<Op> {
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let old_middle = &old[prefix..old.len() - suffix];
+    let new_middle = &new[prefix..new.len() - suffix];
+
+    let mut ops = vec![Op::Equal; prefix];
+    if old_middle.len().saturating_mul(new_middle.len()) > MAX_TABLE_CELLS {
+        ops.extend(std::iter::repeat_n(Op::Delete, old_middle.len()));
+        ops.extend(std::iter::repeat_n(Op::Insert, new_middle.len()));
+    } else {
+        ops.extend(lcs_ops(old_middle, new_middle));
+    }
+    ops.extend(std::iter::repeat_n(Op::Equal, suffix));
+    ops
+}
+
+/// Edit script from a longest-common-subsequence table; deletions come before insertions
+fn lcs_ops(old: &[&str], new: &[&str]) -> Vec<Op> {
+    let width = new.len() + 1;
+    // lengths[i * width + j] = LCS length of old[i..] and new[j..]
+    let mut lengths = vec![0u32; (old.len() + 1) * width];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lengths[i * width + j] = if old[i] == new[j] {
+                lengths[(i + 1) * width + j + 1] + 1
+            } else {
+                lengths[(i + 1) * width + j].max(lengths[i * width + j + 1])
+            };
+        }
+    }
+
+    let mut ops = Vec::with_capacity(old.len() + new.len());
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() && j < new.len() {
+        if old[i] == new[j] {
+            ops.push(Op::Equal);
+            i += 1;
+            j += 1;
+        } else if lengths[(i + 1) * width + j] >= lengths[i * width + j + 1] {
+            ops.push(Op::Delete);
+            i += 1;
+        } else {
+            ops.push(Op::Insert);
+            j += 1;
+        }
+    }
+    ops.extend(std::iter::repeat_n(Op::Delete, old.len() - i));
+    ops.extend(std::iter::repeat_n(Op::Insert, new.len() - j));
+    ops
+}
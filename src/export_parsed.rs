@@ -1,169 +1,396 @@
+//! Export parsed text from either parsing binary to text files
+//!
+//! Input: Output parquet of `wikitext_parser_rust` (`*_paragraphs` columns) or
+//! `parse_single` (`*_parsed` columns)
+//! Output: One `<pageid>_<column>.txt` file per row and text column (or
+//! `<title>_<column>.txt` with `--name-by title`), or a single file with all
+//! texts (`--single-file`)
+
 use anyhow::Result;
-use arrow::array::{Array, StringArray};
+use arrow::array::{Array, AsArray, RecordBatch, StringArray};
+use arrow::compute::cast;
+use arrow::datatypes::DataType;
+use clap::Parser as ClapParser;
 use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
-use std::fs::{self, File};
-use std::path::Path;
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use wikitext_parser::{columns, storage};
+
+/// Exported file names, one per line, kept in the output directory
+const MANIFEST_FILE: &str = ".export_manifest";
+
+/// Characters replaced in title-based file names
+const UNSAFE_FILENAME_CHARS: [char; 9] = ['/', '\\', ':', '*', '?', '"', '<', '>', '|'];
+
+/// Longest title part of a file name in bytes, leaving room for the column
+/// and de-duplication suffixes within the usual 255-byte limit
+const MAX_TITLE_BYTES: usize = 150;
+
+/// What the per-row file names start with
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+enum NameBy {
+    /// The page ID
+    Id,
+    /// The sanitized page title (page ID when the title is null)
+    Title,
+}
+
+#[derive(ClapParser, Debug)]
+#[command(author, version, about = "Export parsed text to individual text files or one corpus file", long_about = None)]
+struct Args {
+    /// Parsed parquet file (output of wikitext_parser_rust, parse_single or clean_parsed)
+    #[arg(short, long)]
+    input: String,
+
+    /// Directory receiving `<pageid>_<column>.txt` files and the export manifest
+    #[arg(short, long, default_value = "data/parsed_export")]
+    output_dir: String,
+
+    /// Put one text column's files in another directory, as COLUMN=DIR (repeatable)
+    #[arg(long, value_parser = parse_column_dir)]
+    column_dir: Vec<(String, String)>,
+
+    /// Text columns to export, comma-separated
+    /// (auto-detected: columns ending in _parsed or _paragraphs)
+    #[arg(long, value_delimiter = ',')]
+    text_columns: Option<Vec<String>>,
+
+    /// Page ID column used in file names (auto-detected: page_id, pageid)
+    #[arg(long)]
+    page_id_column: Option<String>,
+
+    /// Title column written to the file header (auto-detected: page_title, title)
+    #[arg(long)]
+    title_column: Option<String>,
+
+    /// Name files by page ID or by sanitized title; title collisions get a numeric suffix
+    #[arg(long, value_enum, default_value_t = NameBy::Id, conflicts_with = "single_file")]
+    name_by: NameBy,
+
+    /// Stream every text into this one file instead of one file per row
+    #[arg(long)]
+    single_file: Option<String>,
+
+    /// Line separating documents in --single-file mode
+    #[arg(long, default_value = "<|doc|>", requires = "single_file")]
+    separator: String,
+
+    /// Re-export every row and overwrite existing files, ignoring the manifest
+    #[arg(long, default_value_t = false, conflicts_with = "single_file")]
+    force: bool,
+}
+
+/// Parse a `--column-dir COLUMN=DIR` value
+fn parse_column_dir(value: &str) -> Result<(String, String), String> {
+    match value.split_once('=') {
+        Some((column, dir)) if !column.is_empty() && !dir.is_empty() => Ok((column.to_string(), dir.to_string())),
+        _ => Err(format!("expected COLUMN=DIR, got '{}'", value)),
+    }
+}
 
 fn main() -> Result<()> {
-    let args: Vec<String> = std::env::args().collect();
-    if args.len() < 2 {
-        eprintln!("Usage: {} <parsed_parquet> [output_dir_official] [output_dir_clone]", args[0]);
-        eprintln!();
-        eprintln!("Exports parsed text to individual files:");
-        eprintln!("  <output_dir_official>/<pageid>_official.txt - Parsed official text");
-        eprintln!("  <output_dir_clone>/<pageid>_clone.txt       - Parsed clone text");
-        eprintln!();
-        eprintln!("If only one output dir is provided, both types go there.");
-        eprintln!();
-        eprintln!("Examples:");
-        eprintln!("  {} input.parquet data/export", args[0]);
-        eprintln!("  {} input.parquet data/official data/clone", args[0]);
-        std::process::exit(1);
-    }
-
-    let parsed_file = &args[1];
-    let output_dir_official = if args.len() > 2 {
-        args[2].clone()
-    } else {
-        "data/parsed_export".to_string()
-    };
-    let output_dir_clone = if args.len() > 3 {
-        args[3].clone()
-    } else {
-        output_dir_official.clone()
-    };
+    let args = Args::parse();
 
     println!("=================================================");
     println!("Parsed Text Export Utility");
     println!("=================================================");
     println!();
-    println!("Input (parsed):         {}", parsed_file);
-    println!("Output dir (official):  {}", output_dir_official);
-    println!("Output dir (clone):     {}", output_dir_clone);
-    println!();
 
-    // Create output directories
-    let output_path_official = Path::new(&output_dir_official);
-    let output_path_clone = Path::new(&output_dir_clone);
-    fs::create_dir_all(&output_path_official)?;
-    fs::create_dir_all(&output_path_clone)?;
+    let file = storage::open_input(&args.input)?;
+    let builder = ParquetRecordBatchReaderBuilder::try_new(file)?;
+    let schema = builder.schema().clone();
 
-    println!("Created directories");
-    println!();
+    let text_columns = match &args.text_columns {
+        Some(cols) => cols.iter().filter(|col| !col.is_empty()).cloned().collect(),
+        None => columns::detect_parsed_text_columns(&schema),
+    };
+    if text_columns.is_empty() {
+        anyhow::bail!(
+            "No text columns found (columns ending with _parsed or _paragraphs); use --text-columns. Available columns: {:?}",
+            schema.fields().iter().map(|f| f.name()).collect::<Vec<_>>()
+        );
+    }
+    for col in &text_columns {
+        if schema.field_with_name(col).is_err() {
+            anyhow::bail!("Specified text column '{}' not found in schema", col);
+        }
+    }
+    for (col, _) in &args.column_dir {
+        if !text_columns.contains(col) {
+            anyhow::bail!("--column-dir names '{}', which is not an exported text column", col);
+        }
+    }
 
-    // Read parsed parquet file
-    println!("Reading parsed file...");
-    let file = File::open(parsed_file)?;
-    let builder = ParquetRecordBatchReaderBuilder::try_new(file)?;
-    let mut reader = builder.build()?;
+    println!("Input (parsed):  {}", args.input);
+    println!("Text columns:    {}", text_columns.join(", "));
+    let reader = builder.build()?;
 
-    let mut batches = Vec::new();
-    while let Some(batch) = reader.next() {
-        batches.push(batch?);
+    if let Some(output_file) = &args.single_file {
+        return export_single_file(reader, &text_columns, output_file, &args.separator);
     }
 
-    if batches.is_empty() {
-        println!("Error: No data found in parquet file");
-        return Ok(());
+    let pageid_column =
+        columns::resolve_column(&schema, args.page_id_column.as_deref(), "page ID", columns::detect_pageid_column)?
+            .ok_or_else(|| anyhow::anyhow!("Could not auto-detect the page ID column. Use --page-id-column to specify."))?;
+    let title_column =
+        columns::resolve_column(&schema, args.title_column.as_deref(), "title", columns::detect_title_column)?;
+    if args.name_by == NameBy::Title && title_column.is_none() {
+        anyhow::bail!("--name-by title needs a title column. Use --title-column to specify.");
     }
 
+    // Output directory of every text column
+    let column_dirs: HashMap<&str, &str> = args.column_dir.iter().map(|(col, dir)| (col.as_str(), dir.as_str())).collect();
+    let output_dirs: Vec<(String, PathBuf)> = text_columns
+        .iter()
+        .map(|col| (col.clone(), PathBuf::from(column_dirs.get(col.as_str()).copied().unwrap_or(&args.output_dir))))
+        .collect();
+    fs::create_dir_all(&args.output_dir)?;
+    for (col, dir) in &output_dirs {
+        fs::create_dir_all(dir)?;
+        println!("Output ({}): {}", col, dir.display());
+    }
     println!();
+
+    // Files exported by earlier runs; without a manifest, seed it from one listing per directory
+    let manifest_path = Path::new(&args.output_dir).join(MANIFEST_FILE);
+    let has_manifest = manifest_path.exists();
+    let exported: HashSet<String> = if args.force {
+        HashSet::new()
+    } else if has_manifest {
+        BufReader::new(File::open(&manifest_path)?).lines().collect::<std::io::Result<_>>()?
+    } else {
+        let mut existing = HashSet::new();
+        for (col, dir) in &output_dirs {
+            existing.extend(list_exported(dir, &format!("_{}.txt", col))?);
+        }
+        existing
+    };
+    if !exported.is_empty() {
+        println!("Resuming: {} files already exported", exported.len());
+    }
+    let mut manifest = BufWriter::new(if args.force {
+        File::create(&manifest_path)?
+    } else {
+        OpenOptions::new().create(true).append(true).open(&manifest_path)?
+    });
+    if !has_manifest {
+        for name in &exported {
+            writeln!(manifest, "{}", name)?;
+        }
+    }
+
     println!("Processing articles...");
     println!();
 
     let mut total_files = 0;
+    // File name stems handed out so far (lowercased, for case-insensitive file systems)
+    let mut used_stems: HashSet<String> = HashSet::new();
+
+    for batch in reader {
+        let batch = batch?;
+        let page_ids = id_column(&batch, &pageid_column)?;
+        let titles = title_column.as_deref().map(|col| id_column(&batch, col)).transpose()?;
+        let texts = text_columns
+            .iter()
+            .map(|col| string_column(&batch, col))
+            .collect::<Result<Vec<_>>>()?;
 
-    // Process each row
-    for batch in batches.iter() {
-        let num_rows = batch.num_rows();
-
-        // Extract columns
-        let page_id = batch
-            .column_by_name("page_id")
-            .ok_or_else(|| anyhow::anyhow!("page_id column not found"))?
-            .as_any()
-            .downcast_ref::<StringArray>()
-            .ok_or_else(|| anyhow::anyhow!("page_id is not a StringArray"))?;
-
-        let page_title = batch
-            .column_by_name("page_title")
-            .ok_or_else(|| anyhow::anyhow!("page_title column not found"))?
-            .as_any()
-            .downcast_ref::<StringArray>()
-            .ok_or_else(|| anyhow::anyhow!("page_title is not a StringArray"))?;
-
-        let official_paragraphs = batch
-            .column_by_name("official_text_paragraphs")
-            .ok_or_else(|| anyhow::anyhow!("official_text_paragraphs column not found"))?
-            .as_any()
-            .downcast_ref::<StringArray>()
-            .ok_or_else(|| anyhow::anyhow!("official_text_paragraphs is not a StringArray"))?;
-
-        let clone_paragraphs = batch
-            .column_by_name("clone_text_paragraphs")
-            .ok_or_else(|| anyhow::anyhow!("clone_text_paragraphs column not found"))?
-            .as_any()
-            .downcast_ref::<StringArray>()
-            .ok_or_else(|| anyhow::anyhow!("clone_text_paragraphs is not a StringArray"))?;
-
-        for row_idx in 0..num_rows {
-            if page_id.is_null(row_idx) {
+        for row_idx in 0..batch.num_rows() {
+            if page_ids.is_null(row_idx) {
                 continue;
             }
 
-            let page_id_val = page_id.value(row_idx);
-            let page_title_val = if page_title.is_null(row_idx) {
-                "untitled"
-            } else {
-                page_title.value(row_idx)
+            let page_id_val = page_ids.value(row_idx);
+            let page_title_val = match &titles {
+                Some(titles) if !titles.is_null(row_idx) => titles.value(row_idx),
+                _ => "untitled",
             };
 
             // Create header with metadata
-            let header = format!(
-                "Page ID: {}\nTitle: {}\n{}\n\n",
-                page_id_val,
-                page_title_val,
-                "=".repeat(60)
-            );
-
-            // Write official text file
-            let official_filename = format!("{}_official.txt", page_id_val);
-            let official_filepath = output_path_official.join(&official_filename);
-            if official_filepath.exists() {
-                // Skip if already exists
-            } else if !official_paragraphs.is_null(row_idx) {
-                let content = format!("{}{}", header, official_paragraphs.value(row_idx));
-                fs::write(&official_filepath, content)?;
-                total_files += 1;
-            }
+            let header = format!("Page ID: {}\nTitle: {}\n{}\n\n", page_id_val, page_title_val, "=".repeat(60));
+
+            // Stems are assigned for every row, exported or not, so a resumed run reproduces them
+            let stem = match (args.name_by, &titles) {
+                (NameBy::Title, Some(titles)) if !titles.is_null(row_idx) => {
+                    unique_stem(sanitize_title(titles.value(row_idx)), &mut used_stems)
+                }
+                _ => page_id_val.to_string(),
+            };
 
-            // Write clone text file
-            let clone_filename = format!("{}_clone.txt", page_id_val);
-            let clone_filepath = output_path_clone.join(&clone_filename);
-            if clone_filepath.exists() {
-                // Skip if already exists
-            } else if !clone_paragraphs.is_null(row_idx) {
-                let content = format!("{}{}", header, clone_paragraphs.value(row_idx));
-                fs::write(&clone_filepath, content)?;
+            let mut wrote = false;
+            for ((col, dir), text) in output_dirs.iter().zip(&texts) {
+                let filename = format!("{}_{}.txt", stem, col);
+                // Skip files a previous run exported
+                if text.is_null(row_idx) || exported.contains(&filename) {
+                    continue;
+                }
+                fs::write(dir.join(&filename), format!("{}{}", header, text.value(row_idx)))?;
+                writeln!(manifest, "{}", filename)?;
                 total_files += 1;
+                wrote = true;
             }
 
-            if !official_filepath.exists() || !clone_filepath.exists() {
+            if wrote {
                 println!("  ✓ Exported: {} - {}", page_id_val, page_title_val);
             }
         }
     }
 
+    manifest.flush()?;
+
     println!();
     println!("=================================================");
     println!("✓ Export complete!");
     println!("=================================================");
     println!();
     println!("Total files created: {}", total_files);
-    println!("Output directories:");
-    println!("  Official: {}", output_dir_official);
-    println!("  Clone:    {}", output_dir_clone);
     println!();
 
     Ok(())
 }
+
+/// A page ID or title column as strings (integer IDs in their decimal form)
+fn id_column(batch: &RecordBatch, name: &str) -> Result<StringArray> {
+    let column = batch
+        .column_by_name(name)
+        .ok_or_else(|| anyhow::anyhow!("{} column not found", name))?;
+    columns::string_values(column, name)
+}
+
+/// A column as Utf8, casting other types
+fn string_column(batch: &RecordBatch, name: &str) -> Result<StringArray> {
+    let column = batch
+        .column_by_name(name)
+        .ok_or_else(|| anyhow::anyhow!("{} column not found", name))?;
+    Ok(cast(column, &DataType::Utf8)?.as_string::<i32>().clone())
+}
+
+/// Make a title usable as a file name: path-hostile characters and control
+/// characters become `_`, leading/trailing dots and spaces are trimmed, and the
+/// result is cut to `MAX_TITLE_BYTES` on a character boundary
+fn sanitize_title(title: &str) -> String {
+    let replaced: String = title
+        .chars()
+        .map(|c| if UNSAFE_FILENAME_CHARS.contains(&c) || c.is_control() { '_' } else { c })
+        .collect();
+    let trimmed = replaced.trim_matches(|c: char| c == '.' || c.is_whitespace());
+
+    let mut end = trimmed.len().min(MAX_TITLE_BYTES);
+    while !trimmed.is_char_boundary(end) {
+        end -= 1;
+    }
+    let truncated = trimmed[..end].trim_end_matches(|c: char| c == '.' || c.is_whitespace());
+    if truncated.is_empty() {
+        "_".to_string()
+    } else {
+        truncated.to_string()
+    }
+}
+
+/// Reserve `stem`, or `stem_2`, `stem_3`, ... if it is already taken
+fn unique_stem(stem: String, used: &mut HashSet<String>) -> String {
+    if used.insert(stem.to_lowercase()) {
+        return stem;
+    }
+    (2..)
+        .map(|n| format!("{}_{}", stem, n))
+        .find(|candidate| used.insert(candidate.to_lowercase()))
+        .expect("some numeric suffix is free")
+}
+
+/// Names of files in `dir` ending with `suffix`, read with a single directory listing
+fn list_exported(dir: &Path, suffix: &str) -> Result<HashSet<String>> {
+    let mut names = HashSet::new();
+    for entry in fs::read_dir(dir)? {
+        if let Some(name) = entry?.file_name().to_str() {
+            if name.ends_with(suffix) {
+                names.insert(name.to_string());
+            }
+        }
+    }
+    Ok(names)
+}
+
+/// Stream every parsed text into one file, separated by `separator` lines
+fn export_single_file(
+    reader: impl Iterator<Item = std::result::Result<RecordBatch, arrow::error::ArrowError>>,
+    text_columns: &[String],
+    output_file: &str,
+    separator: &str,
+) -> Result<()> {
+    println!("Output file:     {}", output_file);
+    println!("Separator:       {}", separator);
+    println!();
+
+    let mut writer = BufWriter::new(storage::create_output(output_file)?);
+    let mut total_docs = 0;
+
+    for batch in reader {
+        let batch = batch?;
+
+        for column in text_columns {
+            let texts = string_column(&batch, column)?;
+            for text in texts.iter().flatten() {
+                if total_docs > 0 {
+                    writeln!(writer, "{}", separator)?;
+                }
+                writeln!(writer, "{}", text)?;
+                total_docs += 1;
+            }
+        }
+    }
+
+    writer.into_inner().map_err(|e| e.into_error())?.finish()?;
+
+    println!("=================================================");
+    println!("✓ Export complete!");
+    println!("=================================================");
+    println!();
+    println!("Total documents written: {}", total_docs);
+    println!("Output file: {}", output_file);
+    println!();
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn titles_lose_path_and_control_characters() {
+        assert_eq!(sanitize_title("AC/DC"), "AC_DC");
+        assert_eq!(sanitize_title("C:\\Windows"), "C__Windows");
+        assert_eq!(sanitize_title("Шаблон:Навигация"), "Шаблон_Навигация");
+        assert_eq!(sanitize_title("Строка\nвторая\t\u{7}"), "Строка_вторая__");
+        assert_eq!(sanitize_title("Что? <Где> \"Когда\"|*"), "Что_ _Где_ _Когда___");
+    }
+
+    #[test]
+    fn dot_only_titles_never_name_a_directory() {
+        assert_eq!(sanitize_title(".."), "_");
+        assert_eq!(sanitize_title(" . "), "_");
+        assert_eq!(sanitize_title(""), "_");
+        assert_eq!(sanitize_title("..Скрытый."), "Скрытый");
+    }
+
+    #[test]
+    fn long_titles_are_cut_on_a_character_boundary() {
+        // One ASCII byte, then two-byte letters: byte MAX_TITLE_BYTES falls inside a letter
+        let title = format!("a{}", "Ж".repeat(MAX_TITLE_BYTES));
+        let sanitized = sanitize_title(&title);
+        assert_eq!(sanitized.len(), MAX_TITLE_BYTES - 1);
+        assert_eq!(sanitized, format!("a{}", "Ж".repeat((MAX_TITLE_BYTES - 2) / 2)));
+    }
+
+    #[test]
+    fn case_insensitive_collisions_get_numeric_suffixes() {
+        let mut used = HashSet::new();
+        assert_eq!(unique_stem("Москва".to_string(), &mut used), "Москва");
+        assert_eq!(unique_stem("МОСКВА".to_string(), &mut used), "МОСКВА_2");
+        assert_eq!(unique_stem("москва".to_string(), &mut used), "москва_3");
+        assert_eq!(unique_stem("Тверь".to_string(), &mut used), "Тверь");
+    }
+}
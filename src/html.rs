@@ -0,0 +1,212 @@
+//! HTML-to-text extraction for dumps that store pre-rendered HTML
+//!
+//! Mirrors the paragraph/heading semantics of the wikitext path: block
+//! elements become paragraphs separated by blank lines, headings become their
+//! own paragraphs, list items are flattened into the current paragraph,
+//! rendered as Markdown lines or dropped according to `list_style`, and
+//! tables, references and navigation boxes are skipped. The result goes
+//! through the same `postprocess_text` cleanup as parsed wikitext.
+
+use crate::parser::{self, HeadingStyle, ListStyle, ParseOptions};
+use ego_tree::NodeRef;
+use scraper::{Html, Node};
+use std::collections::HashSet;
+
+/// Extract plain paragraph text from rendered article HTML
+/// HTML extraction has no output size guard, so it never skips an article
+pub fn parse_html(html: &str, options: &ParseOptions) -> String {
+    let document = Html::parse_fragment(html);
+    let mut extractor = Extractor::new(options.list_style, options.heading_style, options.max_sections);
+    extractor.walk(document.tree.root());
+    let text = extractor.finish();
+
+    parser::postprocess_text(&text, &extractor.headings, options)
+}
+
+/// Check whether a text looks like rendered HTML rather than wikitext
+pub fn looks_like_html(text: &str) -> bool {
+    text.contains("<p>") || text.contains("<p ") || text.contains("<div")
+}
+
+/// Tags whose content is never extracted
+const SKIPPED_TAGS: [&str; 7] = ["table", "script", "style", "figure", "noscript", "math", "head"];
+
+/// Class names marking non-prose blocks (references, navboxes, edit links)
+const SKIPPED_CLASSES: [&str; 7] = [
+    "reference",
+    "references",
+    "reflist",
+    "mw-editsection",
+    "navbox",
+    "infobox",
+    "metadata",
+];
+
+/// Tags that start and end a paragraph
+const BLOCK_TAGS: [&str; 9] = ["p", "div", "section", "blockquote", "pre", "center", "ul", "ol", "dl"];
+
+struct Extractor {
+    list_style: ListStyle,
+    heading_style: HeadingStyle,
+    /// Stop after this many top-level sections (see `ParseOptions::max_sections`)
+    max_sections: Option<usize>,
+    sections: usize,
+    stopped: bool,
+    text: String,
+    current_paragraph: String,
+    headings: HashSet<String>,
+}
+
+impl Extractor {
+    fn new(list_style: ListStyle, heading_style: HeadingStyle, max_sections: Option<usize>) -> Self {
+        Extractor {
+            list_style,
+            heading_style,
+            max_sections,
+            sections: 0,
+            stopped: false,
+            text: String::new(),
+            current_paragraph: String::new(),
+            headings: HashSet::new(),
+        }
+    }
+
+    fn walk(&mut self, node: NodeRef<Node>) {
+        if self.stopped {
+            return;
+        }
+        match node.value() {
+            Node::Text(text) => {
+                // Collapse HTML source whitespace the way a browser would
+                let collapsed = text.split_whitespace().collect::<Vec<_>>().join(" ");
+                let needs_space = !self.current_paragraph.is_empty()
+                    && !self.current_paragraph.ends_with(char::is_whitespace);
+                if text.starts_with(char::is_whitespace) && needs_space {
+                    self.current_paragraph.push(' ');
+                }
+                self.current_paragraph.push_str(&collapsed);
+                if text.ends_with(char::is_whitespace) && !collapsed.is_empty() {
+                    self.current_paragraph.push(' ');
+                }
+            }
+            Node::Element(element) => {
+                let name = element.name();
+                let skipped_class = element
+                    .attr("class")
+                    .map(|classes| classes.split_whitespace().any(|c| SKIPPED_CLASSES.contains(&c)))
+                    .unwrap_or(false);
+                let is_list = matches!(name, "ul" | "ol" | "dl");
+
+                if SKIPPED_TAGS.contains(&name) || skipped_class || (is_list && self.list_style == ListStyle::Skip) {
+                    return;
+                }
+
+                match name {
+                    "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                        let level = name[1..].parse::<u8>().unwrap_or(u8::MAX);
+                        if let Some(max_sections) = self.max_sections {
+                            if level <= parser::TOP_LEVEL_HEADING {
+                                self.sections += 1;
+                                if self.sections > max_sections {
+                                    self.stopped = true;
+                                    return;
+                                }
+                            }
+                        }
+                        let heading = self.extract_children(node);
+                        if !heading.is_empty() {
+                            self.flush_paragraph();
+                            self.text.push_str(&parser::format_heading(level, &heading, self.heading_style));
+                            self.text.push_str("\n\n");
+                            self.headings.insert(heading);
+                        }
+                    }
+                    "li" | "dt" | "dd" => {
+                        let item = self.extract_children(node);
+                        if !item.is_empty() {
+                            self.current_paragraph.push_str(&item);
+                            self.current_paragraph.push(' ');
+                        }
+                    }
+                    "ul" | "ol" | "dl" if self.list_style == ListStyle::Markdown => {
+                        self.flush_paragraph();
+                        let lines = self.markdown_list(node, name == "ol", 0);
+                        if !lines.trim().is_empty() {
+                            self.text.push_str(lines.trim_end());
+                            self.text.push_str("\n\n");
+                        }
+                    }
+                    "br" => self.current_paragraph.push('\n'),
+                    _ if BLOCK_TAGS.contains(&name) && !is_list => {
+                        self.flush_paragraph();
+                        self.walk_children(node);
+                        self.flush_paragraph();
+                    }
+                    _ => self.walk_children(node),
+                }
+            }
+            _ => self.walk_children(node),
+        }
+    }
+
+    fn walk_children(&mut self, node: NodeRef<Node>) {
+        for child in node.children() {
+            self.walk(child);
+        }
+    }
+
+    /// Render a list element's items as Markdown lines, recursing into nested lists
+    fn markdown_list(&mut self, list: NodeRef<Node>, ordered: bool, depth: usize) -> String {
+        let mut lines = String::new();
+        let mut number = 0;
+        for item in list.children() {
+            let Some(element) = item.value().as_element() else {
+                continue;
+            };
+            if !matches!(element.name(), "li" | "dt" | "dd") {
+                continue;
+            }
+            number += 1;
+
+            // Nested lists get their own indented lines after the item's text
+            let mut inner = Extractor::new(self.list_style, self.heading_style, None);
+            let mut nested = String::new();
+            for child in item.children() {
+                match child.value().as_element().map(|e| e.name()) {
+                    Some(name @ ("ul" | "ol" | "dl")) => nested.push_str(&self.markdown_list(child, name == "ol", depth + 1)),
+                    _ => inner.walk(child),
+                }
+            }
+            let item_text = parser::collapse_whitespace(&inner.finish());
+            self.headings.extend(inner.headings);
+
+            if !item_text.is_empty() {
+                lines.push_str(&parser::markdown_list_item(depth, ordered, number, &item_text));
+            }
+            lines.push_str(&nested);
+        }
+        lines
+    }
+
+    /// Extract the children of a node into a standalone trimmed string
+    fn extract_children(&mut self, node: NodeRef<Node>) -> String {
+        let mut inner = Extractor::new(self.list_style, self.heading_style, None);
+        inner.walk_children(node);
+        let text = inner.finish();
+        self.headings.extend(inner.headings);
+        text.trim().to_string()
+    }
+
+    fn flush_paragraph(&mut self) {
+        if !self.current_paragraph.trim().is_empty() {
+            self.text.push_str(self.current_paragraph.trim());
+            self.text.push_str("\n\n");
+        }
+        self.current_paragraph.clear();
+    }
+
+    fn finish(&mut self) -> String {
+        self.flush_paragraph();
+        std::mem::take(&mut self.text)
+    }
+}
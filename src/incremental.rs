@@ -0,0 +1,96 @@
+//! Incremental processing: only reparse rows modified since a given time
+//!
+//! Rows whose timestamp column is older than `--since` are either omitted from
+//! the output or passed through with their source text unparsed. The
+//! timestamp column may be an Arrow Timestamp (any unit, with or without a
+//! time zone) or a string column with RFC 3339 / ISO 8601 timestamps. Rows
+//! with a null or unparseable timestamp are treated as changed.
+
+use crate::parser::ParseResult;
+use anyhow::Result;
+use arrow::array::{Array, ArrayRef, BooleanArray, RecordBatch, TimestampNanosecondArray};
+use arrow::compute::kernels::cast_utils::string_to_timestamp_nanos;
+use arrow::compute::{cast_with_options, filter_record_batch, CastOptions};
+use arrow::datatypes::{DataType, TimeUnit};
+
+/// What to do with rows older than `--since`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum UnchangedAction {
+    /// Leave unchanged rows out of the output
+    Omit,
+    /// Keep unchanged rows with their source text unparsed
+    Passthrough,
+}
+
+/// Parse a `--since` value (RFC 3339 / ISO 8601, e.g. "2024-05-01T00:00:00Z") into UTC nanoseconds
+pub fn parse_since(since: &str) -> Result<i64> {
+    string_to_timestamp_nanos(since).map_err(|e| anyhow::anyhow!("Invalid --since timestamp '{}': {}", since, e))
+}
+
+/// Mark the rows whose timestamp is at or after `since_nanos` (or unknown)
+pub fn changed_rows(timestamps: &ArrayRef, since_nanos: i64) -> Result<BooleanArray> {
+    // Unit conversion keeps the UTC epoch value; strings are parsed as UTC unless they carry an offset
+    let target = match timestamps.data_type() {
+        DataType::Timestamp(_, tz) => DataType::Timestamp(TimeUnit::Nanosecond, tz.clone()),
+        DataType::Utf8 | DataType::LargeUtf8 => DataType::Timestamp(TimeUnit::Nanosecond, None),
+        other => anyhow::bail!("Timestamp column has unsupported type {}", other),
+    };
+    let options = CastOptions { safe: true, ..Default::default() };
+    let nanos = cast_with_options(timestamps, &target, &options)?;
+    let nanos = nanos
+        .as_any()
+        .downcast_ref::<TimestampNanosecondArray>()
+        .ok_or_else(|| anyhow::anyhow!("Timestamp column could not be converted"))?;
+
+    Ok(nanos
+        .iter()
+        .map(|value| Some(value.is_none_or(|value| value >= since_nanos)))
+        .collect())
+}
+
+/// Apply `--since` to a batch
+/// With `Omit`, unchanged rows are filtered out and no mask is returned; with
+/// `Passthrough`, the batch is kept whole and the changed-row mask is returned
+pub fn select_changed(
+    batch: &RecordBatch,
+    column: &str,
+    since_nanos: i64,
+    action: UnchangedAction,
+) -> Result<(RecordBatch, Option<BooleanArray>)> {
+    let timestamps = batch
+        .column_by_name(column)
+        .ok_or_else(|| anyhow::anyhow!("Timestamp column '{}' not found (required by --since)", column))?;
+    let changed = changed_rows(timestamps, since_nanos)?;
+    eprintln!("  {} of {} rows changed since --since", changed.true_count(), batch.num_rows());
+
+    match action {
+        UnchangedAction::Omit => Ok((filter_record_batch(batch, &changed)?, None)),
+        UnchangedAction::Passthrough => Ok((batch.clone(), Some(changed))),
+    }
+}
+
+/// Inputs to send to the parser: rows not marked as changed are masked out
+pub fn inputs_to_parse<'a>(inputs: &[Option<&'a str>], changed: Option<&BooleanArray>) -> Vec<Option<&'a str>> {
+    inputs
+        .iter()
+        .enumerate()
+        .map(|(i, input)| input.filter(|_| changed.is_none_or(|mask| mask.value(i))))
+        .collect()
+}
+
+/// Give rows not marked as changed their source text as the result
+pub fn pass_through_unchanged(
+    results: &mut [Option<ParseResult>],
+    inputs: &[Option<&str>],
+    changed: Option<&BooleanArray>,
+) {
+    let Some(mask) = changed else {
+        return;
+    };
+    for (i, (result, input)) in results.iter_mut().zip(inputs).enumerate() {
+        if !mask.value(i) {
+            *result = input.map(|text| Ok(text.to_string()));
+        }
+    }
+}
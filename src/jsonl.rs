@@ -0,0 +1,158 @@
+//! JSON Lines output for downstream tooling that doesn't read parquet
+//!
+//! Each row becomes one JSON object with only the page ID, title and parsed
+//! text fields, keyed by the input column names they came from. Null texts
+//! are written as explicit `null`s rather than omitted. With
+//! `sections-json` each parsed text is written as a list of
+//! `{heading, level, paragraphs}` objects instead of a string.
+
+use crate::sections;
+use crate::storage::{self, OutputFile};
+use anyhow::Result;
+use arrow::array::{Array, ArrayRef, AsArray, ListArray, RecordBatch, StringArray, StructArray, UInt8Array};
+use arrow::buffer::OffsetBuffer;
+use arrow::datatypes::{DataType, Field, Fields, Schema};
+use arrow::json::writer::{LineDelimited, Writer, WriterBuilder};
+use std::sync::Arc;
+
+/// Output file format
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum OutputFormat {
+    Parquet,
+    /// One JSON object per row (id, title and parsed text fields only)
+    Jsonl,
+    /// The same fields as CSV with a header row
+    Csv,
+    /// Like jsonl, with each parsed text split into sections:
+    /// `[{heading, level, paragraphs: [...]}, ...]`, the lead under a null heading
+    SectionsJson,
+}
+
+impl OutputFormat {
+    /// File extension of outputs named by the binaries (`--output-dir`)
+    pub fn extension(self) -> &'static str {
+        match self {
+            OutputFormat::Parquet => "parquet",
+            OutputFormat::Jsonl | OutputFormat::SectionsJson => "jsonl",
+            OutputFormat::Csv => "csv",
+        }
+    }
+}
+
+/// JSON Lines writer keeping only the given `(output column, JSON key)` pairs
+pub struct JsonlWriter {
+    writer: Writer<OutputFile, LineDelimited>,
+    columns: Vec<(String, String)>,
+    section_columns: Vec<String>,
+}
+
+impl JsonlWriter {
+    pub fn create(path: &str, columns: &[(String, String)]) -> Result<Self> {
+        let writer = WriterBuilder::new()
+            .with_explicit_nulls(true)
+            .build::<_, LineDelimited>(storage::create_output(path)?);
+        Ok(JsonlWriter { writer, columns: columns.to_vec(), section_columns: Vec::new() })
+    }
+
+    /// Write these output columns as section lists (`sections-json`)
+    pub fn with_section_columns(mut self, columns: &[String]) -> Self {
+        self.section_columns = columns.to_vec();
+        self
+    }
+
+    pub fn write(&mut self, batch: &RecordBatch) -> Result<()> {
+        let batch = if self.section_columns.is_empty() {
+            batch.clone()
+        } else {
+            split_section_columns(batch, &self.section_columns)?
+        };
+        self.writer.write(&select_columns(&batch, &self.columns)?)?;
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> Result<()> {
+        self.writer.finish()?;
+        self.writer.into_inner().finish()
+    }
+}
+
+/// Project a batch onto the requested columns, renamed to their JSON keys
+pub(crate) fn select_columns(batch: &RecordBatch, columns: &[(String, String)]) -> Result<RecordBatch> {
+    let schema = batch.schema();
+    let mut fields = Vec::with_capacity(columns.len());
+    let mut arrays = Vec::with_capacity(columns.len());
+    for (column, key) in columns {
+        let (index, field) = schema
+            .column_with_name(column)
+            .ok_or_else(|| anyhow::anyhow!("Column '{}' not found for JSONL/CSV output", column))?;
+        fields.push(Field::new(key, field.data_type().clone(), true));
+        arrays.push(Arc::clone(batch.column(index)));
+    }
+    Ok(RecordBatch::try_new(Arc::new(Schema::new(fields)), arrays)?)
+}
+
+/// Replace the given text columns with their section lists
+fn split_section_columns(batch: &RecordBatch, columns: &[String]) -> Result<RecordBatch> {
+    let schema = batch.schema();
+    let mut fields: Vec<Field> = schema.fields().iter().map(|field| field.as_ref().clone()).collect();
+    let mut arrays: Vec<ArrayRef> = batch.columns().to_vec();
+    for column in columns {
+        let (index, field) = schema
+            .column_with_name(column)
+            .ok_or_else(|| anyhow::anyhow!("Column '{}' not found for sections-json output", column))?;
+        let texts = arrays[index]
+            .as_string_opt::<i32>()
+            .ok_or_else(|| anyhow::anyhow!("Column '{}' is not a string column", column))?;
+        let sections = sections_array(texts);
+        fields[index] = Field::new(field.name(), sections.data_type().clone(), true);
+        arrays[index] = Arc::new(sections);
+    }
+    Ok(RecordBatch::try_new(Arc::new(Schema::new(fields)), arrays)?)
+}
+
+/// One list of `{heading, level, paragraphs}` structs per text (null for null texts)
+pub fn sections_array(texts: &StringArray) -> ListArray {
+    let mut headings: Vec<Option<String>> = Vec::new();
+    let mut levels: Vec<Option<u8>> = Vec::new();
+    let mut paragraphs: Vec<String> = Vec::new();
+    let mut paragraph_offsets = vec![0i32];
+    let mut section_offsets = vec![0i32];
+    for text in texts.iter() {
+        for section in text.map(sections::split_sections).unwrap_or_default() {
+            headings.push(section.heading);
+            levels.push(section.level);
+            paragraphs.extend(section.paragraphs);
+            paragraph_offsets.push(paragraphs.len() as i32);
+        }
+        section_offsets.push(headings.len() as i32);
+    }
+
+    let paragraph_field = Arc::new(Field::new("item", DataType::Utf8, false));
+    let paragraphs = ListArray::new(
+        Arc::clone(&paragraph_field),
+        OffsetBuffer::new(paragraph_offsets.into()),
+        Arc::new(StringArray::from(paragraphs)),
+        None,
+    );
+    let struct_fields = Fields::from(vec![
+        Field::new("heading", DataType::Utf8, true),
+        Field::new("level", DataType::UInt8, true),
+        Field::new("paragraphs", DataType::List(paragraph_field), false),
+    ]);
+    let sections = StructArray::new(
+        struct_fields.clone(),
+        vec![
+            Arc::new(StringArray::from(headings)) as ArrayRef,
+            Arc::new(UInt8Array::from(levels)),
+            Arc::new(paragraphs),
+        ],
+        None,
+    );
+    ListArray::new(
+        Arc::new(Field::new("item", DataType::Struct(struct_fields), false)),
+        OffsetBuffer::new(section_offsets.into()),
+        Arc::new(sections),
+        texts.nulls().cloned(),
+    )
+}
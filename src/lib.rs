@@ -0,0 +1,89 @@
+//! Wikitext-to-plain-text extraction for Russian Wikipedia and Ruwiki dumps
+//!
+//! The parser itself only needs `parse_wiki_text` and `regex`:
+//!
+//! ```no_run
+//! use wikitext_parser::{parse_wikitext, ListStyle, ParseOptions};
+//!
+//! let options = ParseOptions { list_style: ListStyle::Skip, ..Default::default() };
+//! let text = parse_wikitext("'''Москва''' — столица [[Россия|России]].", &options);
+//! ```
+//!
+//! `ParseOptions::default()` disables every optional cleanup step, including
+//! template dropping, template expansion and empty-section removal; the
+//! binaries start from `DEFAULT_DROP_TEMPLATES`, `DEFAULT_EXPAND_TEMPLATES`,
+//! `DEFAULT_DROP_LINK_NAMESPACES`, `DEFAULT_SECTION_NAMES` and
+//! `DEFAULT_CATEGORY_PREFIXES` instead.
+//!
+//! With the `arrow` feature, `batch::process_record_batch` (or a reusable
+//! `batch::BatchProcessor`) parses the text columns of in-memory Arrow
+//! `RecordBatch`es, producing the same columns as the `parse_single` binary.
+//!
+//! Features (all enabled by default through `cli`):
+//! - `arrow`: Arrow/parquet batch helpers and local/object-storage I/O
+//! - `html`: extraction from rendered article HTML
+//! - `progress`: row progress bar shown by the binaries
+//! - `cli`: everything the binaries need (`arrow`, `html`, `progress` and clap),
+//!   including the shared `cli::ParseArgs` flags
+
+#[cfg(feature = "arrow")]
+pub mod batch;
+pub mod chunk;
+#[cfg(feature = "cli")]
+pub mod cli;
+pub mod configuration;
+#[cfg(feature = "arrow")]
+pub mod csv_output;
+#[cfg(feature = "arrow")]
+pub mod columns;
+#[cfg(feature = "arrow")]
+pub mod decompress;
+pub mod diff;
+#[cfg(feature = "html")]
+pub mod html;
+#[cfg(feature = "arrow")]
+pub mod incremental;
+#[cfg(feature = "arrow")]
+pub mod jsonl;
+#[cfg(feature = "arrow")]
+pub mod output;
+#[cfg(feature = "arrow")]
+pub mod page_filter;
+pub mod parser;
+pub mod pool;
+#[cfg(feature = "progress")]
+pub mod progress;
+pub mod quality;
+#[cfg(feature = "arrow")]
+pub mod sample;
+#[cfg(feature = "arrow")]
+pub mod schema;
+pub mod sections;
+pub mod sentences;
+pub mod simhash;
+#[cfg(feature = "arrow")]
+pub mod sort;
+#[cfg(feature = "arrow")]
+pub mod stats;
+#[cfg(feature = "arrow")]
+pub mod status;
+#[cfg(feature = "arrow")]
+pub mod storage;
+
+/// Re-exported so callers of `extract_text_from_nodes` use the same `Node` type
+pub use parse_wiki_text;
+
+pub use parser::{
+    decode_entities, expand_common_templates, extract_categories, extract_display_title, extract_redirect_target,
+    extract_sortkey, extract_text_from_nodes, parse_wikitext, parser_warnings, postprocess_text,
+    remove_dropped_templates, remove_image_fragments, remove_leaked_templates, try_parse_wikitext,
+    try_parse_wikitext_with_warnings,
+};
+pub use parser::{
+    DashStyle, HeadingStyle, Language, LinkStyle, ListStyle, ParagraphDedup, ParseOptions, ParseResult,
+    ParserWarnings, RefStyle, SkipReason, TableStyle, UnicodeForm,
+};
+pub use parser::{
+    DEFAULT_CATEGORY_PREFIXES, DEFAULT_DISAMBIGUATION_MARKERS, DEFAULT_DROP_LINK_NAMESPACES, DEFAULT_DROP_TEMPLATES,
+    DEFAULT_EXPAND_TEMPLATES, DEFAULT_SECTION_NAMES,
+};
@@ -1,210 +1,800 @@
-mod parser;
-
 use anyhow::Result;
 use clap::Parser as ClapParser;
-use std::fs::File;
 use std::sync::Arc;
-use std::sync::mpsc;
-use std::thread;
-use std::time::Duration;
+use wikitext_parser::{
+    cli, columns, decompress, incremental, jsonl, output, parser, pool, progress, quality, sample, schema, sentences,
+    simhash, sort, stats, status, storage,
+};
 
-use arrow::array::{Array, ArrayRef, RecordBatch, StringArray};
+use arrow::array::{Array, ArrayRef, BooleanArray, Float32Array, RecordBatch, StringArray, UInt64Array};
 use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
-use parquet::arrow::ArrowWriter;
-use parquet::file::properties::WriterProperties;
 
 #[derive(ClapParser, Debug)]
 #[command(author, version, about, long_about = None)]
 struct Args {
-    /// Input parquet file path
-    #[arg(short, long)]
-    input: String,
+    /// Input parquet file path; several paths or a glob ('parts/*.parquet') read every
+    /// matching file, and all inputs must share one schema
+    #[arg(short, long, required = true, num_args = 1..)]
+    input: Vec<String>,
 
-    /// Output parquet file path
-    #[arg(short, long)]
-    output: String,
+    /// Output file path (all inputs are written into this one file)
+    #[arg(short, long, required_unless_present = "output_dir", conflicts_with = "output_dir")]
+    output: Option<String>,
 
-    /// Skip lists (remove all bullet/numbered lists from output)
-    #[arg(long, default_value_t = false)]
-    skip_lists: bool,
+    /// Write one output per input into this directory, named after the input file
+    #[arg(long)]
+    output_dir: Option<String>,
+
+    /// Also write per-article metrics (input/output chars, paragraphs, parse status,
+    /// parser warnings) keyed by page ID to this parquet file (parses each article a second time)
+    #[arg(long, conflicts_with = "output_dir")]
+    stats_output: Option<String>,
+
+    /// Only read and process the first N input rows (quick runs while iterating on the parser)
+    #[arg(long)]
+    sample: Option<usize>,
+
+    /// Page ID column (auto-detected: page_id, pageid)
+    #[arg(long)]
+    page_id_column: Option<String>,
+
+    /// Wikipedia text column (auto-detected: official_text, or a name containing "official" and "text")
+    #[arg(long)]
+    official_text_column: Option<String>,
+
+    /// Ruwiki text column (auto-detected: clone_text, or a name containing "clone" and "text")
+    #[arg(long)]
+    clone_text_column: Option<String>,
+
+    /// Page title column (auto-detected: page_title, title)
+    #[arg(long)]
+    page_title_column: Option<String>,
+
+    /// Wikipedia timestamp column, also used by --since (auto-detected: official_timestamp, or "official" + "timestamp")
+    #[arg(long)]
+    official_timestamp_column: Option<String>,
+
+    /// Ruwiki title column (auto-detected: clone_page_title, clone_title, or "clone" + "title")
+    #[arg(long)]
+    clone_page_title_column: Option<String>,
+
+    /// Ruwiki timestamp column (auto-detected: clone_timestamp, or "clone" + "timestamp")
+    #[arg(long)]
+    clone_timestamp_column: Option<String>,
+
+    /// Extra input columns copied verbatim into the output, comma-separated (e.g. wikidata_id,revision_id)
+    #[arg(long, value_delimiter = ',')]
+    include_columns: Vec<String>,
+
+    #[command(flatten)]
+    parse: cli::ParseArgs,
+
+    /// How Binary/LargeBinary text columns are decoded: none (raw UTF-8), auto (gzip/zstd by magic bytes), gzip or zstd
+    #[arg(long, value_enum, default_value_t = decompress::Decompress::Auto)]
+    decompress: decompress::Decompress,
+
+    /// Binary text values that aren't valid UTF-8: skip (null, reported), replace (U+FFFD, reported) or error
+    #[arg(long, value_enum, default_value_t = decompress::OnInvalid::Skip)]
+    on_invalid: decompress::OnInvalid,
 
     /// Timeout in seconds for parsing each article (0 = no timeout)
     #[arg(long, default_value_t = 30)]
     timeout: u64,
+
+    /// Number of worker threads parsing articles in parallel (0 = one per CPU core)
+    #[arg(long, default_value_t = 0)]
+    threads: usize,
+
+    /// Add `official_simhash`/`clone_simhash` columns with 64-bit SimHashes of the parsed text
+    #[arg(long, default_value_t = false)]
+    emit_simhash: bool,
+
+    /// Add `official_sentences`/`clone_sentences` columns with the parsed text split into sentences, one per line
+    #[arg(long, default_value_t = false)]
+    segment_sentences: bool,
+
+    #[command(flatten)]
+    extract: cli::ExtractArgs,
+
+    /// Only reparse rows whose official timestamp is at or after this time
+    /// (RFC 3339, e.g. 2024-05-01T00:00:00Z)
+    #[arg(long)]
+    since: Option<String>,
+
+    /// What to do with rows older than --since
+    #[arg(long, value_enum, default_value_t = incremental::UnchangedAction::Omit)]
+    unchanged_action: incremental::UnchangedAction,
+
+    /// Output format: parquet, or JSON Lines / CSV with page_id, page_title and the parsed texts
+    /// (sections-json: JSON Lines with each text as [{heading, level, paragraphs}, ...])
+    #[arg(long, value_enum, default_value_t = jsonl::OutputFormat::Parquet)]
+    output_format: jsonl::OutputFormat,
+
+    /// Print the resolved parse options and detected input columns, then exit
+    #[arg(long, default_value_t = false)]
+    explain: bool,
+
+    /// Add a constant `options_hash` column fingerprinting the effective parse options
+    #[arg(long, default_value_t = false)]
+    emit_options_hash: bool,
+
+    /// Add `official_quality`/`clone_quality` columns with a 0-1 extraction-quality score
+    #[arg(long, default_value_t = false)]
+    quality_score: bool,
+
+    /// Log parse_wiki_text warnings per article to stderr and count them in
+    /// `official_parse_warnings`/`clone_parse_warnings` columns (parses each article a second time)
+    #[arg(long, default_value_t = false)]
+    report_warnings: bool,
+
+    /// Save up to N inputs that timed out, hit the output limit or produced no text
+    /// (0 = disabled)
+    #[arg(long, default_value_t = 0)]
+    report_unparseable_sample: usize,
+
+    /// Parquet file receiving the --report-unparseable-sample inputs and their status
+    #[arg(long, default_value = "unparseable_sample.parquet")]
+    unparseable_sample_path: String,
+
+    /// Sort output rows by this column (e.g. page_id) within each batch,
+    /// recorded as parquet `sorting_columns` metadata
+    #[arg(long)]
+    sort_by: Option<String>,
+
+    /// With --sort-by, sort all rows globally instead of per batch
+    /// (holds a second full copy of the output in memory)
+    #[arg(long, default_value_t = false, requires = "sort_by")]
+    global_sort: bool,
+
+    /// Parquet compression codec for the output
+    #[arg(long, value_enum, default_value_t = output::OutputCompression::None)]
+    compression: output::OutputCompression,
+
+    /// Compression level for --compression gzip (0-10) or zstd (1-22)
+    #[arg(long)]
+    compression_level: Option<i32>,
+
+    /// Re-read the written output and fail if its row count or schema don't match
+    #[arg(long, default_value_t = false)]
+    verify_output: bool,
+
+    /// Log every article as it is parsed instead of showing a progress bar
+    #[arg(short, long, default_value_t = false)]
+    verbose: bool,
+}
+
+/// Input columns read by `process_batch`, resolved from `--*-column` flags or detected
+/// The text columns and page ID are required; missing metadata columns are left out of the output
+struct InputColumns {
+    page_id: Option<String>,
+    official_text: Option<String>,
+    clone_text: Option<String>,
+    page_title: Option<String>,
+    official_timestamp: Option<String>,
+    clone_page_title: Option<String>,
+    clone_timestamp: Option<String>,
+}
+
+impl InputColumns {
+    /// Explicitly named columns that do not exist are recorded in `check` and left unresolved
+    fn resolve(args: &Args, check: &mut columns::SchemaCheck) -> Self {
+        let mut resolve = |specified: &Option<String>, label: &str, detect: fn(&arrow::datatypes::Schema) -> Option<String>| {
+            check.resolve(specified.as_deref(), label, detect)
+        };
+        InputColumns {
+            page_id: resolve(&args.page_id_column, "page_id", columns::detect_pageid_column),
+            official_text: resolve(&args.official_text_column, "official_text", columns::detect_official_text_column),
+            clone_text: resolve(&args.clone_text_column, "clone_text", columns::detect_clone_text_column),
+            page_title: resolve(&args.page_title_column, "page_title", columns::detect_title_column),
+            official_timestamp: resolve(
+                &args.official_timestamp_column,
+                "official_timestamp",
+                columns::detect_official_timestamp_column,
+            ),
+            clone_page_title: resolve(&args.clone_page_title_column, "clone_page_title", columns::detect_clone_title_column),
+            clone_timestamp: resolve(
+                &args.clone_timestamp_column,
+                "clone_timestamp",
+                columns::detect_clone_timestamp_column,
+            ),
+        }
+    }
+
+    /// Output column name, resolved input column and the flag overriding it, in output order
+    fn roles(&self) -> [(&'static str, Option<&str>, &'static str); 7] {
+        [
+            ("page_id", self.page_id.as_deref(), "--page-id-column"),
+            ("official_text", self.official_text.as_deref(), "--official-text-column"),
+            ("clone_text", self.clone_text.as_deref(), "--clone-text-column"),
+            ("page_title", self.page_title.as_deref(), "--page-title-column"),
+            ("official_timestamp", self.official_timestamp.as_deref(), "--official-timestamp-column"),
+            ("clone_page_title", self.clone_page_title.as_deref(), "--clone-page-title-column"),
+            ("clone_timestamp", self.clone_timestamp.as_deref(), "--clone-timestamp-column"),
+        ]
+    }
+
+    /// Record the columns this run needs that are missing or of the wrong type
+    fn check(&self, check: &mut columns::SchemaCheck, since: bool) {
+        for (role, column, flag) in self.roles() {
+            let kind = match role {
+                "official_text" | "clone_text" => Some(columns::ColumnKind::Text),
+                "page_id" | "page_title" => Some(columns::ColumnKind::Id),
+                "official_timestamp" if since => Some(columns::ColumnKind::Timestamp),
+                _ => None,
+            };
+            let required =
+                matches!(role, "page_id" | "official_text" | "clone_text") || (since && role == "official_timestamp");
+            if required {
+                check.require(column, role, flag);
+            }
+            if let Some(kind) = kind {
+                check.check_type(column, role, kind);
+            }
+        }
+    }
 }
 
-/// Parse wikitext with a timeout to handle problematic articles
-/// Returns the parsed text or a placeholder if parsing times out
-fn parse_wikitext_with_timeout(wikitext: &str, skip_lists: bool, timeout_secs: u64) -> String {
-    let wikitext = wikitext.to_string();
-    let (tx, rx) = mpsc::channel();
-
-    // Spawn parsing in a separate thread
-    thread::spawn(move || {
-        let result = parser::parse_wikitext(&wikitext, skip_lists);
-        let _ = tx.send(result);
-    });
-
-    // Wait for result with timeout
-    match rx.recv_timeout(Duration::from_secs(timeout_secs)) {
-        Ok(result) => result,
-        Err(_) => {
-            eprintln!("WARNING: Article parsing timed out after {} seconds", timeout_secs);
-            format!("[Article skipped: parsing timeout after {} seconds]", timeout_secs)
+/// Print the effective configuration for `--explain`
+fn explain(args: &Args, options: &parser::ParseOptions, schema: &arrow::datatypes::Schema, columns: &InputColumns) {
+    println!("Parse options (fingerprint {}):", options.fingerprint());
+    println!("{:#?}", options);
+    println!("Timeout: {}s per article, threads: {}", args.timeout, args.threads);
+    if let Some(since) = &args.since {
+        let column = columns.official_timestamp.as_deref().unwrap_or("missing");
+        println!("Incremental: since {} on {} ({:?} unchanged rows)", since, column, args.unchanged_action);
+    }
+    println!("Input columns:");
+    for (role, column, _) in columns.roles() {
+        match column.and_then(|column| schema.field_with_name(column).ok()) {
+            Some(field) => println!("  {}: {} ({})", role, field.name(), field.data_type()),
+            None => println!("  {}: missing", role),
         }
     }
+    if !args.include_columns.is_empty() {
+        println!("Included columns: {}", args.include_columns.join(", "));
+    }
 }
 
 fn main() -> Result<()> {
     let args = Args::parse();
 
-    println!("Reading input file: {}", args.input);
+    if args.verify_output && args.output_format != jsonl::OutputFormat::Parquet {
+        anyhow::bail!("--verify-output only applies to parquet output");
+    }
+    let compression = args.compression.codec(args.compression_level)?;
 
-    // Read input parquet file
-    let file = File::open(&args.input)?;
-    let builder = ParquetRecordBatchReaderBuilder::try_new(file)?;
-    let mut reader = builder.build()?;
+    let mut options = parser::ParseOptions::from(&args.parse);
+    // sections-json finds section boundaries by their Markdown heading prefixes
+    if args.output_format == jsonl::OutputFormat::SectionsJson {
+        options.heading_style = parser::HeadingStyle::Markdown;
+    }
 
-    // Collect all record batches
-    let mut batches = Vec::new();
-    while let Some(batch) = reader.next() {
-        batches.push(batch?);
+    // Every input must have the first one's schema, so the columns are checked once
+    let inputs = storage::expand_inputs(&args.input)?;
+    let (input_schema, input_rows) = storage::check_inputs(&inputs)?;
+    if inputs.len() > 1 {
+        println!("Reading {} input files", inputs.len());
     }
 
-    if batches.is_empty() {
-        println!("No data found in input file");
+    // Every missing or mistyped column is reported at once, before any batch is read
+    let mut check = columns::SchemaCheck::new(&input_schema);
+    let input_columns = InputColumns::resolve(&args, &mut check);
+    if args.explain {
+        explain(&args, &options, &input_schema, &input_columns);
         return Ok(());
     }
+    input_columns.check(&mut check, args.since.is_some());
+    let output_columns = output_column_names(&args);
+    for col in &args.include_columns {
+        check.require_existing(col, "included");
+        check.require_unique(col, "included", &output_columns);
+    }
+    check.finish()?;
 
-    // Process batches
-    let processed_batches: Vec<RecordBatch> = batches
-        .iter()
-        .map(|batch| process_batch(batch, args.skip_lists, args.timeout))
-        .collect::<Result<Vec<_>>>()?;
+    // (inputs, their row counts, output path) per output file
+    let outputs: Vec<(&[String], &[u64], String)> = match (&args.output, &args.output_dir) {
+        (Some(output), _) => vec![(inputs.as_slice(), input_rows.as_slice(), output.clone())],
+        (None, Some(dir)) => inputs
+            .iter()
+            .zip(&input_rows)
+            .map(|(input, rows)| {
+                let output = storage::output_in_dir(dir, input, args.output_format.extension())?;
+                Ok((std::slice::from_ref(input), std::slice::from_ref(rows), output))
+            })
+            .collect::<Result<_>>()?,
+        (None, None) => anyhow::bail!("Either --output or --output-dir is required"),
+    };
+    let mut names = std::collections::HashSet::new();
+    if let Some((_, _, output)) = outputs.iter().find(|(_, _, output)| !names.insert(output)) {
+        anyhow::bail!("Several inputs would be written to {}; their file names must differ", output);
+    }
+    // An output renamed over an input would replace the raw data it was parsed from
+    for (_, _, output) in &outputs {
+        for input in &inputs {
+            if storage::same_location(output, input)? {
+                anyhow::bail!("Output {} would overwrite input {}", output, input);
+            }
+        }
+    }
 
-    // Write output parquet file
-    println!("Writing output file: {}", args.output);
-    let output_file = File::create(&args.output)?;
-    let schema = processed_batches[0].schema();
+    let options_hash = args.emit_options_hash.then(|| options.fingerprint());
+    if let Some(hash) = &options_hash {
+        println!("Options hash: {}", hash);
+    }
 
-    let props = WriterProperties::builder().build();
-    let mut writer = ArrowWriter::try_new(output_file, schema, Some(props))?;
+    let pool =
+        pool::ParsePool::new(args.threads, args.timeout, move |text| parser::try_parse_wikitext_with_warnings(text, &options))
+            .verbose(args.verbose);
+    println!("Parsing with {} worker thread(s)", pool.threads());
 
-    for batch in processed_batches {
-        writer.write(&batch)?;
+    let mut sample = sample::FailureSample::new(args.report_unparseable_sample);
+    let run = Run {
+        args: &args,
+        input_columns: &input_columns,
+        pool: &pool,
+        since: args.since.as_deref().map(incremental::parse_since).transpose()?,
+        options_hash: options_hash.as_deref(),
+        compression,
+    };
+    for (inputs, rows, output_path) in &outputs {
+        run.write_output(inputs, rows, output_path, &mut sample)?;
     }
 
-    writer.close()?;
+    if args.report_unparseable_sample > 0 {
+        sample.write(&args.unparseable_sample_path)?;
+    }
     println!("Processing complete!");
 
     Ok(())
 }
 
-fn process_batch(batch: &RecordBatch, skip_lists: bool, timeout: u64) -> Result<RecordBatch> {
-    let _schema = batch.schema();
+/// What every output file of a run is written with
+struct Run<'a> {
+    args: &'a Args,
+    input_columns: &'a InputColumns,
+    pool: &'a pool::ParsePool,
+    since: Option<i64>,
+    options_hash: Option<&'a str>,
+    compression: parquet::basic::Compression,
+}
 
-    // Extract columns
-    let page_id = batch.column_by_name("page_id")
-        .ok_or_else(|| anyhow::anyhow!("page_id column not found"))?
-        .as_any()
-        .downcast_ref::<StringArray>()
-        .ok_or_else(|| anyhow::anyhow!("page_id is not a StringArray"))?;
-    let page_title = batch.column_by_name("page_title")
-        .ok_or_else(|| anyhow::anyhow!("page_title column not found"))?
-        .as_any()
-        .downcast_ref::<StringArray>()
-        .ok_or_else(|| anyhow::anyhow!("page_title is not a StringArray"))?;
-    let official_text = batch.column_by_name("official_text")
-        .ok_or_else(|| anyhow::anyhow!("official_text column not found"))?
-        .as_any()
-        .downcast_ref::<StringArray>()
-        .ok_or_else(|| anyhow::anyhow!("official_text is not a StringArray"))?;
-    let official_timestamp = batch.column_by_name("official_timestamp")
-        .ok_or_else(|| anyhow::anyhow!("official_timestamp column not found"))?;
-    let clone_page_title = batch.column_by_name("clone_page_title")
-        .ok_or_else(|| anyhow::anyhow!("clone_page_title column not found"))?;
-    let clone_text = batch.column_by_name("clone_text")
-        .ok_or_else(|| anyhow::anyhow!("clone_text column not found"))?
-        .as_any()
-        .downcast_ref::<StringArray>()
-        .ok_or_else(|| anyhow::anyhow!("clone_text is not a StringArray"))?;
-    let clone_timestamp = batch.column_by_name("clone_timestamp")
-        .ok_or_else(|| anyhow::anyhow!("clone_timestamp column not found"))?;
-
-    eprintln!("Processing batch with {} rows", official_text.len());
+impl Run<'_> {
+    /// Parse `inputs` (with `rows` rows each) into one output file
+    /// `--sample` limits the rows of the whole output, not of each input
+    fn write_output(
+        &self,
+        inputs: &[String],
+        rows: &[u64],
+        output_path: &str,
+        sample: &mut sample::FailureSample,
+    ) -> Result<()> {
+        let args = self.args;
+        let input_columns = self.input_columns;
 
-    // Parse wikitext for both official and clone texts
-    let official_paragraphs: Vec<Option<String>> = (0..official_text.len())
-        .map(|i| {
-            let pid = if page_id.is_null(i) { "unknown".to_string() } else { page_id.value(i).to_string() };
-            let title = if page_title.is_null(i) { "untitled".to_string() } else { page_title.value(i).to_string() };
-            eprintln!("  [{}] Processing official text for page_id={} title={}", i+1, pid, title);
-
-            if official_text.is_null(i) {
-                None
-            } else {
-                let result = if timeout == 0 {
-                    // No timeout - direct call for maximum speed
-                    parser::parse_wikitext(official_text.value(i), skip_lists)
-                } else {
-                    // Use timeout wrapper
-                    parse_wikitext_with_timeout(official_text.value(i), skip_lists, timeout)
-                };
-                eprintln!("  [{}] Done processing official text for page_id={}", i+1, pid);
-                Some(result)
+        // Read, process and write one batch at a time; the writer is created from
+        // the first processed batch's schema. A global sort has to hold every
+        // batch until the end.
+        let mut writer: Option<output::BatchWriter> = None;
+        let mut stats_writer: Option<output::BatchWriter> = None;
+        let mut schema = None;
+        let mut unsorted_batches = Vec::new();
+
+        let text_columns: Vec<&str> =
+            [&input_columns.official_text, &input_columns.clone_text].into_iter().flatten().map(String::as_str).collect();
+        let total_rows = rows.iter().sum::<u64>().min(args.sample.map_or(u64::MAX, |limit| limit as u64));
+        let progress = progress::row_progress(total_rows, args.verbose);
+        let mut remaining = args.sample;
+        for input in inputs {
+            if remaining == Some(0) {
+                break;
             }
-        })
-        .collect();
-
-    let clone_paragraphs: Vec<Option<String>> = (0..clone_text.len())
-        .map(|i| {
-            let pid = if page_id.is_null(i) { "unknown".to_string() } else { page_id.value(i).to_string() };
-            let title = if page_title.is_null(i) { "untitled".to_string() } else { page_title.value(i).to_string() };
-            eprintln!("  [{}] Processing clone text for page_id={} title={}", i+1, pid, title);
-
-            if clone_text.is_null(i) {
-                None
-            } else {
-                let result = if timeout == 0 {
-                    // No timeout - direct call for maximum speed
-                    parser::parse_wikitext(clone_text.value(i), skip_lists)
-                } else {
-                    // Use timeout wrapper
-                    parse_wikitext_with_timeout(clone_text.value(i), skip_lists, timeout)
+            println!("Reading input file: {}", input);
+            let builder = ParquetRecordBatchReaderBuilder::try_new(storage::open_input(input)?)?;
+            let (builder, sampled_rows) = storage::with_sample(builder, remaining);
+            remaining = remaining.map(|limit| limit - sampled_rows as usize);
+
+            for batch in builder.build()? {
+                let batch = decompress::decode_text_columns(&batch?, &text_columns, args.decompress, args.on_invalid)?;
+                let input_rows = batch.num_rows() as u64;
+                // Incremental mode: drop unchanged rows or mark them for passthrough
+                let (batch, changed) = match self.since {
+                    Some(since) => {
+                        // InputColumns::check requires the timestamp column when --since is set,
+                        // so SchemaCheck::finish has already rejected inputs without one
+                        let column = input_columns.official_timestamp.as_deref().ok_or_else(|| {
+                            anyhow::anyhow!("--since needs a timestamp column; pass --official-timestamp-column")
+                        })?;
+                        incremental::select_changed(&batch, column, since, args.unchanged_action)?
+                    }
+                    None => (batch, None),
                 };
-                eprintln!("  [{}] Done processing clone text for page_id={}", i+1, pid);
-                Some(result)
+                let (processed, row_stats) =
+                    process_batch(&batch, input_columns, self.pool, sample, changed.as_ref(), args, self.options_hash)?;
+                progress.inc(input_rows);
+                if let (Some(row_stats), Some(stats_path)) = (row_stats, &args.stats_output) {
+                    if stats_writer.is_none() {
+                        println!("Writing stats file: {}", stats_path);
+                        stats_writer = Some(output::BatchWriter::create(
+                            stats_path,
+                            jsonl::OutputFormat::Parquet,
+                            &row_stats.schema(),
+                            None,
+                            false,
+                            &[],
+                            self.compression,
+                        )?);
+                    }
+                    if let Some(stats_writer) = stats_writer.as_mut() {
+                        stats_writer.write(&row_stats)?;
+                    }
+                }
+
+                let schema = schema.get_or_insert_with(|| schema::nullable_schema(&processed.schema()));
+                let processed = schema::coerce_batch(&processed, schema)?;
+
+                if args.global_sort {
+                    unsorted_batches.push(processed);
+                    continue;
+                }
+                let processed = match &args.sort_by {
+                    Some(column) => sort::sort_batch(&processed, column)?,
+                    None => processed,
+                };
+                if writer.is_none() {
+                    writer = Some(create_writer(args, output_path, schema, self.compression)?);
+                }
+                if let Some(writer) = writer.as_mut() {
+                    writer.write(&processed)?;
+                }
+            }
+        }
+        progress.finish();
+        if let Some(stats_writer) = stats_writer {
+            stats_writer.finish()?;
+        }
+
+        let Some(schema) = schema else {
+            println!("No data found in {}", inputs.join(", "));
+            return Ok(());
+        };
+
+        if let (true, Some(column)) = (args.global_sort, &args.sort_by) {
+            let mut global_writer = create_writer(args, output_path, &schema, self.compression)?;
+            global_writer.write(&sort::sort_all(&unsorted_batches, column)?)?;
+            writer = Some(global_writer);
+        }
+
+        if let Some(writer) = writer {
+            let written_rows = writer.finish()?;
+            if args.verify_output {
+                storage::verify_output(output_path, written_rows, &schema)?;
             }
-        })
-        .collect();
+        }
+        Ok(())
+    }
+}
+
+/// Create the output writer for the processed schema
+fn create_writer(
+    args: &Args,
+    output_path: &str,
+    schema: &arrow::datatypes::SchemaRef,
+    compression: parquet::basic::Compression,
+) -> Result<output::BatchWriter> {
+    println!("Writing output file: {}", output_path);
+
+    // JSONL and CSV keep the page ID, title and parsed texts under their input column names, plus the parse statuses
+    let mut record_columns = vec![("page_id".to_string(), "page_id".to_string())];
+    if schema.field_with_name("page_title").is_ok() {
+        record_columns.push(("page_title".to_string(), "page_title".to_string()));
+    }
+    record_columns.push(("official_text_paragraphs".to_string(), "official_text".to_string()));
+    record_columns.push(("clone_text_paragraphs".to_string(), "clone_text".to_string()));
+    for status_column in ["official_parse_status", "clone_parse_status"] {
+        record_columns.push((status_column.to_string(), status_column.to_string()));
+    }
+    for col in &args.include_columns {
+        record_columns.push((col.clone(), col.clone()));
+    }
+
+    output::BatchWriter::create(
+        output_path,
+        args.output_format,
+        schema,
+        args.sort_by.as_deref(),
+        args.sort_by.is_some() && !args.global_sort,
+        &record_columns,
+        compression,
+    )
+    .map(|writer| {
+        writer.with_section_columns(&["official_text_paragraphs".to_string(), "clone_text_paragraphs".to_string()])
+    })
+}
+
+/// Build a SimHash column for parsed texts (null where the text is null)
+fn simhash_array(texts: &[Option<String>]) -> ArrayRef {
+    Arc::new(UInt64Array::from(
+        texts
+            .iter()
+            .map(|text| text.as_deref().map(simhash::simhash))
+            .collect::<Vec<_>>(),
+    ))
+}
+
+/// Build a sentence-per-line column for parsed texts (null where the text is null)
+fn sentences_array(texts: &[Option<String>]) -> ArrayRef {
+    Arc::new(StringArray::from(
+        texts
+            .iter()
+            .map(|text| text.as_deref().map(|text| sentences::split_sentences(text).join("\n")))
+            .collect::<Vec<_>>(),
+    ))
+}
+
+/// Build a quality-score column for parsed texts (null where the text is null)
+fn quality_array(texts: &[Option<String>]) -> ArrayRef {
+    Arc::new(Float32Array::from(
+        texts
+            .iter()
+            .map(|text| text.as_deref().map(quality::quality_score))
+            .collect::<Vec<_>>(),
+    ))
+}
+
+/// Every column this run can write besides the included ones, whether or not the input has
+/// the optional metadata columns; `--include-columns` must not reuse any of them
+fn output_column_names(args: &Args) -> Vec<String> {
+    let mut names: Vec<String> = [
+        "page_id",
+        "page_title",
+        "official_text_paragraphs",
+        "official_parse_status",
+        "official_timestamp",
+        "clone_page_title",
+        "clone_text_paragraphs",
+        "clone_parse_status",
+        "clone_timestamp",
+    ]
+    .map(String::from)
+    .into();
+    let mut suffixes = Vec::new();
+    if args.emit_simhash {
+        suffixes.push("simhash");
+    }
+    if args.segment_sentences {
+        suffixes.push("sentences");
+    }
+    if args.quality_score {
+        suffixes.push("quality");
+    }
+    suffixes.extend(args.extract.suffixes());
+    if args.report_warnings {
+        suffixes.push("parse_warnings");
+    }
+    for suffix in suffixes {
+        names.push(format!("official_{}", suffix));
+        names.push(format!("clone_{}", suffix));
+    }
+    if args.emit_options_hash {
+        names.push("options_hash".to_string());
+    }
+    names
+}
+
+fn process_batch(
+    batch: &RecordBatch,
+    input_columns: &InputColumns,
+    pool: &pool::ParsePool,
+    sample: &mut sample::FailureSample,
+    changed: Option<&BooleanArray>,
+    args: &Args,
+    options_hash: Option<&str>,
+) -> Result<(RecordBatch, Option<RecordBatch>)> {
+    let _schema = batch.schema();
+
+    // Extract columns
+    let string_column = |name: &Option<String>, role: &str| {
+        let name = name.as_deref().ok_or_else(|| anyhow::anyhow!("{} column not found", role))?;
+        batch
+            .column_by_name(name)
+            .ok_or_else(|| anyhow::anyhow!("{} column not found", name))?
+            .as_any()
+            .downcast_ref::<StringArray>()
+            .ok_or_else(|| anyhow::anyhow!("{} is not a StringArray", name))
+    };
+    // Page IDs may be stored as integers; they are kept as is in the output and stringified for labels
+    let page_id_name = input_columns.page_id.as_deref().ok_or_else(|| anyhow::anyhow!("page_id column not found"))?;
+    let page_id_column = batch
+        .column_by_name(page_id_name)
+        .ok_or_else(|| anyhow::anyhow!("{} column not found", page_id_name))?;
+    let page_id = &columns::string_values(page_id_column, page_id_name)?;
+    let official_text = string_column(&input_columns.official_text, "official_text")?;
+    let clone_text = string_column(&input_columns.clone_text, "clone_text")?;
+
+    // Metadata columns are optional; missing ones are omitted from the output
+    let optional_column = |name: &Option<String>| name.as_deref().and_then(|name| batch.column_by_name(name));
+    let page_title = optional_column(&input_columns.page_title);
+    let official_timestamp = optional_column(&input_columns.official_timestamp);
+    let clone_page_title = optional_column(&input_columns.clone_page_title);
+    let clone_timestamp = optional_column(&input_columns.clone_timestamp);
+    let page_title_strings = match (page_title, &input_columns.page_title) {
+        (Some(col), Some(name)) => Some(columns::string_values(col, name)?),
+        _ => None,
+    };
+
+    if args.verbose {
+        eprintln!("Processing batch with {} rows", official_text.len());
+    }
+
+    // Describe a row for progress and timeout messages
+    let row_label = |kind: &str, i: usize| {
+        let pid = if page_id.is_null(i) { "unknown".to_string() } else { page_id.value(i).to_string() };
+        let title = match &page_title_strings {
+            Some(titles) if !titles.is_null(i) => titles.value(i).to_string(),
+            _ => "untitled".to_string(),
+        };
+        format!("{} text for page_id={} title={}", kind, pid, title)
+    };
+
+    // Parse wikitext for both official and clone texts
+    // Rows unchanged since --since (passthrough mode) keep their source text unparsed
+    let official_inputs: Vec<Option<&str>> = official_text.iter().collect();
+    let to_parse = incremental::inputs_to_parse(&official_inputs, changed);
+    let (mut official_results, official_warnings) = pool.parse_all(&to_parse, |i| row_label("official", i));
+    status::report_too_large(&official_results, args.parse.max_output_bytes, |i| row_label("official", i));
+    incremental::pass_through_unchanged(&mut official_results, &official_inputs, changed);
+
+    let clone_inputs: Vec<Option<&str>> = clone_text.iter().collect();
+    let to_parse = incremental::inputs_to_parse(&clone_inputs, changed);
+    let (mut clone_results, clone_warnings) = pool.parse_all(&to_parse, |i| row_label("clone", i));
+    status::report_too_large(&clone_results, args.parse.max_output_bytes, |i| row_label("clone", i));
+    incremental::pass_through_unchanged(&mut clone_results, &clone_inputs, changed);
+
+    // Both text columns are present here, so their names are resolved
+    let official_name = input_columns.official_text.as_deref().unwrap_or_default();
+    let clone_name = input_columns.clone_text.as_deref().unwrap_or_default();
+    record_failures(sample, page_id, official_name, &official_inputs, &official_results);
+    record_failures(sample, page_id, clone_name, &clone_inputs, &clone_results);
+
+    // Skipped articles get a null text; the reason goes to the parse_status columns
+    let official_status = status::status_array(&official_results, changed);
+    let clone_status = status::status_array(&clone_results, changed);
+    let warning_columns = if args.report_warnings {
+        Some((
+            status::warning_counts(&official_warnings, &official_results, changed, |i| row_label("official", i)),
+            status::warning_counts(&clone_warnings, &clone_results, changed, |i| row_label("clone", i)),
+        ))
+    } else if args.stats_output.is_some() {
+        Some((
+            status::silent_warning_counts(&official_warnings, &official_results, changed),
+            status::silent_warning_counts(&clone_warnings, &clone_results, changed),
+        ))
+    } else {
+        None
+    };
+    let official_paragraphs = status::texts(official_results);
+    let clone_paragraphs = status::texts(clone_results);
+
+    // --stats-output: per-article metrics in a separate table
+    let row_stats = match (&args.stats_output, &warning_columns) {
+        (Some(_), Some((official_warnings, clone_warnings))) => {
+            let mut row_stats = stats::RowStats::new("page_id", page_id_column);
+            let official_name = |name: &str| format!("official_{}", name);
+            row_stats.add(official_name, &official_inputs, &official_paragraphs, &official_status, official_warnings);
+            row_stats.add(|name| format!("clone_{}", name), &clone_inputs, &clone_paragraphs, &clone_status, clone_warnings);
+            Some(row_stats.finish()?)
+        }
+        _ => None,
+    };
+
+    let simhash_columns = if args.emit_simhash {
+        Some((simhash_array(&official_paragraphs), simhash_array(&clone_paragraphs)))
+    } else {
+        None
+    };
+    let sentence_columns = if args.segment_sentences {
+        Some((sentences_array(&official_paragraphs), sentences_array(&clone_paragraphs)))
+    } else {
+        None
+    };
+    let quality_columns = if args.quality_score {
+        Some((quality_array(&official_paragraphs), quality_array(&clone_paragraphs)))
+    } else {
+        None
+    };
 
     // Create new arrays
     let official_text_paragraphs: ArrayRef = Arc::new(StringArray::from(official_paragraphs));
     let clone_text_paragraphs: ArrayRef = Arc::new(StringArray::from(clone_paragraphs));
 
-    // Build output schema with renamed columns
-    let output_schema = Arc::new(arrow::datatypes::Schema::new(vec![
-        arrow::datatypes::Field::new("page_id", arrow::datatypes::DataType::Utf8, true),
-        arrow::datatypes::Field::new("page_title", arrow::datatypes::DataType::Utf8, true),
-        arrow::datatypes::Field::new("official_text_paragraphs", arrow::datatypes::DataType::Utf8, true),
-        arrow::datatypes::Field::new("official_timestamp", official_timestamp.data_type().clone(), true),
-        arrow::datatypes::Field::new("clone_page_title", clone_page_title.data_type().clone(), true),
-        arrow::datatypes::Field::new("clone_text_paragraphs", arrow::datatypes::DataType::Utf8, true),
-        arrow::datatypes::Field::new("clone_timestamp", clone_timestamp.data_type().clone(), true),
-    ]));
-
-    let output_batch = RecordBatch::try_new(
-        output_schema,
-        vec![
-            Arc::new(page_id.clone()) as ArrayRef,
-            Arc::new(page_title.clone()) as ArrayRef,
-            official_text_paragraphs,
-            Arc::clone(official_timestamp),
-            Arc::clone(clone_page_title),
-            clone_text_paragraphs,
-            Arc::clone(clone_timestamp),
-        ],
-    )?;
-
-    Ok(output_batch)
+    // Build output schema with renamed columns, skipping absent metadata columns
+    let output_columns: [(&str, Option<ArrayRef>); 9] = [
+        ("page_id", Some(Arc::clone(page_id_column))),
+        ("page_title", page_title.cloned()),
+        ("official_text_paragraphs", Some(official_text_paragraphs)),
+        ("official_parse_status", Some(official_status)),
+        ("official_timestamp", official_timestamp.cloned()),
+        ("clone_page_title", clone_page_title.cloned()),
+        ("clone_text_paragraphs", Some(clone_text_paragraphs)),
+        ("clone_parse_status", Some(clone_status)),
+        ("clone_timestamp", clone_timestamp.cloned()),
+    ];
+    let mut fields = Vec::new();
+    let mut columns = Vec::new();
+    for (name, column) in output_columns {
+        if let Some(column) = column {
+            fields.push(arrow::datatypes::Field::new(name, column.data_type().clone(), true));
+            columns.push(column);
+        }
+    }
+
+    // --include-columns: metadata copied as is, under its input name (collisions are rejected up front)
+    for name in &args.include_columns {
+        let column = batch.column_by_name(name).ok_or_else(|| anyhow::anyhow!("{} column not found", name))?;
+        fields.push(arrow::datatypes::Field::new(name, column.data_type().clone(), true));
+        columns.push(Arc::clone(column));
+    }
+
+    if let Some((official_simhash, clone_simhash)) = simhash_columns {
+        fields.push(arrow::datatypes::Field::new("official_simhash", arrow::datatypes::DataType::UInt64, true));
+        fields.push(arrow::datatypes::Field::new("clone_simhash", arrow::datatypes::DataType::UInt64, true));
+        columns.push(official_simhash);
+        columns.push(clone_simhash);
+    }
+
+    if let Some((official_sentences, clone_sentences)) = sentence_columns {
+        fields.push(arrow::datatypes::Field::new("official_sentences", arrow::datatypes::DataType::Utf8, true));
+        fields.push(arrow::datatypes::Field::new("clone_sentences", arrow::datatypes::DataType::Utf8, true));
+        columns.push(official_sentences);
+        columns.push(clone_sentences);
+    }
+
+    if let Some((official_quality, clone_quality)) = quality_columns {
+        fields.push(arrow::datatypes::Field::new("official_quality", arrow::datatypes::DataType::Float32, true));
+        fields.push(arrow::datatypes::Field::new("clone_quality", arrow::datatypes::DataType::Float32, true));
+        columns.push(official_quality);
+        columns.push(clone_quality);
+    }
+
+    // --extract-*: metadata from the raw wikitext, official then clone per column
+    let extracted = args.extract.columns(&official_inputs).into_iter().zip(args.extract.columns(&clone_inputs));
+    for ((suffix, official), (_, clone)) in extracted {
+        fields.push(arrow::datatypes::Field::new(format!("official_{}", suffix), arrow::datatypes::DataType::Utf8, true));
+        fields.push(arrow::datatypes::Field::new(format!("clone_{}", suffix), arrow::datatypes::DataType::Utf8, true));
+        columns.push(official);
+        columns.push(clone);
+    }
+
+    if let (true, Some((official_warnings, clone_warnings))) = (args.report_warnings, warning_columns) {
+        fields.push(arrow::datatypes::Field::new("official_parse_warnings", arrow::datatypes::DataType::UInt32, true));
+        fields.push(arrow::datatypes::Field::new("clone_parse_warnings", arrow::datatypes::DataType::UInt32, true));
+        columns.push(official_warnings);
+        columns.push(clone_warnings);
+    }
+
+    if let Some(hash) = options_hash {
+        fields.push(arrow::datatypes::Field::new("options_hash", arrow::datatypes::DataType::Utf8, true));
+        columns.push(Arc::new(StringArray::from(vec![hash; batch.num_rows()])));
+    }
+
+    let output_schema = Arc::new(arrow::datatypes::Schema::new(fields));
+    let output_batch = RecordBatch::try_new(output_schema, columns)?;
+
+    Ok((output_batch, row_stats))
+}
+
+/// Add failed parses of one text column to the unparseable-input sample
+fn record_failures(
+    sample: &mut sample::FailureSample,
+    page_id: &StringArray,
+    column: &str,
+    inputs: &[Option<&str>],
+    results: &[Option<parser::ParseResult>],
+) {
+    for (i, (input, result)) in inputs.iter().zip(results).enumerate() {
+        if let (Some(input), Some(result)) = (input, result) {
+            let pid = if page_id.is_null(i) { None } else { Some(page_id.value(i)) };
+            sample.record(pid, column, input, result);
+        }
+    }
 }
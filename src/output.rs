@@ -0,0 +1,133 @@
+//! Streaming output writer for parquet, JSON Lines (optionally split into sections) or CSV
+//!
+//! Batches are written as soon as they are processed, so memory use is
+//! bounded by the batch size rather than the file size. With per-batch
+//! sorting each batch ends its own parquet row group so the sort order
+//! recorded in `sorting_columns` holds within every row group.
+//! Parquet output is compressed with the codec chosen by `--compression`.
+
+use crate::csv_output::CsvWriter;
+use crate::jsonl::{JsonlWriter, OutputFormat};
+use crate::sort;
+use crate::storage::{self, OutputFile};
+use anyhow::Result;
+use arrow::array::RecordBatch;
+use arrow::datatypes::SchemaRef;
+use parquet::arrow::ArrowWriter;
+use parquet::basic::{Compression, GzipLevel, ZstdLevel};
+use parquet::file::properties::WriterProperties;
+use std::sync::Arc;
+
+/// Parquet compression codec for the output (`--compression`)
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum OutputCompression {
+    #[default]
+    None,
+    Snappy,
+    Gzip,
+    Zstd,
+    Lz4,
+}
+
+impl OutputCompression {
+    /// The parquet codec; `level` (`--compression-level`) is only accepted for
+    /// gzip (0-10) and zstd (1-22)
+    pub fn codec(self, level: Option<i32>) -> Result<Compression> {
+        let level_error = |e| anyhow::anyhow!("Invalid --compression-level for {:?}: {}", self, e);
+        Ok(match (self, level) {
+            (OutputCompression::Gzip, Some(level)) => {
+                let level = u32::try_from(level).map_err(|_| anyhow::anyhow!("--compression-level must not be negative"))?;
+                Compression::GZIP(GzipLevel::try_new(level).map_err(level_error)?)
+            }
+            (OutputCompression::Gzip, None) => Compression::GZIP(GzipLevel::default()),
+            (OutputCompression::Zstd, Some(level)) => Compression::ZSTD(ZstdLevel::try_new(level).map_err(level_error)?),
+            (OutputCompression::Zstd, None) => Compression::ZSTD(ZstdLevel::default()),
+            (_, Some(_)) => anyhow::bail!("--compression-level only applies to gzip and zstd"),
+            (OutputCompression::None, None) => Compression::UNCOMPRESSED,
+            (OutputCompression::Snappy, None) => Compression::SNAPPY,
+            (OutputCompression::Lz4, None) => Compression::LZ4_RAW,
+        })
+    }
+}
+
+enum Sink {
+    Parquet(Box<ArrowWriter<OutputFile>>),
+    Jsonl(JsonlWriter),
+    /// `sections-json`: JSON Lines with the section columns split into sections
+    Sections(JsonlWriter),
+    Csv(Box<CsvWriter>),
+}
+
+pub struct BatchWriter {
+    sink: Sink,
+    row_group_per_batch: bool,
+    rows: usize,
+}
+
+impl BatchWriter {
+    /// Create the output file
+    /// `sort_by` is recorded as parquet `sorting_columns`; `row_group_per_batch`
+    /// ends a row group after every batch (needed for per-batch sorting);
+    /// `record_columns` are the `(output column, key)` pairs written by JSONL
+    /// and CSV output;
+    /// `compression` applies to parquet only
+    pub fn create(
+        path: &str,
+        format: OutputFormat,
+        schema: &SchemaRef,
+        sort_by: Option<&str>,
+        row_group_per_batch: bool,
+        record_columns: &[(String, String)],
+        compression: Compression,
+    ) -> Result<Self> {
+        let sink = match format {
+            OutputFormat::Parquet => {
+                let mut props = WriterProperties::builder().set_compression(compression);
+                if let Some(column) = sort_by {
+                    props = props.set_sorting_columns(Some(sort::sorting_columns(schema, column)?));
+                }
+                let output_file = storage::create_output(path)?;
+                Sink::Parquet(Box::new(ArrowWriter::try_new(output_file, Arc::clone(schema), Some(props.build()))?))
+            }
+            OutputFormat::Jsonl => Sink::Jsonl(JsonlWriter::create(path, record_columns)?),
+            OutputFormat::SectionsJson => Sink::Sections(JsonlWriter::create(path, record_columns)?),
+            OutputFormat::Csv => Sink::Csv(Box::new(CsvWriter::create(path, record_columns)?)),
+        };
+        Ok(BatchWriter { sink, row_group_per_batch, rows: 0 })
+    }
+
+    /// Output columns written as section lists by `sections-json` output
+    /// (parsed with Markdown headings); other formats ignore them
+    pub fn with_section_columns(mut self, columns: &[String]) -> Self {
+        if let Sink::Sections(writer) = self.sink {
+            self.sink = Sink::Sections(writer.with_section_columns(columns));
+        }
+        self
+    }
+
+    pub fn write(&mut self, batch: &RecordBatch) -> Result<()> {
+        match &mut self.sink {
+            Sink::Parquet(writer) => {
+                writer.write(batch)?;
+                if self.row_group_per_batch {
+                    writer.flush()?;
+                }
+            }
+            Sink::Jsonl(writer) | Sink::Sections(writer) => writer.write(batch)?,
+            Sink::Csv(writer) => writer.write(batch)?,
+        }
+        self.rows += batch.num_rows();
+        Ok(())
+    }
+
+    /// Finalize the output and return the number of rows written
+    pub fn finish(self) -> Result<usize> {
+        match self.sink {
+            Sink::Parquet(writer) => writer.into_inner()?.finish()?,
+            Sink::Jsonl(writer) | Sink::Sections(writer) => writer.finish()?,
+            Sink::Csv(writer) => writer.finish()?,
+        }
+        Ok(self.rows)
+    }
+}
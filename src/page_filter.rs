@@ -0,0 +1,60 @@
+//! Process only selected pages (`--page-ids`, `--page-ids-file`)
+//!
+//! IDs are compared in their string form: integer ID columns are cast to
+//! decimal text, so `--page-ids 42` matches both an Int64 `42` and a Utf8
+//! `"42"`. Rows with a null ID never match.
+
+use crate::columns;
+use anyhow::Result;
+use arrow::array::{Array, BooleanArray, RecordBatch};
+use arrow::compute::filter_record_batch;
+use std::collections::HashSet;
+
+/// Set of page IDs to keep
+pub struct PageIdFilter {
+    ids: HashSet<String>,
+}
+
+impl PageIdFilter {
+    /// Combine the IDs given on the command line with those in a file (one per
+    /// line, blank lines ignored); `None` when neither source is given
+    pub fn load(ids: Option<&[String]>, file: Option<&str>) -> Result<Option<Self>> {
+        if ids.is_none() && file.is_none() {
+            return Ok(None);
+        }
+        let mut filter = PageIdFilter::new(ids.unwrap_or_default().iter().map(String::as_str));
+        if let Some(path) = file {
+            let contents = std::fs::read_to_string(path)
+                .map_err(|e| anyhow::anyhow!("Could not read --page-ids-file {}: {}", path, e))?;
+            filter.ids.extend(PageIdFilter::new(contents.lines()).ids);
+        }
+        if filter.ids.is_empty() {
+            anyhow::bail!("--page-ids / --page-ids-file did not contain any page IDs");
+        }
+        Ok(Some(filter))
+    }
+
+    pub fn new<'a>(ids: impl IntoIterator<Item = &'a str>) -> Self {
+        let ids = ids.into_iter().map(str::trim).filter(|id| !id.is_empty()).map(str::to_string).collect();
+        PageIdFilter { ids }
+    }
+
+    pub fn len(&self) -> usize {
+        self.ids.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty()
+    }
+
+    /// Keep only the rows whose `column` value is one of the IDs
+    pub fn select(&self, batch: &RecordBatch, column: &str) -> Result<RecordBatch> {
+        let ids = batch
+            .column_by_name(column)
+            .ok_or_else(|| anyhow::anyhow!("Page ID column '{}' not found (required by --page-ids)", column))?;
+        let ids = columns::string_values(ids, column)?;
+        let mask: BooleanArray =
+            (0..ids.len()).map(|i| Some(ids.is_valid(i) && self.ids.contains(ids.value(i)))).collect();
+        Ok(filter_record_batch(batch, &mask)?)
+    }
+}
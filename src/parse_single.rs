@@ -8,281 +8,977 @@
 //! - pageid, title, content, timestamp (Ruwiki format)
 //!
 //! Output: Same columns with text/content replaced by parsed plaintext
-
-mod parser;
+//!
+//! `--stdin` skips parquet entirely: one article is read from stdin and its
+//! parsed text printed to stdout, with the same parse options.
 
 use anyhow::Result;
 use clap::Parser as ClapParser;
-use std::fs::File;
+use std::collections::HashMap;
+use std::io::Read;
 use std::sync::Arc;
-use std::sync::mpsc;
-use std::thread;
-use std::time::Duration;
-
-use arrow::array::{Array, ArrayRef, RecordBatch, StringArray};
+use wikitext_parser::batch::derived_column_name;
+use wikitext_parser::{
+    chunk, cli, columns, decompress, html, incremental, jsonl, output, page_filter, parser, pool, progress, quality, sample,
+    schema, sections, sentences, simhash, sort, stats, status, storage,
+};
+
+use arrow::array::{Array, ArrayRef, BooleanArray, Float32Array, RecordBatch, StringArray, UInt32Array, UInt64Array};
+use arrow::compute::take;
 use arrow::datatypes::{DataType, Field, Schema};
 use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
-use parquet::arrow::ArrowWriter;
-use parquet::file::properties::WriterProperties;
 
 #[derive(ClapParser, Debug)]
 #[command(author, version, about = "Parse wikitext from single-column parquet files", long_about = None)]
 struct Args {
     /// Input parquet file path
-    #[arg(short, long)]
-    input: String,
+    #[arg(short, long, required_unless_present = "stdin")]
+    input: Option<String>,
+
+    /// Output parquet file path (not needed with --dry-run)
+    #[arg(short, long, required_unless_present_any = ["dry_run", "stdin"])]
+    output: Option<String>,
 
-    /// Output parquet file path
-    #[arg(short, long)]
-    output: String,
+    /// Parse one article read from stdin and print the text to stdout instead of
+    /// reading parquet (no per-article timeout)
+    #[arg(long, default_value_t = false, conflicts_with_all = ["input", "output", "dry_run", "explain"])]
+    stdin: bool,
+
+    /// Also write per-article metrics (input/output chars, paragraphs, parse status,
+    /// parser warnings) keyed by page ID to this parquet file (parses each article a second time)
+    #[arg(long, conflicts_with = "dry_run")]
+    stats_output: Option<String>,
+
+    /// Only read and process the first N input rows (quick runs while iterating on the parser)
+    #[arg(long)]
+    sample: Option<usize>,
 
     /// Name of the text column to parse (auto-detected if not specified)
     #[arg(long)]
     text_column: Option<String>,
 
-    /// Skip lists (remove all bullet/numbered lists from output)
+    /// Several text columns to parse in one pass, comma-separated
+    /// (each gets its own `<col>_parsed` output column)
+    #[arg(long, value_delimiter = ',', conflicts_with = "text_column")]
+    text_columns: Option<Vec<String>>,
+
+    /// Name of the parsed text column instead of `<col>_parsed` (single text column only)
+    #[arg(long)]
+    output_column: Option<String>,
+
+    /// Keep the raw text column in the output, before its parsed column
     #[arg(long, default_value_t = false)]
-    skip_lists: bool,
+    keep_original: bool,
+
+    /// Name of the page ID column (auto-detected if not specified)
+    #[arg(long)]
+    page_id_column: Option<String>,
+
+    /// Name of the title column (auto-detected if not specified)
+    #[arg(long)]
+    title_column: Option<String>,
+
+    #[command(flatten)]
+    parse: cli::ParseArgs,
+
+    /// How Binary/LargeBinary text columns are decoded: none (raw UTF-8), auto (gzip/zstd by magic bytes), gzip or zstd
+    #[arg(long, value_enum, default_value_t = decompress::Decompress::Auto)]
+    decompress: decompress::Decompress,
+
+    /// Binary text values that aren't valid UTF-8: skip (null, reported), replace (U+FFFD, reported) or error
+    #[arg(long, value_enum, default_value_t = decompress::OnInvalid::Skip)]
+    on_invalid: decompress::OnInvalid,
 
     /// Timeout in seconds for parsing each article (0 = no timeout)
     #[arg(long, default_value_t = 30)]
     timeout: u64,
+
+    /// Number of worker threads parsing articles in parallel (0 = one per CPU core)
+    #[arg(long, default_value_t = 0)]
+    threads: usize,
+
+    /// Add a `simhash` column with a 64-bit SimHash of the parsed text
+    #[arg(long, default_value_t = false)]
+    emit_simhash: bool,
+
+    /// Add a `sentences` column with the parsed text split into sentences, one per line
+    #[arg(long, default_value_t = false)]
+    segment_sentences: bool,
+
+    #[command(flatten)]
+    extract: cli::ExtractArgs,
+
+    /// Add a constant `options_hash` column fingerprinting the effective parse options
+    #[arg(long, default_value_t = false)]
+    emit_options_hash: bool,
+
+    /// Add a `quality` column with a 0-1 extraction-quality score of the parsed text
+    #[arg(long, default_value_t = false)]
+    quality_score: bool,
+
+    /// Log parse_wiki_text warnings per article to stderr and count them in a `parse_warnings` column
+    /// (parses each article a second time)
+    #[arg(long, default_value_t = false, conflicts_with = "input_is_html")]
+    report_warnings: bool,
+
+    /// Add `was_skipped` (timeout or output limit) and `was_truncated` (cut by
+    /// --max-output-chars) boolean columns
+    #[arg(long, default_value_t = false)]
+    flag_skips: bool,
+
+    /// Split parsed text into overlapping chunks of at most N characters,
+    /// one output row per chunk with a `chunk_index` column (0 = no chunking)
+    #[arg(long, default_value_t = 0)]
+    chunk_size: usize,
+
+    /// Characters shared between consecutive chunks (must be smaller than --chunk-size)
+    #[arg(long, default_value_t = 0)]
+    chunk_overlap: usize,
+
+    /// Write one output row per paragraph with a `paragraph_index` column;
+    /// articles without paragraphs (null, skipped or empty) produce no rows
+    #[arg(long, default_value_t = false, conflicts_with = "chunk_size")]
+    explode_paragraphs: bool,
+
+    /// With --explode-paragraphs: add a `section_path` column naming the headings that
+    /// enclose each paragraph ("История > Ранние годы"); headings get no rows of their own
+    #[arg(long, default_value_t = false, requires = "explode_paragraphs")]
+    emit_section_path: bool,
+
+    /// Treat the text column as rendered HTML instead of wikitext
+    #[arg(long, default_value_t = false)]
+    input_is_html: bool,
+
+    /// Save up to N inputs that timed out, hit the output limit or produced no text
+    /// (0 = disabled)
+    #[arg(long, default_value_t = 0)]
+    report_unparseable_sample: usize,
+
+    /// Parquet file receiving the --report-unparseable-sample inputs and their status
+    #[arg(long, default_value = "unparseable_sample.parquet")]
+    unparseable_sample_path: String,
+
+    /// Sort output rows by this column (e.g. page_id) within each batch,
+    /// recorded as parquet `sorting_columns` metadata
+    #[arg(long)]
+    sort_by: Option<String>,
+
+    /// With --sort-by, sort all rows globally instead of per batch
+    /// (holds a second full copy of the output in memory)
+    #[arg(long, default_value_t = false, requires = "sort_by")]
+    global_sort: bool,
+
+    /// Parquet compression codec for the output
+    #[arg(long, value_enum, default_value_t = output::OutputCompression::None)]
+    compression: output::OutputCompression,
+
+    /// Compression level for --compression gzip (0-10) or zstd (1-22)
+    #[arg(long)]
+    compression_level: Option<i32>,
+
+    /// Re-read the written output and fail if its row count or schema don't match
+    #[arg(long, default_value_t = false)]
+    verify_output: bool,
+
+    /// Log every article as it is parsed instead of showing a progress bar
+    #[arg(short, long, default_value_t = false)]
+    verbose: bool,
+
+    /// Parse every row but only print a statistics report; no output file is written
+    #[arg(long, default_value_t = false, conflicts_with_all = ["chunk_size", "explode_paragraphs", "verify_output"])]
+    dry_run: bool,
+
+    /// Output format: parquet, or JSON Lines / CSV with the page ID, title and parsed text columns
+    /// (sections-json: JSON Lines with each text as [{heading, level, paragraphs}, ...])
+    #[arg(long, value_enum, default_value_t = jsonl::OutputFormat::Parquet)]
+    output_format: jsonl::OutputFormat,
+
+    /// Print the resolved parse options and detected columns, then exit
+    #[arg(long, default_value_t = false)]
+    explain: bool,
+
+    /// Only reparse rows whose timestamp is at or after this time
+    /// (RFC 3339, e.g. 2024-05-01T00:00:00Z)
+    #[arg(long)]
+    since: Option<String>,
+
+    /// What to do with rows older than --since
+    #[arg(long, value_enum, default_value_t = incremental::UnchangedAction::Omit)]
+    unchanged_action: incremental::UnchangedAction,
+
+    /// Timestamp column used by --since (auto-detected if not specified)
+    #[arg(long)]
+    timestamp_column: Option<String>,
+
+    /// Only process rows with these page IDs (matched against the page ID column)
+    #[arg(long, value_delimiter = ',')]
+    page_ids: Option<Vec<String>>,
+
+    /// File with page IDs to process, one per line (combined with --page-ids)
+    #[arg(long)]
+    page_ids_file: Option<String>,
 }
 
-/// Parse wikitext with a timeout to handle problematic articles
-fn parse_wikitext_with_timeout(wikitext: &str, skip_lists: bool, timeout_secs: u64) -> String {
-    let wikitext = wikitext.to_string();
-    let (tx, rx) = mpsc::channel();
-
-    thread::spawn(move || {
-        let result = parser::parse_wikitext(&wikitext, skip_lists);
-        let _ = tx.send(result);
-    });
-
-    match rx.recv_timeout(Duration::from_secs(timeout_secs)) {
-        Ok(result) => result,
-        Err(_) => {
-            eprintln!("WARNING: Article parsing timed out after {} seconds", timeout_secs);
-            format!("[Article skipped: parsing timeout after {} seconds]", timeout_secs)
-        }
+/// Number of rows sampled to check whether the input looks like HTML
+const HTML_SAMPLE_ROWS: usize = 100;
+
+/// Extract text with the HTML or wikitext path depending on the input mode,
+/// with the parser warnings of wikitext input (HTML input has none)
+fn parse_text(
+    text: &str,
+    options: &parser::ParseOptions,
+    input_is_html: bool,
+) -> (parser::ParseResult, parser::ParserWarnings) {
+    if input_is_html {
+        (Ok(html::parse_html(text, options)), Vec::new())
+    } else {
+        parser::try_parse_wikitext_with_warnings(text, options)
     }
 }
 
-/// Detect the text column name from schema
-fn detect_text_column(schema: &Schema) -> Option<String> {
-    // Priority order: text, content, official_text, clone_text
-    let candidates = ["text", "content", "official_text", "clone_text"];
-
-    for candidate in candidates {
-        if schema.field_with_name(candidate).is_ok() {
-            return Some(candidate.to_string());
+/// Print the resolved options and columns for `--explain`, before missing columns are reported
+fn explain(
+    args: &Args,
+    options: &parser::ParseOptions,
+    explode: Option<Explode>,
+    schema: &Schema,
+    columns: &[(&str, Option<&str>)],
+) {
+    println!("Parse options (fingerprint {}):", options.fingerprint());
+    println!("{:#?}", options);
+    println!("Input mode: {}", if args.input_is_html { "html" } else { "wikitext" });
+    println!("Timeout: {}s per article, threads: {}", args.timeout, args.threads);
+    match explode {
+        Some(Explode::Chunks(chunk_options)) => {
+            println!("Chunking: {} chars with {} chars overlap", chunk_options.size, chunk_options.overlap);
         }
-    }
-
-    // Fall back to any column with "text" in name
-    for field in schema.fields() {
-        if field.name().to_lowercase().contains("text") {
-            return Some(field.name().clone());
+        Some(Explode::Paragraphs { section_paths: false }) => println!("Exploding: one row per paragraph"),
+        Some(Explode::Paragraphs { section_paths: true }) => {
+            println!("Exploding: one row per paragraph, with its section path")
         }
+        None => {}
     }
-
-    None
-}
-
-/// Detect the page ID column name from schema
-fn detect_pageid_column(schema: &Schema) -> Option<String> {
-    let candidates = ["page_id", "pageid"];
-    for candidate in candidates {
-        if schema.field_with_name(candidate).is_ok() {
-            return Some(candidate.to_string());
+    if let Some(since) = &args.since {
+        println!("Incremental: since {} ({:?} unchanged rows)", since, args.unchanged_action);
+    }
+    println!("Input columns:");
+    for (role, column) in columns {
+        match column.and_then(|column| schema.field_with_name(column).ok()) {
+            Some(field) => println!("  {}: {} ({})", role, field.name(), field.data_type()),
+            None => println!("  {}: missing", role),
         }
     }
-    None
 }
 
-/// Detect the title column name from schema
-fn detect_title_column(schema: &Schema) -> Option<String> {
-    let candidates = ["page_title", "title"];
-    for candidate in candidates {
-        if schema.field_with_name(candidate).is_ok() {
-            return Some(candidate.to_string());
-        }
+/// Warn when the sampled rows suggest the wrong input mode was chosen
+fn check_input_mode(batch: &RecordBatch, text_column: &str, input_is_html: bool) {
+    let Some(text_array) = batch
+        .column_by_name(text_column)
+        .and_then(|col| col.as_any().downcast_ref::<StringArray>())
+    else {
+        return;
+    };
+
+    let sample: Vec<&str> = text_array.iter().flatten().take(HTML_SAMPLE_ROWS).collect();
+    if sample.is_empty() {
+        return;
+    }
+    let html_rows = sample.iter().filter(|text| html::looks_like_html(text)).count();
+
+    if input_is_html && html_rows == 0 {
+        eprintln!(
+            "WARNING: --input-is-html is set but none of the {} sampled rows contain <p> or <div> tags",
+            sample.len()
+        );
+    } else if !input_is_html && html_rows * 2 > sample.len() {
+        eprintln!(
+            "WARNING: {} of {} sampled rows look like HTML; consider --input-is-html",
+            html_rows,
+            sample.len()
+        );
     }
-    None
 }
 
 fn main() -> Result<()> {
     let args = Args::parse();
+    if args.stdin {
+        return parse_stdin(&args);
+    }
 
-    println!("Reading input file: {}", args.input);
+    if args.verify_output && args.output_format != jsonl::OutputFormat::Parquet {
+        anyhow::bail!("--verify-output only applies to parquet output");
+    }
+    let compression = args.compression.codec(args.compression_level)?;
+
+    let explode = if args.chunk_size > 0 {
+        if args.chunk_overlap >= args.chunk_size {
+            anyhow::bail!("--chunk-overlap must be smaller than --chunk-size");
+        }
+        Some(Explode::Chunks(chunk::ChunkOptions { size: args.chunk_size, overlap: args.chunk_overlap }))
+    } else if args.explode_paragraphs {
+        Some(Explode::Paragraphs { section_paths: args.emit_section_path })
+    } else {
+        None
+    };
+
+    // clap requires --input unless --stdin, which returned above
+    let input = args.input.as_deref().unwrap_or_default();
+    if let Some(output) = &args.output {
+        if storage::same_location(output, input)? {
+            anyhow::bail!("Output {} would overwrite input {}", output, input);
+        }
+    }
+    println!("Reading input file: {}", input);
 
     // Read input parquet file
-    let file = File::open(&args.input)?;
+    let file = storage::open_input(input)?;
     let builder = ParquetRecordBatchReaderBuilder::try_new(file)?;
     let schema = builder.schema().clone();
-    let mut reader = builder.build()?;
-
-    // Detect or validate text column
-    let text_column = match &args.text_column {
-        Some(col) => {
-            if schema.field_with_name(col).is_err() {
-                anyhow::bail!("Specified text column '{}' not found in schema", col);
-            }
-            col.clone()
+    let (builder, total_rows) = storage::with_sample(builder, args.sample);
+    let reader = builder.build()?;
+
+    // Detect or validate text columns
+    let text_columns: Vec<String> = match (&args.text_columns, &args.text_column) {
+        (Some(cols), _) => cols.iter().filter(|col| !col.is_empty()).cloned().collect(),
+        (None, Some(col)) => vec![col.clone()],
+        (None, None) => columns::detect_text_column(&schema).into_iter().collect(),
+    };
+    if text_columns.is_empty() && args.text_columns.is_some() {
+        anyhow::bail!("--text-columns must list at least one column");
+    }
+    if let (Some(explode), true) = (explode, text_columns.len() > 1) {
+        anyhow::bail!("{} can only be used with a single text column", explode.flag());
+    }
+    if let (Some(explode), jsonl::OutputFormat::SectionsJson) = (explode, args.output_format) {
+        anyhow::bail!("{} cannot be combined with --output-format sections-json", explode.flag());
+    }
+    if let Some(name) = &args.output_column {
+        if text_columns.len() > 1 {
+            anyhow::bail!("--output-column can only be used with a single text column");
         }
-        None => {
-            detect_text_column(&schema)
-                .ok_or_else(|| anyhow::anyhow!("Could not auto-detect text column. Use --text-column to specify."))?
+        // The replaced text column's name is free unless --keep-original keeps it
+        let replaced = text_columns.contains(name) && !args.keep_original;
+        if schema.field_with_name(name).is_ok() && !replaced {
+            anyhow::bail!("--output-column {} would duplicate an input column", name);
         }
-    };
+    }
+
+    // Every missing or mistyped column is reported at once, before any batch is read
+    let mut check = columns::SchemaCheck::new(&schema);
+    check.require(text_columns.first().map(String::as_str), "text", "--text-column");
+    for col in &text_columns {
+        check.check_type(Some(col), "text", columns::ColumnKind::Text);
+    }
+    let pageid_column = check.resolve(args.page_id_column.as_deref(), "page ID", columns::detect_pageid_column);
+    let title_column = check.resolve(args.title_column.as_deref(), "title", columns::detect_title_column);
+    check.check_type(pageid_column.as_deref(), "page ID", columns::ColumnKind::Id);
+    check.check_type(title_column.as_deref(), "title", columns::ColumnKind::Id);
+    if args.page_ids.is_some() || args.page_ids_file.is_some() {
+        check.require(pageid_column.as_deref(), "page ID", "--page-id-column (required by --page-ids)");
+    }
+    if args.stats_output.is_some() {
+        check.require(pageid_column.as_deref(), "page ID", "--page-id-column (required by --stats-output)");
+    }
+    let timestamp_column = args
+        .since
+        .as_ref()
+        .and_then(|_| check.resolve(args.timestamp_column.as_deref(), "timestamp", columns::detect_timestamp_column));
+    if args.since.is_some() {
+        check.require(timestamp_column.as_deref(), "timestamp", "--timestamp-column (required by --since)");
+        check.check_type(timestamp_column.as_deref(), "timestamp", columns::ColumnKind::Timestamp);
+    }
+    let options = parse_options(&args);
+    if args.explain {
+        let roles: Vec<(&str, Option<&str>)> = text_columns
+            .iter()
+            .map(|col| ("text", Some(col.as_str())))
+            .chain([
+                ("page ID", pageid_column.as_deref()),
+                ("title", title_column.as_deref()),
+                ("timestamp", timestamp_column.as_deref()),
+            ])
+            .collect();
+        explain(&args, &options, explode, &schema, &roles);
+        return Ok(());
+    }
+    check.finish()?;
 
-    let pageid_column = detect_pageid_column(&schema);
-    let title_column = detect_title_column(&schema);
+    let page_filter = page_filter::PageIdFilter::load(args.page_ids.as_deref(), args.page_ids_file.as_deref())?;
 
-    println!("Using text column: {}", text_column);
+    println!("Using text column(s): {}", text_columns.join(", "));
     if let Some(ref col) = pageid_column {
         println!("Using page ID column: {}", col);
     }
     if let Some(ref col) = title_column {
         println!("Using title column: {}", col);
     }
-
-    // Collect all record batches
-    let mut batches = Vec::new();
-    while let Some(batch) = reader.next() {
-        batches.push(batch?);
+    if let Some(ref col) = timestamp_column {
+        println!("Using timestamp column: {}", col);
     }
-
-    if batches.is_empty() {
-        println!("No data found in input file");
-        return Ok(());
+    if let Some(filter) = &page_filter {
+        println!("Processing only {} page ID(s)", filter.len());
     }
 
-    // Build output schema - keep all columns, just rename text column to add _parsed suffix
-    let output_text_column = format!("{}_parsed", text_column);
-    let output_fields: Vec<Field> = schema
+    // Build output schema - keep all columns, rename text columns to add _parsed suffix
+    // (or --output-column) and follow each with its parse status
+    let mut output_fields: Vec<Field> = schema
         .fields()
         .iter()
-        .map(|f| {
-            if f.name() == &text_column {
-                Field::new(&output_text_column, DataType::Utf8, true)
+        .flat_map(|f| {
+            if text_columns.contains(f.name()) {
+                // Kept as decoded by `decompress`, so always Utf8
+                let original = args.keep_original.then(|| f.as_ref().clone().with_data_type(DataType::Utf8));
+                original
+                    .into_iter()
+                    .chain([
+                        Field::new(parsed_column_name(&args, f.name()), DataType::Utf8, true),
+                        Field::new(derived_column_name(&text_columns, f.name(), "parse_status"), DataType::Utf8, true),
+                    ])
+                    .collect()
             } else {
-                f.as_ref().clone()
+                vec![f.as_ref().clone()]
             }
         })
         .collect();
-    let output_schema = Arc::new(Schema::new(output_fields));
+    if let Some(explode) = explode {
+        output_fields.push(Field::new(explode.index_column(), DataType::UInt32, true));
+    }
+    if args.emit_section_path {
+        output_fields.push(Field::new("section_path", DataType::Utf8, true));
+    }
+    if args.emit_simhash {
+        for col in &text_columns {
+            output_fields.push(Field::new(derived_column_name(&text_columns, col, "simhash"), DataType::UInt64, true));
+        }
+    }
+    if args.segment_sentences {
+        for col in &text_columns {
+            output_fields.push(Field::new(derived_column_name(&text_columns, col, "sentences"), DataType::Utf8, true));
+        }
+    }
+    for suffix in args.extract.suffixes() {
+        for col in &text_columns {
+            output_fields.push(Field::new(derived_column_name(&text_columns, col, suffix), DataType::Utf8, true));
+        }
+    }
+    if args.emit_options_hash {
+        output_fields.push(Field::new("options_hash", DataType::Utf8, true));
+    }
+    if args.quality_score {
+        for col in &text_columns {
+            output_fields.push(Field::new(derived_column_name(&text_columns, col, "quality"), DataType::Float32, true));
+        }
+    }
+    if args.report_warnings {
+        for col in &text_columns {
+            output_fields.push(Field::new(derived_column_name(&text_columns, col, "parse_warnings"), DataType::UInt32, true));
+        }
+    }
+    if args.flag_skips {
+        for col in &text_columns {
+            output_fields.push(Field::new(derived_column_name(&text_columns, col, "was_skipped"), DataType::Boolean, true));
+            output_fields.push(Field::new(derived_column_name(&text_columns, col, "was_truncated"), DataType::Boolean, true));
+        }
+    }
+    let output_schema = schema::nullable_schema(&Schema::new(output_fields));
 
-    // Process batches
-    let processed_batches: Vec<RecordBatch> = batches
-        .iter()
-        .map(|batch| {
-            process_single_column_batch(
-                batch,
-                &text_column,
-                &output_text_column,
-                pageid_column.as_deref(),
-                title_column.as_deref(),
-                args.skip_lists,
-                args.timeout,
-                &output_schema,
-            )
-        })
-        .collect::<Result<Vec<_>>>()?;
+    let options_hash = args.emit_options_hash.then(|| options.fingerprint());
+    if let Some(hash) = &options_hash {
+        println!("Options hash: {}", hash);
+    }
 
-    // Write output parquet file
-    println!("Writing output file: {}", args.output);
-    let output_file = File::create(&args.output)?;
+    // --max-output-chars is applied per batch (`status::truncate_results`) so
+    // --flag-skips can tell which articles were cut
+    let options = parser::ParseOptions { max_output_chars: 0, ..options };
+    let input_is_html = args.input_is_html;
+    let pool = pool::ParsePool::new(args.threads, args.timeout, move |text| {
+        parse_text(text, &options, input_is_html)
+    })
+    .verbose(args.verbose);
+    println!("Parsing with {} worker thread(s)", pool.threads());
+
+    let mut sample = sample::FailureSample::new(args.report_unparseable_sample);
+    let since = args.since.as_deref().map(incremental::parse_since).transpose()?;
+
+    // JSONL and CSV keep the page ID, title and parsed texts under their input column names, plus the parse statuses
+    let mut record_columns: Vec<(String, String)> = Vec::new();
+    for col in pageid_column.iter().chain(&title_column) {
+        record_columns.push((col.clone(), col.clone()));
+    }
+    if let Some(explode) = explode {
+        record_columns.push((explode.index_column().to_string(), explode.index_column().to_string()));
+    }
+    if args.emit_section_path {
+        record_columns.push(("section_path".to_string(), "section_path".to_string()));
+    }
+    for col in &text_columns {
+        record_columns.push((parsed_column_name(&args, col), col.clone()));
+        let status_column = derived_column_name(&text_columns, col, "parse_status");
+        record_columns.push((status_column.clone(), status_column));
+    }
+    let section_columns: Vec<String> = text_columns.iter().map(|col| parsed_column_name(&args, col)).collect();
+    // clap requires --output unless --dry-run, which never creates a writer
+    let output_path = args.output.as_deref().unwrap_or_default();
+    let create_writer = || {
+        println!("Writing output file: {}", output_path);
+        output::BatchWriter::create(
+            output_path,
+            args.output_format,
+            &output_schema,
+            args.sort_by.as_deref(),
+            args.sort_by.is_some() && !args.global_sort,
+            &record_columns,
+            compression,
+        )
+        .map(|writer| writer.with_section_columns(&section_columns))
+    };
 
-    let props = WriterProperties::builder().build();
-    let mut writer = ArrowWriter::try_new(output_file, output_schema, Some(props))?;
+    // Read, process and write one batch at a time; a global sort has to hold
+    // every batch until the end
+    let mut writer: Option<output::BatchWriter> = None;
+    let mut stats_writer: Option<output::BatchWriter> = None;
+    let mut unsorted_batches = Vec::new();
+    let mut seen_batches = false;
+    // --dry-run: statistics per text column instead of output
+    let mut dry_run_stats: Vec<(String, stats::ParseStats)> =
+        text_columns.iter().map(|col| (col.clone(), stats::ParseStats::default())).collect();
+
+    let run = Run {
+        text_columns: &text_columns,
+        pageid_column: pageid_column.as_deref(),
+        title_column: title_column.as_deref(),
+        pool: &pool,
+        explode,
+        args: &args,
+        options_hash: options_hash.as_deref(),
+        output_schema: &output_schema,
+    };
+    let text_column_names: Vec<&str> = text_columns.iter().map(String::as_str).collect();
+    let progress = progress::row_progress(total_rows, args.verbose);
+    for batch in reader {
+        let batch = decompress::decode_text_columns(&batch?, &text_column_names, args.decompress, args.on_invalid)?;
+        let input_rows = batch.num_rows() as u64;
+        if !seen_batches {
+            for col in &text_columns {
+                check_input_mode(&batch, col, args.input_is_html);
+            }
+            seen_batches = true;
+        }
 
-    for batch in processed_batches {
-        writer.write(&batch)?;
+        // --page-ids: drop every other row before parsing
+        let batch = match (&page_filter, &pageid_column) {
+            (Some(filter), Some(col)) => filter.select(&batch, col)?,
+            _ => batch,
+        };
+        // Incremental mode: drop unchanged rows or mark them for passthrough
+        let (batch, changed) = match (since, &timestamp_column) {
+            (Some(since), Some(col)) => incremental::select_changed(&batch, col, since, args.unchanged_action)?,
+            _ => (batch, None),
+        };
+        let (processed, row_stats) = run.process_batch(&batch, &mut sample, changed.as_ref())?;
+        let processed = schema::coerce_batch(&processed, &output_schema)?;
+        progress.inc(input_rows);
+        if let (Some(row_stats), Some(stats_path)) = (row_stats, &args.stats_output) {
+            if stats_writer.is_none() {
+                println!("Writing stats file: {}", stats_path);
+                stats_writer = Some(output::BatchWriter::create(
+                    stats_path,
+                    jsonl::OutputFormat::Parquet,
+                    &row_stats.schema(),
+                    None,
+                    false,
+                    &[],
+                    compression,
+                )?);
+            }
+            if let Some(stats_writer) = stats_writer.as_mut() {
+                stats_writer.write(&row_stats)?;
+            }
+        }
+
+        if args.dry_run {
+            for (col, col_stats) in dry_run_stats.iter_mut() {
+                let texts = string_column(&processed, &parsed_column_name(&args, col))?;
+                let statuses = string_column(&processed, &derived_column_name(&text_columns, col, "parse_status"))?;
+                col_stats.record(texts, statuses);
+            }
+            continue;
+        }
+        if args.global_sort {
+            unsorted_batches.push(processed);
+            continue;
+        }
+        let processed = match &args.sort_by {
+            Some(column) => sort::sort_batch(&processed, column)?,
+            None => processed,
+        };
+        if writer.is_none() {
+            writer = Some(create_writer()?);
+        }
+        if let Some(writer) = writer.as_mut() {
+            writer.write(&processed)?;
+        }
+    }
+    progress.finish();
+    if let Some(stats_writer) = stats_writer {
+        stats_writer.finish()?;
+    }
+
+    if !seen_batches {
+        println!("No data found in input file");
+        return Ok(());
+    }
+
+    if args.dry_run {
+        for (col, col_stats) in &dry_run_stats {
+            println!("Dry run statistics for {}:", col);
+            println!("{}", col_stats);
+        }
+        if args.report_unparseable_sample > 0 {
+            sample.write(&args.unparseable_sample_path)?;
+        }
+        println!("Dry run complete: no output written");
+        return Ok(());
+    }
+
+    if let (true, Some(column)) = (args.global_sort, &args.sort_by) {
+        let mut global_writer = create_writer()?;
+        global_writer.write(&sort::sort_all(&unsorted_batches, column)?)?;
+        writer = Some(global_writer);
     }
 
-    writer.close()?;
+    if let Some(writer) = writer {
+        let written_rows = writer.finish()?;
+        if args.verify_output {
+            storage::verify_output(output_path, written_rows, &output_schema)?;
+        }
+    }
+
+    if args.report_unparseable_sample > 0 {
+        sample.write(&args.unparseable_sample_path)?;
+    }
     println!("Processing complete!");
 
     Ok(())
 }
 
-fn process_single_column_batch(
-    batch: &RecordBatch,
-    text_column: &str,
-    output_text_column: &str,
-    pageid_column: Option<&str>,
-    title_column: Option<&str>,
-    skip_lists: bool,
-    timeout: u64,
-    output_schema: &Arc<Schema>,
-) -> Result<RecordBatch> {
-    // Get the text column
-    let text_array = batch
-        .column_by_name(text_column)
-        .ok_or_else(|| anyhow::anyhow!("Text column '{}' not found", text_column))?
+/// `--stdin`: parse one article from stdin and print its text to stdout
+fn parse_stdin(args: &Args) -> Result<()> {
+    let mut wikitext = String::new();
+    std::io::stdin()
+        .read_to_string(&mut wikitext)
+        .map_err(|e| anyhow::anyhow!("Could not read stdin: {}", e))?;
+    match parse_text(&wikitext, &parse_options(args), args.input_is_html).0 {
+        Ok(text) => {
+            println!("{}", text);
+            Ok(())
+        }
+        Err(reason) => anyhow::bail!("Article skipped: {}", reason.as_str()),
+    }
+}
+
+/// Parse options from the command line
+fn parse_options(args: &Args) -> parser::ParseOptions {
+    let mut options = parser::ParseOptions::from(&args.parse);
+    // sections-json and --emit-section-path find section boundaries by their Markdown heading prefixes
+    if args.output_format == jsonl::OutputFormat::SectionsJson || args.emit_section_path {
+        options.heading_style = parser::HeadingStyle::Markdown;
+    }
+    options
+}
+
+/// Output column of a text column's parsed text: `--output-column`, or `<col>_parsed`
+fn parsed_column_name(args: &Args, text_column: &str) -> String {
+    args.output_column.clone().unwrap_or_else(|| format!("{}_parsed", text_column))
+}
+
+/// A processed string column by name
+fn string_column<'a>(batch: &'a RecordBatch, name: &str) -> Result<&'a StringArray> {
+    batch
+        .column_by_name(name)
+        .ok_or_else(|| anyhow::anyhow!("Column '{}' not found", name))?
         .as_any()
         .downcast_ref::<StringArray>()
-        .ok_or_else(|| anyhow::anyhow!("Text column is not a StringArray"))?;
-
-    // Get optional page ID and title for logging
-    let pageid_array = pageid_column.and_then(|col| {
-        batch.column_by_name(col)?.as_any().downcast_ref::<StringArray>()
-    });
-    let title_array = title_column.and_then(|col| {
-        batch.column_by_name(col)?.as_any().downcast_ref::<StringArray>()
-    });
-
-    eprintln!("Processing batch with {} rows", text_array.len());
-
-    // Parse wikitext
-    let parsed_texts: Vec<Option<String>> = (0..text_array.len())
-        .map(|i| {
-            let pid = pageid_array
-                .map(|arr| if arr.is_null(i) { "unknown".to_string() } else { arr.value(i).to_string() })
-                .unwrap_or_else(|| format!("row_{}", i));
-            let title = title_array
-                .map(|arr| if arr.is_null(i) { "untitled".to_string() } else { arr.value(i).to_string() })
-                .unwrap_or_else(|| "untitled".to_string());
-
-            eprintln!("  [{}] Processing page_id={} title={}", i + 1, pid, title);
-
-            if text_array.is_null(i) {
-                None
+        .ok_or_else(|| anyhow::anyhow!("Column '{}' is not a StringArray", name))
+}
+
+/// What every batch of a run is processed with; per-run settings go here
+/// (or into `Args`/`ParseOptions`) rather than into `process_batch`'s signature
+#[derive(Clone, Copy)]
+struct Run<'a> {
+    text_columns: &'a [String],
+    pageid_column: Option<&'a str>,
+    title_column: Option<&'a str>,
+    pool: &'a pool::ParsePool,
+    explode: Option<Explode>,
+    args: &'a Args,
+    options_hash: Option<&'a str>,
+    output_schema: &'a Arc<Schema>,
+}
+
+impl Run<'_> {
+    fn process_batch(
+        &self,
+        batch: &RecordBatch,
+        sample: &mut sample::FailureSample,
+        changed: Option<&BooleanArray>,
+    ) -> Result<(RecordBatch, Option<RecordBatch>)> {
+        let Run { text_columns, pageid_column, title_column, pool, explode, args, options_hash, output_schema } = *self;
+        // Get optional page ID and title for logging
+        // Integer IDs are stringified; the output keeps the source columns unchanged
+        let id_strings = |column: Option<&str>| {
+            column
+                .and_then(|col| Some((col, batch.column_by_name(col)?)))
+                .map(|(col, array)| columns::string_values(array, col))
+                .transpose()
+        };
+        let pageid_strings = id_strings(pageid_column)?;
+        let title_strings = id_strings(title_column)?;
+        let pageid_array = pageid_strings.as_ref();
+        let title_array = title_strings.as_ref();
+
+        if args.verbose {
+            eprintln!("Processing batch with {} rows", batch.num_rows());
+        }
+
+        // Parsed text, simhash, quality, sortkey and chunk/paragraph index arrays by output column name
+        let mut generated: HashMap<String, ArrayRef> = HashMap::new();
+        // Source row of every output row when articles are exploded into chunks or paragraphs
+        let mut row_indices: Option<UInt32Array> = None;
+        // --stats-output: per-article metrics in a separate table (main checks the page ID column)
+        let mut row_stats = match (&args.stats_output, pageid_column) {
+            (Some(_), Some(col)) => {
+                let page_ids = batch.column_by_name(col).ok_or_else(|| anyhow::anyhow!("Column '{}' not found", col))?;
+                Some(stats::RowStats::new(col, page_ids))
+            }
+            _ => None,
+        };
+
+        for text_column in text_columns {
+            let text_array = batch
+                .column_by_name(text_column)
+                .ok_or_else(|| anyhow::anyhow!("Text column '{}' not found", text_column))?
+                .as_any()
+                .downcast_ref::<StringArray>()
+                .ok_or_else(|| anyhow::anyhow!("Text column '{}' is not a StringArray", text_column))?;
+
+            // Parse wikitext; rows unchanged since --since (passthrough mode) keep their source text
+            let inputs: Vec<Option<&str>> = text_array.iter().collect();
+            let to_parse = incremental::inputs_to_parse(&inputs, changed);
+            let label = |i: usize| {
+                let pid = pageid_array
+                    .map(|arr| if arr.is_null(i) { "unknown".to_string() } else { arr.value(i).to_string() })
+                    .unwrap_or_else(|| format!("row_{}", i));
+                let title = title_array
+                    .map(|arr| if arr.is_null(i) { "untitled".to_string() } else { arr.value(i).to_string() })
+                    .unwrap_or_else(|| "untitled".to_string());
+                format!("{} for page_id={} title={}", text_column, pid, title)
+            };
+            let (mut results, parser_warnings) = pool.parse_all(&to_parse, label);
+            status::report_too_large(&results, args.parse.max_output_bytes, label);
+            incremental::pass_through_unchanged(&mut results, &inputs, changed);
+            let truncated = status::truncate_results(&mut results, args.parse.max_output_chars, changed);
+
+            for (i, (input, result)) in inputs.iter().zip(&results).enumerate() {
+                if let (Some(input), Some(result)) = (input, result) {
+                    let pid = pageid_array.and_then(|arr| if arr.is_null(i) { None } else { Some(arr.value(i)) });
+                    sample.record(pid, text_column, input, result);
+                }
+            }
+
+            // Skipped articles get a null text; the reason goes to the parse_status column
+            let statuses = status::status_array(&results, changed);
+            let skip_flags = args.flag_skips.then(|| (status::skipped_array(&results), truncated));
+            let warnings = if args.report_warnings {
+                Some(status::warning_counts(&parser_warnings, &results, changed, label))
+            } else if row_stats.is_some() {
+                Some(status::silent_warning_counts(&parser_warnings, &results, changed))
             } else {
-                let result = if timeout == 0 {
-                    parser::parse_wikitext(text_array.value(i), skip_lists)
-                } else {
-                    parse_wikitext_with_timeout(text_array.value(i), skip_lists, timeout)
-                };
-                eprintln!("  [{}] Done processing page_id={}", i + 1, pid);
-                Some(result)
+                None
+            };
+            let parsed_texts = status::texts(results);
+            if let (Some(row_stats), Some(warnings)) = (row_stats.as_mut(), &warnings) {
+                let column_name = |name: &str| derived_column_name(text_columns, text_column, name);
+                row_stats.add(column_name, &inputs, &parsed_texts, &statuses, warnings);
             }
-        })
-        .collect();
 
-    let parsed_text_array: ArrayRef = Arc::new(StringArray::from(parsed_texts));
+            // Explode articles into chunk or paragraph rows; other columns are repeated per row
+            let parsed_texts = match explode {
+                Some(explode) => {
+                    let exploded = explode_rows(parsed_texts, explode);
+                    row_indices = Some(exploded.rows);
+                    generated.insert(explode.index_column().to_string(), Arc::new(exploded.indices));
+                    if let Some(paths) = exploded.section_paths {
+                        generated.insert("section_path".to_string(), Arc::new(StringArray::from(paths)));
+                    }
+                    exploded.pieces
+                }
+                None => parsed_texts,
+            };
+
+            let statuses = match &row_indices {
+                Some(indices) => take(&statuses, indices, None)?,
+                None => statuses,
+            };
+            generated.insert(derived_column_name(text_columns, text_column, "parse_status"), statuses);
+
+            if let Some((skipped, truncated)) = skip_flags {
+                for (name, flags) in [("was_skipped", skipped), ("was_truncated", truncated)] {
+                    let flags = match &row_indices {
+                        Some(indices) => take(&flags, indices, None)?,
+                        None => flags,
+                    };
+                    generated.insert(derived_column_name(text_columns, text_column, name), flags);
+                }
+            }
 
-    // Build output columns - replace text column with parsed version
-    let output_columns: Vec<ArrayRef> = output_schema
-        .fields()
-        .iter()
-        .map(|field| {
-            if field.name() == output_text_column {
-                Arc::clone(&parsed_text_array)
-            } else {
-                // Find the corresponding column in the input batch
-                let original_name = if field.name() == output_text_column {
-                    text_column
-                } else {
-                    field.name()
+            if let (true, Some(warnings)) = (args.report_warnings, warnings) {
+                let warnings = match &row_indices {
+                    Some(indices) => take(&warnings, indices, None)?,
+                    None => warnings,
                 };
-                Arc::clone(batch.column_by_name(original_name).unwrap())
+                generated.insert(derived_column_name(text_columns, text_column, "parse_warnings"), warnings);
             }
-        })
-        .collect();
 
-    let output_batch = RecordBatch::try_new(Arc::clone(output_schema), output_columns)?;
+            for (suffix, extracted) in args.extract.columns(&inputs) {
+                let extracted = match &row_indices {
+                    Some(indices) => take(&extracted, indices, None)?,
+                    None => extracted,
+                };
+                generated.insert(derived_column_name(text_columns, text_column, suffix), extracted);
+            }
+            if args.emit_simhash {
+                let simhashes: Vec<Option<u64>> =
+                    parsed_texts.iter().map(|text| text.as_deref().map(simhash::simhash)).collect();
+                let name = derived_column_name(text_columns, text_column, "simhash");
+                generated.insert(name, Arc::new(UInt64Array::from(simhashes)));
+            }
+            if args.segment_sentences {
+                let sentences: Vec<Option<String>> = parsed_texts
+                    .iter()
+                    .map(|text| text.as_deref().map(|text| sentences::split_sentences(text).join("\n")))
+                    .collect();
+                let name = derived_column_name(text_columns, text_column, "sentences");
+                generated.insert(name, Arc::new(StringArray::from(sentences)));
+            }
+            if args.quality_score {
+                let scores: Vec<Option<f32>> =
+                    parsed_texts.iter().map(|text| text.as_deref().map(quality::quality_score)).collect();
+                let name = derived_column_name(text_columns, text_column, "quality");
+                generated.insert(name, Arc::new(Float32Array::from(scores)));
+            }
+            generated.insert(parsed_column_name(args, text_column), Arc::new(StringArray::from(parsed_texts)));
+        }
+
+        if let Some(hash) = options_hash {
+            let rows = row_indices.as_ref().map_or(batch.num_rows(), |indices| indices.len());
+            generated.insert("options_hash".to_string(), Arc::new(StringArray::from(vec![hash; rows])));
+        }
+
+        // Build output columns - generated arrays by name, everything else from the input batch
+        let output_columns: Vec<ArrayRef> = output_schema
+            .fields()
+            .iter()
+            .map(|field| {
+                if let Some(array) = generated.get(field.name()) {
+                    return Ok(Arc::clone(array));
+                }
+                let column = batch
+                    .column_by_name(field.name())
+                    .ok_or_else(|| anyhow::anyhow!("Column '{}' not found", field.name()))?;
+                match &row_indices {
+                    Some(indices) => Ok(take(column, indices, None)?),
+                    None => Ok(Arc::clone(column)),
+                }
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let output_batch = RecordBatch::try_new(Arc::clone(output_schema), output_columns)?;
+        let row_stats = row_stats.map(stats::RowStats::finish).transpose()?;
+
+        Ok((output_batch, row_stats))
+    }
+}
+
+/// How articles are split into several output rows
+#[derive(Clone, Copy, Debug)]
+enum Explode {
+    /// Overlapping word-aligned chunks (`--chunk-size`)
+    Chunks(chunk::ChunkOptions),
+    /// One row per paragraph (`--explode-paragraphs`), optionally with the
+    /// headings enclosing it (`--emit-section-path`)
+    Paragraphs { section_paths: bool },
+}
+
+impl Explode {
+    fn flag(self) -> &'static str {
+        match self {
+            Explode::Chunks(_) => "--chunk-size",
+            Explode::Paragraphs { .. } => "--explode-paragraphs",
+        }
+    }
+
+    /// Output column holding each row's position within its article
+    fn index_column(self) -> &'static str {
+        match self {
+            Explode::Chunks(_) => "chunk_index",
+            Explode::Paragraphs { .. } => "paragraph_index",
+        }
+    }
+}
 
-    Ok(output_batch)
+/// Articles split into one row per chunk or paragraph
+struct Exploded {
+    /// Source row of every piece
+    rows: UInt32Array,
+    pieces: Vec<Option<String>>,
+    /// Each piece's index within its article
+    indices: UInt32Array,
+    /// Headings enclosing each paragraph (`--emit-section-path`)
+    section_paths: Option<Vec<Option<String>>>,
+}
+
+/// Split each parsed text into chunks or paragraphs, one row per piece
+/// With chunking, null or empty texts keep a single row with a null index;
+/// with paragraphs they produce no rows
+fn explode_rows(parsed_texts: Vec<Option<String>>, explode: Explode) -> Exploded {
+    let mut row_indices = Vec::new();
+    let mut pieces = Vec::new();
+    let mut piece_indices = Vec::new();
+    let mut section_paths = Vec::new();
+
+    for (row, text) in parsed_texts.into_iter().enumerate() {
+        // (section path, piece); paths are only tracked with --emit-section-path
+        let text_pieces: Vec<(Option<String>, String)> = match explode {
+            Explode::Chunks(options) => text
+                .as_deref()
+                .map(|text| chunk::chunk_text(text, options).into_iter().map(|piece| (None, piece)).collect()),
+            Explode::Paragraphs { section_paths: false } => {
+                text.as_deref().map(|text| chunk::paragraphs(text).into_iter().map(|piece| (None, piece)).collect())
+            }
+            Explode::Paragraphs { section_paths: true } => text.as_deref().map(sections::paragraphs_with_paths),
+        }
+        .unwrap_or_default();
+        if text_pieces.is_empty() {
+            if let Explode::Chunks(_) = explode {
+                row_indices.push(row as u32);
+                pieces.push(text);
+                piece_indices.push(None);
+            }
+            continue;
+        }
+        for (index, (path, piece)) in text_pieces.into_iter().enumerate() {
+            row_indices.push(row as u32);
+            pieces.push(Some(piece));
+            piece_indices.push(Some(index as u32));
+            section_paths.push(path);
+        }
+    }
+
+    Exploded {
+        rows: UInt32Array::from(row_indices),
+        pieces,
+        indices: UInt32Array::from(piece_indices),
+        section_paths: matches!(explode, Explode::Paragraphs { section_paths: true }).then_some(section_paths),
+    }
 }
@@ -1,16 +1,385 @@
-use parse_wiki_text::{Configuration, Node};
+use parse_wiki_text::{
+    DefinitionListItem, DefinitionListItemType, ListItem, Node, Parameter, TableCaption, TableCellType, TableRow, Warning,
+};
 use regex::Regex;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::LazyLock;
+use unicode_normalization::UnicodeNormalization as _;
+use crate::configuration;
+
+/// Canonical dash form used by `--normalize-dashes`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum DashStyle {
+    /// Em-dash (—), the Russian punctuation convention
+    Em,
+    /// En-dash (–)
+    En,
+    /// ASCII hyphen-minus (-)
+    Hyphen,
+}
+
+impl DashStyle {
+    fn as_str(self) -> &'static str {
+        match self {
+            DashStyle::Em => "—",
+            DashStyle::En => "–",
+            DashStyle::Hyphen => "-",
+        }
+    }
+}
+
+/// Unicode normalization form applied to the final text (`--normalize-unicode`)
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum UnicodeForm {
+    /// Leave the text as extracted
+    #[default]
+    None,
+    /// Canonical composition ("е" + U+0308 → "ё")
+    Nfc,
+    /// Compatibility composition, also folding ligatures, full-width forms and the like ("ﬁ" → "fi")
+    Nfkc,
+}
+
+/// Which paragraphs count as duplicates for `ParseOptions::dedup_paragraphs`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ParagraphDedup {
+    /// Identical text
+    Exact,
+    /// Identical once runs of whitespace are collapsed
+    Whitespace,
+}
+
+/// How bullet, numbered and definition lists are rendered
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum ListStyle {
+    /// Join list items with spaces into the surrounding paragraph
+    #[default]
+    Flatten,
+    /// One item per line ("- item" / "1. item"), nested lists indented two spaces per level
+    Markdown,
+    /// Remove lists entirely
+    Skip,
+}
+
+/// How section headings are rendered
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum HeadingStyle {
+    /// Heading text only
+    #[default]
+    Plain,
+    /// ATX prefix matching the level ("## Section", "### Subsection")
+    Markdown,
+}
+
+/// How wikitables are rendered
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum TableStyle {
+    /// Remove tables entirely
+    #[default]
+    Skip,
+    /// One line per row, cells separated by tabs
+    Text,
+    /// GitHub-style pipe table; a first row of header cells becomes the table header
+    Markdown,
+}
+
+/// What happens to `<ref>` citation text
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum RefStyle {
+    /// Remove citations
+    #[default]
+    Drop,
+    /// Keep citation text where the ref appears
+    Inline,
+    /// Replace each ref with "[N]" and append the numbered citations at the end
+    Footnote,
+}
+
+/// What internal `[[target|text]]` links become
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum LinkStyle {
+    /// Display text only
+    #[default]
+    Text,
+    /// Linked article title
+    Target,
+    /// The link re-emitted as `[[target|text]]` (`[[target]]` when both are equal)
+    Wiki,
+}
+
+/// Wiki language edition; selects the parser's namespaces and link trail
+/// (`configuration::configuration`) and the month names and date format
+/// used for template expansion
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum Language {
+    #[default]
+    Ru,
+    En,
+    De,
+}
+
+/// Options controlling how wikitext is turned into plain text
+#[derive(Clone, Debug, Default)]
+pub struct ParseOptions {
+    /// How lists are rendered (flattened into the paragraph, Markdown lines, or skipped)
+    pub list_style: ListStyle,
+    /// How headings are rendered (plain text or Markdown `#` prefixes by level)
+    pub heading_style: HeadingStyle,
+    /// Whether tables are skipped, flattened into tab-separated rows, or rendered as Markdown tables
+    pub tables: TableStyle,
+    /// Whether `<ref>` citations are dropped, kept inline, or appended as numbered footnotes
+    pub keep_refs: RefStyle,
+    /// Whether internal links become their display text, their target title, or `[[target|text]]`
+    pub links: LinkStyle,
+    /// Namespace prefixes (without the colon, case-insensitive) of links
+    /// dropped entirely, display text included (empty = none)
+    pub drop_link_namespaces: Vec<String>,
+    /// Keep `&nbsp;` as a non-breaking space (U+00A0) instead of a regular space
+    pub keep_nbsp: bool,
+    /// Turn `<br>` tags into line breaks and `<p>`/`</p>` into paragraph breaks
+    pub html_breaks: bool,
+    /// Keep the lines of `<poem>` and prefix `<blockquote>` lines with "> ",
+    /// each as a paragraph of its own
+    pub block_formatting: bool,
+    /// Prefix the text of `{{lang|en|...}}`/`{{lang-en|...}}` with its language
+    /// code ("[en] Hello"); without it only the text is kept
+    pub lang_codes: bool,
+    /// Abort an article once its extracted text exceeds this many bytes (0 = no limit)
+    pub max_output_bytes: usize,
+    /// Truncate the final text to this many characters (Unicode scalar values,
+    /// ellipsis included), cutting at a paragraph, sentence or word boundary (0 = no limit)
+    pub max_output_chars: usize,
+    /// Drop paragraphs shorter than this many characters (Unicode scalar
+    /// values, headings included; 0 = keep all)
+    pub min_paragraph_chars: usize,
+    /// Collapse consecutive identical heading paragraphs into one
+    pub collapse_repeated_headings: bool,
+    /// Drop later repeats of a paragraph anywhere in the article, keeping the first
+    pub dedup_paragraphs: Option<ParagraphDedup>,
+    /// Convert standalone punctuation dashes to a single canonical form
+    pub normalize_dashes: Option<DashStyle>,
+    /// Collapse runs of spaces, tabs and non-breaking spaces inside lines to one
+    /// space and trim line ends, keeping paragraph breaks and list indentation
+    pub normalize_whitespace: bool,
+    /// Unicode normalization form of the final text
+    pub normalize_unicode: UnicodeForm,
+    /// Lowercase the final text (full Unicode case mapping, so "Ё" → "ё")
+    pub lowercase: bool,
+    /// Template name patterns dropped entirely, including all arguments
+    /// (case-insensitive, `*` wildcard allowed at either end)
+    pub drop_templates: Vec<String>,
+    /// Template names expanded from their parsed arguments (dates, numbers,
+    /// `convert`, `nowrap`; case-insensitive); other templates are skipped
+    pub expand_templates: Vec<String>,
+    /// Phrases marking a disambiguation lead ("Иван — имя; может означать:");
+    /// a leading sentence with an em-dash and one of these phrases is removed
+    /// together with the list that follows it (empty = disabled)
+    pub disambiguation_markers: Vec<String>,
+    /// Keep only the lead and the first N top-level (level 1-2) sections
+    pub max_sections: Option<usize>,
+    /// Structural section headings ("Примечания", "Ссылки", ...) dropped when
+    /// nothing but other structural paragraphs follows them (empty = none)
+    pub section_names: Vec<String>,
+    /// Prefixes of leaked category lines ("Категория:") treated like the above
+    pub category_prefixes: Vec<String>,
+    /// Language of month names and dates produced from date templates
+    pub language: Language,
+}
+
+impl ParseOptions {
+    /// Short stable fingerprint of the effective options (16 hex digits)
+    /// FNV-1a over the Debug representation, so any option change changes it
+    pub fn fingerprint(&self) -> String {
+        let mut hash: u64 = 0xcbf29ce484222325;
+        for byte in format!("{:?}", self).bytes() {
+            hash ^= u64::from(byte);
+            hash = hash.wrapping_mul(0x100000001b3);
+        }
+        format!("{:016x}", hash)
+    }
+}
+
+/// Maintenance banners and stub markers dropped by default
+pub const DEFAULT_DROP_TEMPLATES: &[&str] = &[
+    "нет источников",
+    "нет ссылок",
+    "нет ссылок в тексте",
+    "rq",
+    "refless",
+    "проверить факты",
+    "плохой перевод",
+    "к удалению",
+    "к улучшению",
+    "к объединению",
+    "к переименованию",
+    "к разделению",
+    "изолированная статья",
+    "orphan",
+    "обновить",
+    "стиль раздела",
+    "заготовка*",
+    "stub",
+    "*-stub",
+];
+
+/// Templates expanded from the node tree by default: every template `expand_template` knows
+pub const DEFAULT_EXPAND_TEMPLATES: &[&str] = &[
+    "сс3",
+    "birth date and age",
+    "birth date",
+    "death date and age",
+    "death date",
+    "start date",
+    "end date",
+    "nowrap",
+    "год",
+    "num",
+    "convert",
+    "конвертация",
+];
+
+/// Deepest heading level that starts a new section for `max_sections`
+pub const TOP_LEVEL_HEADING: u8 = 2;
+
+/// Structural section headings removed when empty, for Russian wikis
+pub const DEFAULT_SECTION_NAMES: &[&str] = &[
+    "Население",
+    "Примечания",
+    "Литература",
+    "Ссылки",
+    "Категория",
+    "См. также",
+    "Источники",
+];
+
+/// Link namespaces (Russian and English names, plus interwiki prefixes)
+/// whose links are dropped entirely by default
+pub const DEFAULT_DROP_LINK_NAMESPACES: &[&str] = &[
+    "Категория",
+    "Category",
+    "Файл",
+    "File",
+    "Изображение",
+    "Image",
+    "Медиа",
+    "Media",
+    "Шаблон",
+    "Template",
+    "Справка",
+    "Help",
+    "Википедия",
+    "Wikipedia",
+    "Портал",
+    "Portal",
+    "Участник",
+    "User",
+    "Обсуждение",
+    "Talk",
+    "wikt",
+    "wiktionary",
+    "commons",
+];
+
+/// Category line prefixes used when none are given
+pub const DEFAULT_CATEGORY_PREFIXES: &[&str] = &["Категория:"];
+
+/// Phrases used by `--trim-leading-disambiguation` when no markers are given
+pub const DEFAULT_DISAMBIGUATION_MARKERS: &[&str] = &["может означать", "может относиться"];
+
+/// Why an article was skipped instead of parsed
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SkipReason {
+    /// Extracted text exceeded `max_output_bytes`
+    TooLarge,
+    /// Parsing took longer than the per-article timeout
+    Timeout,
+}
+
+impl SkipReason {
+    /// Value written to the `parse_status` column
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SkipReason::TooLarge => "too_large",
+            SkipReason::Timeout => "timeout",
+        }
+    }
+}
+
+/// Parsed text of one article, or the reason it was skipped
+pub type ParseResult = Result<String, SkipReason>;
+
+/// Warnings `parse_wiki_text` reports for malformed wikitext, as counts per
+/// kind (`UnrecognizedTagName`, ...) in name order
+pub type ParserWarnings = Vec<(String, usize)>;
+
+/// Parser warnings of a wikitext on their own
+/// Parses the text again, so it is meant for diagnostics rather than every run;
+/// `try_parse_wikitext_with_warnings` counts them during the parse instead
+pub fn parser_warnings(wikitext: &str, language: Language) -> ParserWarnings {
+    let output = configuration::configuration(language).parse(wikitext);
+    count_warnings(&output.warnings)
+}
+
+fn count_warnings(warnings: &[Warning]) -> ParserWarnings {
+    let mut counts: BTreeMap<String, usize> = BTreeMap::new();
+    for warning in warnings {
+        *counts.entry(format!("{:?}", warning.message)).or_default() += 1;
+    }
+    counts.into_iter().collect()
+}
 
 /// Parse wikitext and extract only plain paragraph text
-pub fn parse_wikitext(wikitext: &str, skip_lists: bool) -> String {
-    let config = Configuration::default();
-    let output = config.parse(wikitext);
+/// Paragraphs are separated by blank lines; headings become their own paragraphs.
+/// Skipped articles yield an empty string; use `try_parse_wikitext` to tell them apart
+pub fn parse_wikitext(wikitext: &str, options: &ParseOptions) -> String {
+    try_parse_wikitext(wikitext, options).unwrap_or_default()
+}
+
+/// Like `parse_wikitext`, but reports why an article was skipped
+pub fn try_parse_wikitext(wikitext: &str, options: &ParseOptions) -> ParseResult {
+    try_parse_wikitext_with_warnings(wikitext, options).0
+}
+
+/// Like `try_parse_wikitext`, plus the warnings `parse_wiki_text` reported
+/// while parsing (see `parser_warnings`), without a second parse
+pub fn try_parse_wikitext_with_warnings(wikitext: &str, options: &ParseOptions) -> (ParseResult, ParserWarnings) {
+    let output = configuration::configuration(options.language).parse(wikitext);
+    let warnings = count_warnings(&output.warnings);
+    let nodes = trim_leading_disambiguation(&output.nodes, wikitext, options);
+    let nodes = truncate_sections(nodes, options.max_sections);
 
     // Extract text and split into paragraphs by ParagraphBreak
-    let text = extract_text_from_nodes(&output.nodes, wikitext, skip_lists);
+    let Some(text) = extract_text_from_nodes(nodes, wikitext, options) else {
+        return (Err(SkipReason::TooLarge), warnings);
+    };
+
+    let headings = if options.collapse_repeated_headings {
+        collect_heading_texts(nodes, wikitext, options)
+    } else {
+        HashSet::new()
+    };
+
+    (Ok(postprocess_text(&text, &headings, options)), warnings)
+}
+
+/// Turn extracted text into the final paragraph string
+/// Shared by the wikitext and HTML input paths; `headings` holds the article's
+/// heading texts and is only consulted for `collapse_repeated_headings`
+pub fn postprocess_text(text: &str, headings: &HashSet<String>, options: &ParseOptions) -> String {
+    // Drop leaked maintenance and sort-key templates before any expansion sees their arguments
+    let text = remove_dropped_templates(text, &options.drop_templates);
+    let text = remove_sortkey_templates(&text);
 
     // Expand common templates for dates and numbers
-    let expanded_text = expand_common_templates(&text);
+    let expanded_text = expand_common_templates(&text, options.language);
 
     // Remove image markup fragments
     let cleaned_text = remove_image_fragments(&expanded_text);
@@ -22,95 +391,736 @@ pub fn parse_wikitext(wikitext: &str, skip_lists: bool) -> String {
         .collect();
 
     // Remove empty sections (headings with no content after them)
-    let cleaned_paragraphs = remove_empty_sections(&paragraphs);
+    let mut cleaned_paragraphs = remove_empty_sections(&paragraphs, &options.section_names, &options.category_prefixes);
+
+    // Drop paragraphs duplicated by template expansion or clone/diff text
+    if let Some(dedup) = options.dedup_paragraphs {
+        cleaned_paragraphs = dedup_paragraphs(cleaned_paragraphs, dedup);
+    }
+
+    // Drop junk fragments too short to be prose
+    if options.min_paragraph_chars > 0 {
+        cleaned_paragraphs.retain(|p| p.chars().count() >= options.min_paragraph_chars);
+    }
+
+    // Collapse headings that appear twice in a row (node + leaked template copy)
+    if options.collapse_repeated_headings {
+        cleaned_paragraphs = collapse_repeated_headings(cleaned_paragraphs, headings);
+    }
 
-    cleaned_paragraphs.join("\n\n")
+    let result = cleaned_paragraphs.join("\n\n");
+    let result = if options.normalize_whitespace { normalize_whitespace(&result) } else { result };
+
+    let result = match options.normalize_dashes {
+        Some(style) => normalize_dashes(&result, style),
+        None => result,
+    };
+
+    let result = match options.normalize_unicode {
+        UnicodeForm::None => result,
+        UnicodeForm::Nfc => result.nfc().collect(),
+        UnicodeForm::Nfkc => result.nfkc().collect(),
+    };
+    let result = if options.lowercase { result.to_lowercase() } else { result };
+
+    if options.max_output_chars > 0 {
+        truncate_chars(&result, options.max_output_chars)
+    } else {
+        result
+    }
 }
 
-/// Remove image markup fragments that leak through
-fn remove_image_fragments(text: &str) -> String {
-    let mut result = text.to_string();
+/// Skip a leading disambiguation sentence and the list immediately after it
+/// The lead must precede the first list without any paragraph break or heading
+/// in between, contain an em-dash, and contain one of the configured markers
+fn trim_leading_disambiguation<'a>(nodes: &'a [Node<'a>], wikitext: &str, options: &ParseOptions) -> &'a [Node<'a>] {
+    if options.disambiguation_markers.is_empty() {
+        return nodes;
+    }
+
+    let list_index = nodes.iter().position(|node| {
+        matches!(
+            node,
+            Node::UnorderedList { .. }
+                | Node::OrderedList { .. }
+                | Node::DefinitionList { .. }
+                | Node::ParagraphBreak { .. }
+                | Node::Heading { .. }
+        )
+    });
+    let list_index = match list_index {
+        Some(index) if !matches!(nodes[index], Node::ParagraphBreak { .. } | Node::Heading { .. }) => index,
+        _ => return nodes,
+    };
+
+    let lead = match extract_text_from_nodes(&nodes[..list_index], wikitext, options) {
+        Some(lead) => lead.to_lowercase(),
+        None => return nodes,
+    };
+    let is_disambiguation = lead.contains('—')
+        && options
+            .disambiguation_markers
+            .iter()
+            .any(|marker| lead.contains(&marker.to_lowercase()));
+
+    if is_disambiguation {
+        &nodes[list_index + 1..]
+    } else {
+        nodes
+    }
+}
+
+/// Cut the node list at the heading that would start section N+1
+/// Only top-level (level 1-2) headings count; subsections stay with their parent
+fn truncate_sections<'a>(nodes: &'a [Node<'a>], max_sections: Option<usize>) -> &'a [Node<'a>] {
+    let Some(max_sections) = max_sections else {
+        return nodes;
+    };
+
+    let mut sections = 0;
+    for (index, node) in nodes.iter().enumerate() {
+        if let Node::Heading { level, .. } = node {
+            if *level <= TOP_LEVEL_HEADING {
+                sections += 1;
+                if sections > max_sections {
+                    return &nodes[..index];
+                }
+            }
+        }
+    }
+    nodes
+}
+
+/// Collapse intra-line whitespace runs (NBSP included) to single spaces and
+/// trim line ends; paragraph breaks and the leading indentation of nested
+/// Markdown list items are kept, blank lines inside a paragraph are dropped
+fn normalize_whitespace(text: &str) -> String {
+    text.split("\n\n")
+        .map(|paragraph| {
+            paragraph
+                .lines()
+                .filter(|line| !line.trim().is_empty())
+                .map(|line| {
+                    let content = line.trim_start();
+                    let indent = " ".repeat(line[..line.len() - content.len()].chars().count());
+                    format!("{}{}", indent, collapse_whitespace(content))
+                })
+                .collect::<Vec<_>>()
+                .join("\n")
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+/// Appended to text cut by `truncate_chars`
+const TRUNCATION_MARK: char = '…';
+
+/// Cut text longer than `max_chars` characters and mark the cut with an ellipsis
+/// The cut goes at the last paragraph or sentence end that keeps at least half
+/// of the allowed length, otherwise at the last word boundary, and only mid-word
+/// when there is none. The result, ellipsis included, never exceeds `max_chars`
+pub fn truncate_chars(text: &str, max_chars: usize) -> String {
+    if text.chars().count() <= max_chars {
+        return text.to_string();
+    }
+    // Room for a space before the ellipsis after a sentence end, plus the ellipsis
+    let budget = max_chars.saturating_sub(2);
+    let head_end = text.char_indices().nth(budget).map_or(text.len(), |(index, _)| index);
+    let head = &text[..head_end];
+    let next_is_space = |index: usize| text[index..].starts_with(char::is_whitespace);
+
+    // Ends of words, and among them the ends of sentences and paragraphs
+    let word_ends: Vec<(usize, char)> = head
+        .char_indices()
+        .filter(|&(index, c)| !c.is_whitespace() && next_is_space(index + c.len_utf8()))
+        .map(|(index, c)| (index + c.len_utf8(), c))
+        .collect();
+    let boundary = word_ends
+        .iter()
+        .rev()
+        .find(|&&(end, c)| matches!(c, '.' | '!' | '?' | '…') || text[end..].starts_with("\n\n"))
+        .filter(|&&(end, _)| head[..end].chars().count() * 2 >= budget);
+
+    match boundary.or(word_ends.last()) {
+        // "Конец фразы. …" keeps the sentence's own punctuation apart from the mark
+        Some(&(end, '.' | '!' | '?' | '…')) => format!("{} {}", &head[..end], TRUNCATION_MARK),
+        Some(&(end, _)) => format!("{}{}", &head[..end], TRUNCATION_MARK),
+        None => {
+            // A single overlong word: cut it, using the space reserved above
+            let end = text.char_indices().nth(max_chars.saturating_sub(1)).map_or(text.len(), |(index, _)| index);
+            format!("{}{}", &text[..end], TRUNCATION_MARK)
+        }
+    }
+}
+
+/// Normalize standalone dashes (hyphen, en-dash, em-dash, minus, ...) to one form
+/// Only dashes surrounded by whitespace are touched, so hyphenated words and
+/// numeric ranges like "1941–1945" stay intact. A dash opening a line is left
+/// alone: it is a Markdown list marker ("- item") or a dialogue dash
+fn normalize_dashes(text: &str, style: DashStyle) -> String {
+    let is_dash = |c: char| matches!(c, '-' | '\u{2010}' | '\u{2012}'..='\u{2015}' | '\u{2212}');
+    let chars: Vec<char> = text.chars().collect();
+    let mut result = String::with_capacity(text.len());
+    // Only indentation seen since the last line break
+    let mut line_start = true;
+
+    for (i, &c) in chars.iter().enumerate() {
+        let standalone = is_dash(c)
+            && !line_start
+            && chars[i - 1].is_whitespace()
+            && (i + 1 == chars.len() || chars[i + 1].is_whitespace());
+        line_start = c == '\n' || (line_start && c.is_whitespace());
+        if standalone {
+            result.push_str(style.as_str());
+        } else {
+            result.push(c);
+        }
+    }
+
+    result
+}
+
+/// Sort-key magic words and templates (`{{DEFAULTSORT:Пушкин, Александр}}`,
+/// `{{сортировка|...}}`); they carry category sorting metadata, not prose
+static SORTKEY_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)\{\{\s*(?:defaultsort|defaultsortkey|defaultcategorysort|сортировка по умолчанию|сортировка)\s*[:|]([^{}]*)\}\}")
+        .unwrap()
+});
+
+/// Sort key of an article from its wikitext, if it declares one
+pub fn extract_sortkey(wikitext: &str) -> Option<String> {
+    SORTKEY_RE
+        .captures(wikitext)
+        .map(|caps| caps[1].trim().to_string())
+        .filter(|key| !key.is_empty())
+}
+
+/// `{{DISPLAYTITLE:...}}` and its Russian and German aliases; an optional
+/// `|noerror`/`|noreplace` argument follows the title
+static DISPLAY_TITLE_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)\{\{\s*(?:displaytitle|показать_заголовок|seitentitel)\s*:([^{}|]*)(?:\|[^{}]*)?\}\}").unwrap()
+});
+
+/// Markup allowed inside a display title: HTML tags and bold/italic quotes
+static DISPLAY_TITLE_MARKUP_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"<[^<>]*>|'{2,}").unwrap());
+
+/// Title an article sets with `{{DISPLAYTITLE:...}}`, as plain text
+/// (`{{DISPLAYTITLE:''Титаник''}}` gives "Титаник")
+pub fn extract_display_title(wikitext: &str) -> Option<String> {
+    let caps = DISPLAY_TITLE_RE.captures(wikitext)?;
+    let title = DISPLAY_TITLE_MARKUP_RE.replace_all(&caps[1], "");
+    let title = collapse_whitespace(&decode_entities(&title, false));
+    (!title.is_empty()).then_some(title)
+}
+
+/// Category links in English, Russian and German (`[[Категория:Города России|Москва]]`);
+/// a leading colon (`[[:Category:...]]`) makes a plain link, which doesn't match
+static CATEGORY_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)\[\[\s*(?:category|категория|kategorie)\s*:([^\[\]|]+)(?:\|[^\[\]]*)?\]\]").unwrap()
+});
+
+/// Categories an article is in, one per line in order of first appearance,
+/// without the namespace and sort key (underscores read as spaces)
+pub fn extract_categories(wikitext: &str) -> Option<String> {
+    let mut categories: Vec<String> = Vec::new();
+    for caps in CATEGORY_RE.captures_iter(wikitext) {
+        let name = collapse_whitespace(&caps[1].replace('_', " "));
+        if !name.is_empty() && !categories.contains(&name) {
+            categories.push(name);
+        }
+    }
+    (!categories.is_empty()).then(|| categories.join("\n"))
+}
+
+/// Redirect at the start of a page (`#REDIRECT [[Цель]]`, `#перенаправление [[Цель]]`,
+/// `#WEITERLEITUNG [[Ziel]]`); the words are those the parser configurations know
+static REDIRECT_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)\A\s*#\s*(?:redirect|перенаправление|перенапр|weiterleitung)\s*:?\s*\[\[([^\[\]|]+)(?:\|[^\[\]]*)?\]\]")
+        .unwrap()
+});
+
+/// Target title of a redirect page (section anchor kept, underscores read as
+/// spaces); `None` for ordinary articles
+pub fn extract_redirect_target(wikitext: &str) -> Option<String> {
+    let caps = REDIRECT_RE.captures(wikitext)?;
+    let target = collapse_whitespace(&caps[1].replace('_', " "));
+    (!target.is_empty()).then_some(target)
+}
+
+/// Remove leaked sort-key templates so their argument never reaches the text
+fn remove_sortkey_templates(text: &str) -> String {
+    if !text.contains("{{") {
+        return text.to_string();
+    }
+    SORTKEY_RE.replace_all(text, "").to_string()
+}
+
+/// Templates linking to an article that only exists in another language edition
+/// (`{{не переведено 5|Название|Текст ссылки|en|Title}}`): the first positional
+/// argument is the Russian title, the second an optional display text
+const INTERWIKI_LINK_TEMPLATES: &[&str] = &[
+    "не переведено",
+    "не переведено 2",
+    "не переведено 3",
+    "не переведено 4",
+    "не переведено 5",
+    "нп",
+    "нп2",
+    "нп3",
+    "нп4",
+    "нп5",
+    "iw",
+];
+
+/// Language code and text nodes of a foreign-language span template:
+/// `{{lang|en|Hello}}` (code, text) or `{{lang-en|Hello}}` (text)
+fn lang_template<'a>(name: &str, parameters: &'a [Parameter<'a>]) -> Option<(String, &'a [Node<'a>])> {
+    let mut positional = parameters.iter().filter(|parameter| parameter.name.is_none());
+    let code = match name.strip_prefix("lang") {
+        Some("") => template_name(&positional.next()?.value),
+        Some(code) => code.strip_prefix('-')?.to_string(),
+        None => return None,
+    };
+    if code.is_empty() || !code.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+        return None;
+    }
+    Some((code, positional.next()?.value.as_slice()))
+}
+
+/// Display nodes of an interwiki link template: the second positional argument
+/// if present and non-blank, otherwise the first
+fn interwiki_display_nodes<'a>(parameters: &'a [Parameter<'a>]) -> Option<&'a [Node<'a>]> {
+    let mut positional = parameters.iter().filter(|parameter| parameter.name.is_none());
+    let title = positional.next();
+    let display = positional.next();
+
+    let is_blank = |nodes: &[Node]| {
+        nodes.iter().all(|node| matches!(node, Node::Text { value, .. } if value.trim().is_empty()))
+    };
+    [display, title]
+        .into_iter()
+        .flatten()
+        .map(|parameter| parameter.value.as_slice())
+        .find(|nodes| !is_blank(nodes))
+}
+
+/// Get the normalized name of a template from its name nodes
+fn template_name(name: &[Node]) -> String {
+    let raw: String = name
+        .iter()
+        .filter_map(|node| match node {
+            Node::Text { value, .. } => Some(*value),
+            _ => None,
+        })
+        .collect();
+    normalize_template_name(&raw)
+}
+
+/// Lowercase a template name and collapse underscores/whitespace to single spaces
+fn normalize_template_name(name: &str) -> String {
+    name.replace('_', " ")
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+/// Check a normalized template name against the drop patterns
+fn is_dropped_template(name: &str, patterns: &[String]) -> bool {
+    patterns.iter().any(|pattern| {
+        let pattern = normalize_template_name(pattern);
+        let core = pattern.trim_matches('*');
+        if core.is_empty() {
+            return false;
+        }
+        match (pattern.starts_with('*'), pattern.ends_with('*')) {
+            (true, true) => name.contains(core),
+            (true, false) => name.ends_with(core),
+            (false, true) => name.starts_with(core),
+            (false, false) => name == core,
+        }
+    })
+}
+
+/// Remove `{{...}}` spans of drop-listed templates that leaked into the flattened text
+pub fn remove_dropped_templates(text: &str, patterns: &[String]) -> String {
+    if patterns.is_empty() || !text.contains("{{") {
+        return text.to_string();
+    }
+
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(start) = rest.find("{{") {
+        result.push_str(&rest[..start]);
+        let candidate = &rest[start..];
+
+        // Template name runs up to the first pipe or closing braces
+        let name_end = candidate[2..]
+            .find(['|', '}', '{'])
+            .map(|i| i + 2)
+            .unwrap_or(candidate.len());
+        let name = normalize_template_name(&candidate[2..name_end]);
+
+        match find_template_end(candidate) {
+            Some(end) if is_dropped_template(&name, patterns) => {
+                rest = &candidate[end..];
+            }
+            _ => {
+                result.push_str("{{");
+                rest = &candidate[2..];
+            }
+        }
+    }
+    result.push_str(rest);
+
+    result
+}
 
+/// Remove every `{{...}}` template that leaked into the flattened text, except
+/// those named in `expand_templates`, which are replaced by their expansion
+/// (`expand_common_templates`; dropped if they can't be expanded)
+/// Unbalanced `{{` and single braces are left alone
+pub fn remove_leaked_templates(text: &str, expand_templates: &[String], language: Language) -> String {
+    if !text.contains("{{") {
+        return text.to_string();
+    }
+
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(start) = rest.find("{{") {
+        result.push_str(&rest[..start]);
+        let candidate = &rest[start..];
+        let Some(end) = find_template_end(candidate) else {
+            result.push_str("{{");
+            rest = &candidate[2..];
+            continue;
+        };
+
+        let name_end = candidate[2..end - 2].find(['|', '{']).map_or(end - 2, |i| i + 2);
+        let name = normalize_template_name(&candidate[2..name_end]);
+        if expand_templates.iter().any(|allowed| normalize_template_name(allowed) == name) {
+            // Arguments may hold templates of their own
+            let inner = remove_leaked_templates(&candidate[2..end - 2], expand_templates, language);
+            let expanded = expand_common_templates(&format!("{{{{{}}}}}", inner), language);
+            if !expanded.starts_with("{{") {
+                result.push_str(&expanded);
+            }
+        }
+        rest = &candidate[end..];
+    }
+    result.push_str(rest);
+
+    result
+}
+
+/// Find the byte offset just past the `}}` closing the template starting at `text[0]`
+fn find_template_end(text: &str) -> Option<usize> {
+    let bytes = text.as_bytes();
+    let mut depth = 0usize;
+    let mut i = 0;
+
+    while i + 1 < bytes.len() {
+        if bytes[i] == b'{' && bytes[i + 1] == b'{' {
+            depth += 1;
+            i += 2;
+        } else if bytes[i] == b'}' && bytes[i + 1] == b'}' {
+            depth = depth.saturating_sub(1);
+            i += 2;
+            if depth == 0 {
+                return Some(i);
+            }
+        } else {
+            i += 1;
+        }
+    }
+
+    None
+}
+
+/// Collect the text of all top-level headings
+fn collect_heading_texts(nodes: &[Node], wikitext: &str, options: &ParseOptions) -> HashSet<String> {
+    nodes
+        .iter()
+        .filter_map(|node| match node {
+            Node::Heading { nodes, .. } => extract_text_from_nodes(nodes, wikitext, options),
+            _ => None,
+        })
+        .map(|heading| heading.trim().to_string())
+        .filter(|heading| !heading.is_empty())
+        .collect()
+}
+
+/// Render a heading's text in the given style
+pub(crate) fn format_heading(level: u8, text: &str, style: HeadingStyle) -> String {
+    match style {
+        HeadingStyle::Plain => text.to_string(),
+        HeadingStyle::Markdown => format!("{} {}", "#".repeat(usize::from(level.clamp(1, 6))), text),
+    }
+}
+
+/// Level and text of a Markdown heading paragraph ("## Section" → 2, "Section")
+pub(crate) fn heading_marker(para: &str) -> Option<(u8, &str)> {
+    let hashes = para.len() - para.trim_start_matches('#').len();
+    match para[hashes..].strip_prefix(' ') {
+        Some(text) if (1..=6).contains(&hashes) => Some((hashes as u8, text)),
+        _ => None,
+    }
+}
+
+/// Heading text of a paragraph, without a Markdown `#` prefix if it has one
+fn strip_heading_marker(para: &str) -> &str {
+    heading_marker(para).map_or(para, |(_, text)| text)
+}
+
+/// Collapse consecutive identical heading paragraphs into one
+/// A Markdown heading and a leaked plain copy of it count as identical
+fn collapse_repeated_headings(paragraphs: Vec<String>, headings: &HashSet<String>) -> Vec<String> {
+    let mut result: Vec<String> = Vec::new();
+
+    for para in paragraphs {
+        let heading = strip_heading_marker(&para);
+        let is_repeat = headings.contains(heading)
+            && result.last().is_some_and(|last| strip_heading_marker(last) == heading);
+        if !is_repeat {
+            result.push(para);
+        }
+    }
+
+    result
+}
+
+/// Keep the first occurrence of each paragraph, in order
+fn dedup_paragraphs(paragraphs: Vec<String>, dedup: ParagraphDedup) -> Vec<String> {
+    let mut seen = HashSet::new();
+    paragraphs
+        .into_iter()
+        .filter(|para| match dedup {
+            ParagraphDedup::Exact => seen.insert(para.clone()),
+            ParagraphDedup::Whitespace => seen.insert(collapse_whitespace(para)),
+        })
+        .collect()
+}
+
+/// `[[Файл:...]]` and `[[File:...]]` markup
+/// Bounded to prevent catastrophic backtracking
+static FILE_LINK_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\[\[(?:Файл|File):[^\]]{0,500}\]\]").unwrap());
+
+/// A whole line of image size/position parameters ("130px|мини|...")
+static IMAGE_PARAMS_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?m)^\d+px\|(?:мини|thumb|миниатюра|left|right|center|слева|справа|центр)\|.{0,200}$").unwrap()
+});
+
+/// Standalone image parameter fragments: "130px|мини|слева|...", "альт=...|мини|..."
+static IMAGE_FRAGMENT_RES: LazyLock<[Regex; 3]> = LazyLock::new(|| {
+    [
+        r"(?m)^\s*\d+px\|мини\|(?:слева|справа|центр)?.{0,200}$",
+        r"(?m)^\s*альт=.{0,100}\|мини\|.{0,200}$",
+        r"(?m)^\s*\d+px\|мини$",
+    ]
+    .map(|pattern| Regex::new(pattern).unwrap())
+});
+
+/// Three or more newlines, left behind by removals
+static MULTI_NEWLINE_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\n{3,}").unwrap());
+
+/// Remove image markup fragments that leak through
+pub fn remove_image_fragments(text: &str) -> String {
     // Remove [[Файл:...]] and [[File:...]] markup completely
-    // Use non-greedy match and limit to prevent catastrophic backtracking
-    let file_re = Regex::new(r"\[\[(?:Файл|File):[^\]]{0,500}\]\]").unwrap();
-    result = file_re.replace_all(&result, "").to_string();
+    let mut result = FILE_LINK_RE.replace_all(text, "").to_string();
 
     // Remove image size/position parameters that appear as standalone text
-    // Pattern: size|position|description where size is like "130px", "150px", etc.
-    // Limit line length to prevent catastrophic backtracking
-    let image_params_re = Regex::new(r"(?m)^\d+px\|(?:мини|thumb|миниатюра|left|right|center|слева|справа|центр)\|.{0,200}$").unwrap();
     let lines: Vec<String> = result.lines()
-        .filter(|line| !image_params_re.is_match(line.trim()))
+        .filter(|line| !IMAGE_PARAMS_RE.is_match(line.trim()))
         .map(|s| s.to_string())
         .collect();
     result = lines.join("\n");
 
     // Remove standalone image parameter fragments (size|position|text)
-    // Common patterns: "130px|мини|слева|...", "альт=...|мини|..."
-    // Use bounded quantifiers to prevent catastrophic backtracking
-    let fragment_patterns = vec![
-        r"(?m)^\s*\d+px\|мини\|(?:слева|справа|центр)?.{0,200}$",
-        r"(?m)^\s*альт=.{0,100}\|мини\|.{0,200}$",
-        r"(?m)^\s*\d+px\|мини$",
-    ];
-
-    for pattern in fragment_patterns {
-        let re = Regex::new(pattern).unwrap();
+    for re in IMAGE_FRAGMENT_RES.iter() {
         result = re.replace_all(&result, "").to_string();
     }
 
     // Clean up multiple consecutive newlines left by removals
-    let multi_newline_re = Regex::new(r"\n{3,}").unwrap();
-    result = multi_newline_re.replace_all(&result, "\n\n").to_string();
+    MULTI_NEWLINE_RE.replace_all(&result, "\n\n").to_string()
+}
 
+/// Named HTML entities decoded in text (besides those the wikitext parser already resolves)
+const NAMED_ENTITIES: &[(&str, char)] = &[
+    ("nbsp", '\u{a0}'),
+    ("amp", '&'),
+    ("lt", '<'),
+    ("gt", '>'),
+    ("quot", '"'),
+    ("apos", '\''),
+    ("shy", '\u{ad}'),
+    ("thinsp", '\u{2009}'),
+    ("ensp", '\u{2002}'),
+    ("emsp", '\u{2003}'),
+    ("zwj", '\u{200d}'),
+    ("zwnj", '\u{200c}'),
+    ("lrm", '\u{200e}'),
+    ("rlm", '\u{200f}'),
+    ("ndash", '–'),
+    ("mdash", '—'),
+    ("minus", '−'),
+    ("hellip", '…'),
+    ("laquo", '«'),
+    ("raquo", '»'),
+    ("bdquo", '„'),
+    ("ldquo", '“'),
+    ("rdquo", '”'),
+    ("lsquo", '‘'),
+    ("rsquo", '’'),
+    ("sbquo", '‚'),
+    ("prime", '′'),
+    ("Prime", '″'),
+    ("middot", '·'),
+    ("bull", '•'),
+    ("deg", '°'),
+    ("plusmn", '±'),
+    ("times", '×'),
+    ("divide", '÷'),
+    ("frac12", '½'),
+    ("frac14", '¼'),
+    ("frac34", '¾'),
+    ("sup2", '²'),
+    ("sup3", '³'),
+    ("le", '≤'),
+    ("ge", '≥'),
+    ("ne", '≠'),
+    ("asymp", '≈'),
+    ("infin", '∞'),
+    ("larr", '←'),
+    ("rarr", '→'),
+    ("harr", '↔'),
+    ("uarr", '↑'),
+    ("darr", '↓'),
+    ("sect", '§'),
+    ("para", '¶'),
+    ("copy", '©'),
+    ("reg", '®'),
+    ("trade", '™'),
+    ("euro", '€'),
+    ("pound", '£'),
+    ("yen", '¥'),
+    ("cent", '¢'),
+    ("numero", '№'),
+];
+
+/// Character for an entity body (`mdash`, `#1040`, `#x410`), if known
+fn decode_entity(body: &str) -> Option<char> {
+    if let Some(number) = body.strip_prefix('#') {
+        let code = match number.strip_prefix(['x', 'X']) {
+            Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+            None => number.parse().ok()?,
+        };
+        return char::from_u32(code).filter(|c| *c != '\0');
+    }
+    NAMED_ENTITIES.iter().find(|(name, _)| *name == body).map(|(_, c)| *c)
+}
+
+/// A decoded non-breaking space becomes a regular space unless `keep_nbsp`
+fn nbsp_character(character: char, keep_nbsp: bool) -> char {
+    if character == '\u{a0}' && !keep_nbsp {
+        ' '
+    } else {
+        character
+    }
+}
+
+/// Decode named and numeric HTML entities (`&nbsp;`, `&mdash;`, `&#1040;`, `&#x410;`)
+/// Unknown or malformed entities are left as they are
+pub fn decode_entities(text: &str, keep_nbsp: bool) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(amp) = rest.find('&') {
+        result.push_str(&rest[..amp]);
+        rest = &rest[amp..];
+        // Entity bodies are short; don't scan far for the closing semicolon
+        let decoded = rest[1..]
+            .char_indices()
+            .take(32)
+            .find(|(_, c)| !c.is_ascii_alphanumeric() && *c != '#')
+            .filter(|(_, c)| *c == ';')
+            .and_then(|(semicolon, _)| Some((decode_entity(&rest[1..1 + semicolon])?, semicolon + 2)));
+        match decoded {
+            Some((character, len)) => {
+                result.push(nbsp_character(character, keep_nbsp));
+                rest = &rest[len..];
+            }
+            None => {
+                result.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    result.push_str(rest);
     result
 }
 
-/// Expand common Russian Wikipedia templates for dates and numbers
-fn expand_common_templates(text: &str) -> String {
+/// Leaked `{{СС3|18.1.1918}}`
+static CC3_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\{\{СС3\|(\d+)\.(\d+)\.(\d+)\}\}").unwrap());
+
+/// Leaked `{{Birth date|1918|1|18}}` and the rest of `YMD_DATE_TEMPLATES`
+static YMD_DATE_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(&format!(
+        r"\{{\{{(?i:{})\s*\|\s*(\d{{1,4}})\s*(?:\|\s*(\d{{1,2}})\s*)?(?:\|\s*(\d{{1,2}})\s*)?(?:\|[^{{}}]{{0,100}})?\}}\}}",
+        YMD_DATE_TEMPLATES.join("|")
+    ))
+    .unwrap()
+});
+
+static NOWRAP_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\{\{(?i:nowrap)\|([^{}|]{0,200})\}\}").unwrap());
+static YEAR_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\{\{год\|(\d{3,4})\}\}").unwrap());
+static NUM_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\{\{num\|(\d+)\}\}").unwrap());
+/// Leaked `{{lang|en|Hello}}` / `{{lang-en|Hello}}`; the text is kept, the code dropped
+static LANG_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"\{\{\s*(?i:lang)(?:\s*\|\s*|-)[A-Za-z0-9-]{1,20}\s*\|([^{}|=]{0,500})\}\}").unwrap()
+});
+/// Leaked `{{convert|10|km|mi}}` / `{{конвертация|...}}`; arguments are split by `expand_template`
+static CONVERT_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\{\{\s*(?i:convert|конвертация)\s*\|([^{}]{0,300})\}\}").unwrap());
+
+/// Expand common date and number templates that leaked into the text
+/// Date templates are recognized for every language; month names and the
+/// date format follow `language`
+pub fn expand_common_templates(text: &str, language: Language) -> String {
     let mut result = text.to_string();
 
     // First, expand specific templates we want to preserve as text
 
     // Template {{СС3|18.1.1918}} → "18 января 1918"
     // This handles date templates with day.month.year format
-    let date_re = Regex::new(r"\{\{СС3\|(\d+)\.(\d+)\.(\d+)\}\}").unwrap();
-    result = date_re.replace_all(&result, |caps: &regex::Captures| {
-        let day = &caps[1];
-        let month_num: u32 = caps[2].parse().unwrap_or(0);
-        let year = &caps[3];
-
-        let month_name = match month_num {
-            1 => "января",
-            2 => "февраля",
-            3 => "марта",
-            4 => "апреля",
-            5 => "мая",
-            6 => "июня",
-            7 => "июля",
-            8 => "августа",
-            9 => "сентября",
-            10 => "октября",
-            11 => "ноября",
-            12 => "декабря",
-            _ => "",
-        };
+    result = CC3_RE.replace_all(&result, |caps: &regex::Captures| {
+        dmy_date(&caps[1], caps[2].parse().unwrap_or(0), &caps[3], language)
+    }).to_string();
 
-        if month_name.is_empty() {
-            format!("{}.{}.{}", day, month_num, year)
-        } else {
-            format!("{} {} {}", day, month_name, year)
-        }
+    // Templates {{Birth date|1918|1|18}}, {{Start date|1918|1}}, ... → "18 January 1918"
+    // Year, month, day positional arguments; trailing arguments (df=y, a
+    // second date in "... and age") are ignored
+    result = YMD_DATE_RE.replace_all(&result, |caps: &regex::Captures| {
+        ymd_date(&caps[1], caps.get(2).map(|m| m.as_str()), caps.get(3).map(|d| d.as_str()), language)
     }).to_string();
 
+    // Template {{nowrap|text}} → "text"
+    result = NOWRAP_RE.replace_all(&result, "$1").to_string();
+
     // Template {{год|YYYY}} → "YYYY"
-    let year_re = Regex::new(r"\{\{год\|(\d{3,4})\}\}").unwrap();
-    result = year_re.replace_all(&result, "$1").to_string();
+    result = YEAR_RE.replace_all(&result, "$1").to_string();
 
     // Template {{num|###}} → "###"
-    let num_re = Regex::new(r"\{\{num\|(\d+)\}\}").unwrap();
-    result = num_re.replace_all(&result, "$1").to_string();
+    result = NUM_RE.replace_all(&result, "$1").to_string();
+
+    // Templates {{lang|en|Hello}}, {{lang-en|Hello}} → "Hello"
+    result = LANG_RE.replace_all(&result, |caps: &regex::Captures| caps[1].trim().to_string()).to_string();
+
+    // Template {{convert|10|km|mi}} → "10 km"; named arguments (abbr=on) are ignored
+    result = CONVERT_RE.replace_all(&result, |caps: &regex::Captures| {
+        let arguments: Vec<String> = caps[1].split('|').filter(|arg| !arg.contains('=')).map(str::to_string).collect();
+        expand_template("convert", &arguments, language).unwrap_or_else(|| caps[0].to_string())
+    }).to_string();
 
     // Note: Additional cleaning (template removal, image fragments, etc.)
     // is handled by the separate clean_parsed binary for better performance
@@ -118,142 +1128,650 @@ fn expand_common_templates(text: &str) -> String {
     result
 }
 
-/// Remove section headings that have no content following them
-fn remove_empty_sections(paragraphs: &[String]) -> Vec<String> {
-    let mut result = Vec::new();
-    let empty_section_names = vec![
-        "Население", "Примечания", "Литература", "Ссылки",
-        "Категория", "См. также", "Источники"
+/// Replacement text for an allow-listed template from its positional arguments
+/// (already extracted to text); None if the template is unknown or its
+/// arguments don't fit, in which case it is skipped like any other template
+fn expand_template(name: &str, arguments: &[String], language: Language) -> Option<String> {
+    let argument = |index: usize| arguments.get(index).map(|value| value.trim()).filter(|value| !value.is_empty());
+
+    match name {
+        // {{СС3|18.1.1918}} → "18 января 1918"
+        "сс3" => {
+            let mut parts = argument(0)?.split('.');
+            let (day, month, year) = (parts.next()?, parts.next()?, parts.next()?);
+            let all_digits = [day, month, year].iter().all(|part| !part.is_empty() && part.bytes().all(|b| b.is_ascii_digit()));
+            if !all_digits || parts.next().is_some() {
+                return None;
+            }
+            Some(dmy_date(day, month.parse().ok()?, year, language))
+        }
+        // {{Birth date|1918|1|18}} → "18 January 1918"; trailing arguments are ignored
+        _ if YMD_DATE_TEMPLATES.contains(&name) => {
+            let year = argument(0).filter(|year| year.bytes().all(|b| b.is_ascii_digit()))?;
+            Some(ymd_date(year, argument(1), argument(2), language))
+        }
+        // {{nowrap|text}}, {{год|1918}}, {{num|5000}} → the argument itself
+        "nowrap" | "год" | "num" => argument(0).map(str::to_string),
+        // {{convert|5|km|mi}} → "5 km"; {{convert|5|-|10|km}} → "5–10 km"
+        "convert" | "конвертация" => {
+            let value = argument(0)?;
+            match argument(1)? {
+                "-" | "–" => Some(format!("{}–{} {}", value, argument(2)?, argument(3)?)),
+                range @ ("to" | "and" | "or") => Some(format!("{} {} {} {}", value, range, argument(2)?, argument(3)?)),
+                unit => Some(format!("{} {}", value, unit)),
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Day.month.year date ({{СС3}}) in the language's format; unknown months stay numeric
+fn dmy_date(day: &str, month_num: u32, year: &str, language: Language) -> String {
+    match month_name(language, month_num, true) {
+        Some(month) => format_date(language, Some(day), month, year),
+        None => format!("{}.{}.{}", day, month_num, year),
+    }
+}
+
+/// Date from year, optional month and optional day arguments; falls back to the year alone
+fn ymd_date(year: &str, month: Option<&str>, day: Option<&str>, language: Language) -> String {
+    let month_num: u32 = month.and_then(|m| m.parse().ok()).unwrap_or(0);
+    let day = day.and_then(|d| d.parse::<u32>().ok()).map(|d| d.to_string());
+
+    match month_name(language, month_num, day.is_some()) {
+        Some(month) => format_date(language, day.as_deref(), month, year),
+        None => year.to_string(),
+    }
+}
+
+/// Template names (case-insensitive) taking year|month|day positional arguments
+const YMD_DATE_TEMPLATES: &[&str] = &[
+    "birth date and age",
+    "birth date",
+    "death date and age",
+    "death date",
+    "start date",
+    "end date",
+];
+
+/// Month names per language: the form used after a day number, and the
+/// standalone form used for month-and-year dates (they differ in Russian)
+fn month_names(language: Language) -> (&'static [&'static str; 12], &'static [&'static str; 12]) {
+    const RU_GENITIVE: [&str; 12] = [
+        "января", "февраля", "марта", "апреля", "мая", "июня",
+        "июля", "августа", "сентября", "октября", "ноября", "декабря",
+    ];
+    const RU: [&str; 12] = [
+        "январь", "февраль", "март", "апрель", "май", "июнь",
+        "июль", "август", "сентябрь", "октябрь", "ноябрь", "декабрь",
     ];
+    const EN: [&str; 12] = [
+        "January", "February", "March", "April", "May", "June",
+        "July", "August", "September", "October", "November", "December",
+    ];
+    const DE: [&str; 12] = [
+        "Januar", "Februar", "März", "April", "Mai", "Juni",
+        "Juli", "August", "September", "Oktober", "November", "Dezember",
+    ];
+    match language {
+        Language::Ru => (&RU_GENITIVE, &RU),
+        Language::En => (&EN, &EN),
+        Language::De => (&DE, &DE),
+    }
+}
+
+/// Name of month 1-12 (None for anything else)
+fn month_name(language: Language, month: u32, with_day: bool) -> Option<&'static str> {
+    let (with_day_names, standalone_names) = month_names(language);
+    let names = if with_day { with_day_names } else { standalone_names };
+    names.get(month.checked_sub(1)? as usize).copied()
+}
 
-    for (i, para) in paragraphs.iter().enumerate() {
-        // Check if this is an empty structural heading
-        let is_empty_section = empty_section_names.iter().any(|&name| {
-            para == name || para.starts_with(&format!("Категория:"))
-        });
-
-        if is_empty_section {
-            // Check if there's content after this heading
-            let has_content_after = i + 1 < paragraphs.len() && {
-                let next = &paragraphs[i + 1];
-                !empty_section_names.iter().any(|&n| next == n || next.starts_with("Категория:"))
+/// Format a date the way the language writes it ("18 января 1918", "18 January 1918", "18. Januar 1918")
+fn format_date(language: Language, day: Option<&str>, month: &str, year: &str) -> String {
+    match (day, language) {
+        (Some(day), Language::De) => format!("{}. {} {}", day, month, year),
+        (Some(day), _) => format!("{} {} {}", day, month, year),
+        (None, _) => format!("{} {}", month, year),
+    }
+}
+
+/// Remove section headings that have no content following them
+/// A structural heading is kept when content follows it anywhere later, even
+/// past other structural headings; the run after the last content is dropped
+fn remove_empty_sections(paragraphs: &[String], section_names: &[String], category_prefixes: &[String]) -> Vec<String> {
+    let is_structural = |para: &str| {
+        section_names.iter().any(|name| strip_heading_marker(para) == name)
+            || category_prefixes.iter().any(|prefix| para.starts_with(prefix.as_str()))
+    };
+    let last_content = paragraphs.iter().rposition(|para| !is_structural(para));
+
+    paragraphs
+        .iter()
+        .enumerate()
+        .filter(|(i, para)| !is_structural(para) || last_content.is_some_and(|last| *i < last))
+        .map(|(_, para)| para.clone())
+        .collect()
+}
+
+/// Render list items as Markdown lines, recursing into lists nested inside items
+fn markdown_list(
+    items: &[ListItem],
+    ordered: bool,
+    depth: usize,
+    wikitext: &str,
+    options: &ParseOptions,
+    footnotes: &mut Footnotes,
+) -> Option<String> {
+    let mut lines = String::new();
+    for (index, item) in items.iter().enumerate() {
+        // Text before, between and after nested lists belongs to the item's own line
+        let mut item_text = String::new();
+        let mut nested = String::new();
+        let mut run_start = 0;
+        for (i, node) in item.nodes.iter().enumerate() {
+            let (nested_items, nested_ordered) = match node {
+                Node::UnorderedList { items, .. } => (items, false),
+                Node::OrderedList { items, .. } => (items, true),
+                _ => continue,
             };
+            item_text.push_str(&extract_nodes(&item.nodes[run_start..i], wikitext, options, footnotes)?);
+            nested.push_str(&markdown_list(nested_items, nested_ordered, depth + 1, wikitext, options, footnotes)?);
+            run_start = i + 1;
+        }
+        item_text.push_str(&extract_nodes(&item.nodes[run_start..], wikitext, options, footnotes)?);
 
-            // Only include if there's actual content after
-            if has_content_after {
-                result.push(para.clone());
+        let item_text = collapse_whitespace(&item_text);
+        if !item_text.is_empty() {
+            lines.push_str(&markdown_list_item(depth, ordered, index + 1, &item_text));
+        }
+        lines.push_str(&nested);
+    }
+    Some(lines)
+}
+
+/// Render definition list items as "Term: definition" lines
+/// Several definitions of one term are joined with "; ", a term without a
+/// definition stands alone, and definitions without a term (plain `:` indents)
+/// get a line each
+fn definition_lines(
+    items: &[DefinitionListItem],
+    wikitext: &str,
+    options: &ParseOptions,
+    footnotes: &mut Footnotes,
+) -> Option<Vec<String>> {
+    let mut lines = Vec::new();
+    let mut term = String::new();
+    let mut definitions = Vec::new();
+    for item in items {
+        let item_text = collapse_whitespace(&extract_nodes(&item.nodes, wikitext, options, footnotes)?);
+        match item.type_ {
+            DefinitionListItemType::Term => {
+                push_definition(&mut lines, std::mem::take(&mut term), std::mem::take(&mut definitions));
+                term = item_text;
             }
-        } else {
-            result.push(para.clone());
+            DefinitionListItemType::Details if !item_text.is_empty() => definitions.push(item_text),
+            DefinitionListItemType::Details => {}
         }
     }
+    push_definition(&mut lines, term, definitions);
+    Some(lines)
+}
 
-    result
+/// Add one term with its definitions to the rendered definition list lines
+fn push_definition(lines: &mut Vec<String>, term: String, definitions: Vec<String>) {
+    if term.is_empty() {
+        lines.extend(definitions);
+    } else if definitions.is_empty() {
+        lines.push(term);
+    } else {
+        lines.push(format!("{}: {}", term, definitions.join("; ")));
+    }
+}
+
+/// Widest `colspan` honoured when padding rows
+const MAX_COLSPAN: usize = 20;
+
+static COLSPAN_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r#"(?i)colspan\s*=\s*["']?\s*(\d+)"#).unwrap());
+
+/// Render a table as caption lines followed by one line per row (tab-separated
+/// cells or a Markdown pipe table)
+/// Cell text is collapsed to one line, a cell spanning N columns is followed by
+/// N-1 empty cells and short rows are padded to the widest one, so columns line up
+fn table_lines(
+    captions: &[TableCaption],
+    rows: &[TableRow],
+    style: TableStyle,
+    wikitext: &str,
+    options: &ParseOptions,
+    footnotes: &mut Footnotes,
+) -> Option<String> {
+    let mut lines = String::new();
+    for caption in captions {
+        let caption = collapse_whitespace(&extract_nodes(&caption.content, wikitext, options, footnotes)?);
+        if !caption.is_empty() {
+            lines.push_str(&caption);
+            lines.push('\n');
+        }
+    }
+
+    // (cells, whether the row holds only header cells); rows without any text are dropped
+    let mut table: Vec<(Vec<String>, bool)> = Vec::new();
+    for row in rows {
+        let mut cells = Vec::new();
+        for cell in &row.cells {
+            cells.push(collapse_whitespace(&extract_nodes(&cell.content, wikitext, options, footnotes)?));
+            let span = cell.attributes.as_deref().map_or(1, colspan);
+            cells.extend(std::iter::repeat_n(String::new(), span - 1));
+        }
+        if cells.iter().any(|cell| !cell.is_empty()) {
+            let header = row.cells.iter().all(|cell| matches!(cell.type_, TableCellType::Heading));
+            table.push((cells, header));
+        }
+    }
+    let width = table.iter().map(|(cells, _)| cells.len()).max().unwrap_or(0);
+    for (cells, _) in table.iter_mut() {
+        cells.resize(width, String::new());
+    }
+
+    match style {
+        TableStyle::Skip => {}
+        TableStyle::Text => {
+            for (cells, _) in &table {
+                lines.push_str(cells.join("\t").trim_end_matches('\t'));
+                lines.push('\n');
+            }
+        }
+        TableStyle::Markdown if table.is_empty() => {}
+        TableStyle::Markdown => {
+            // Pipe tables need a header row; without header cells it stays blank
+            let (header, body) = match table.split_first() {
+                Some(((cells, true), body)) => (cells.clone(), body),
+                _ => (vec![String::new(); width], &table[..]),
+            };
+            let pipe_row = |cells: &[String]| {
+                let cells: Vec<String> = cells.iter().map(|cell| cell.replace('|', "\\|")).collect();
+                format!("| {} |\n", cells.join(" | "))
+            };
+            lines.push_str(&pipe_row(&header));
+            lines.push_str(&pipe_row(&vec!["---".to_string(); width]));
+            for (cells, _) in body {
+                lines.push_str(&pipe_row(cells));
+            }
+        }
+    }
+    Some(lines)
+}
+
+/// Number of columns a cell spans according to its `colspan` attribute (1 to `MAX_COLSPAN`)
+fn colspan(attributes: &[Node]) -> usize {
+    let source: String = attributes
+        .iter()
+        .filter_map(|node| match node {
+            Node::Text { value, .. } => Some(*value),
+            _ => None,
+        })
+        .collect();
+    COLSPAN_RE
+        .captures(&source)
+        .and_then(|captures| captures[1].parse().ok())
+        .map_or(1, |span: usize| span.clamp(1, MAX_COLSPAN))
+}
+
+/// One Markdown list line ("- text" or "N. text"), indented two spaces per nesting level
+pub(crate) fn markdown_list_item(depth: usize, ordered: bool, number: usize, text: &str) -> String {
+    let indent = "  ".repeat(depth);
+    if ordered {
+        format!("{}{}. {}\n", indent, number, text)
+    } else {
+        format!("{}- {}\n", indent, text)
+    }
+}
+
+/// End the current paragraph and add rendered list or table lines as a paragraph of their own
+fn push_list_block(text: &mut String, current_paragraph: &mut String, lines: &str) {
+    if lines.trim().is_empty() {
+        return;
+    }
+    if !current_paragraph.trim().is_empty() {
+        text.push_str(current_paragraph.trim());
+        text.push_str("\n\n");
+    }
+    current_paragraph.clear();
+    text.push_str(lines.trim_end());
+    text.push_str("\n\n");
+}
+
+/// Join the words of a text with single spaces
+pub(crate) fn collapse_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
 }
 
-/// Extract plain text from nodes, using the original wikitext for Bold/Italic ranges
-fn extract_text_from_nodes(nodes: &[Node], wikitext: &str, skip_lists: bool) -> String {
+/// Move the current paragraph into `text`, followed by a blank line
+fn end_paragraph(text: &mut String, current_paragraph: &mut String) {
+    if !current_paragraph.trim().is_empty() {
+        text.push_str(current_paragraph.trim());
+        text.push_str("\n\n");
+    }
+    current_paragraph.clear();
+}
+
+/// Prefix every line of `text[start..]` with "> "; blank lines (paragraph breaks) stay blank
+fn quote_lines(text: &mut String, start: usize) {
+    let quoted: Vec<String> = text[start..]
+        .split('\n')
+        .map(|line| if line.trim().is_empty() { String::new() } else { format!("> {}", line) })
+        .collect();
+    text.truncate(start);
+    text.push_str(&quoted.join("\n"));
+}
+
+/// Lines of a `<poem>`: each trimmed, runs of blank lines (stanza breaks) kept as one
+fn poem_lines(poem: &str) -> String {
+    let mut lines: Vec<&str> = Vec::new();
+    for line in poem.lines().map(str::trim) {
+        if !line.is_empty() || lines.last().is_some_and(|last| !last.is_empty()) {
+            lines.push(line);
+        }
+    }
+    lines.join("\n").trim_end().to_string()
+}
+
+/// Case-insensitive tag name comparison (`<BR>` is as common as `<br>`)
+fn is_tag(name: &str, tag: &str) -> bool {
+    name.eq_ignore_ascii_case(tag)
+}
+
+/// Check whether a link target starts with one of the namespaces (`Категория:X`,
+/// also the `:Категория:X` form that links to the category page itself)
+fn in_dropped_namespace(target: &str, namespaces: &[String]) -> bool {
+    let Some((prefix, _)) = target.trim_start_matches(':').split_once(':') else {
+        return false;
+    };
+    let prefix = prefix.trim().to_lowercase();
+    namespaces.iter().any(|namespace| namespace.to_lowercase() == prefix)
+}
+
+/// Check whether accumulated output has grown past the configured limit
+fn exceeds_output_limit(text: &str, current_paragraph: &str, options: &ParseOptions) -> bool {
+    options.max_output_bytes > 0 && text.len() + current_paragraph.len() > options.max_output_bytes
+}
+
+/// Citations collected in `RefStyle::Footnote` mode, numbered by first use
+#[derive(Default)]
+struct Footnotes {
+    citations: Vec<String>,
+    /// Number of each named ref, so `<ref name="x" />` reuses its definition's number
+    names: HashMap<String, usize>,
+    /// Set while a citation's own content is extracted
+    in_ref: bool,
+}
+
+impl Footnotes {
+    /// Number for a ref, adding its citation on first use
+    /// An unnamed ref without text gets no number; a named ref used before
+    /// its definition reserves a number that the definition fills in later
+    fn add(&mut self, name: Option<&str>, citation: String) -> Option<usize> {
+        if let Some(name) = name {
+            if let Some(&number) = self.names.get(name) {
+                if self.citations[number - 1].is_empty() {
+                    self.citations[number - 1] = citation;
+                }
+                return Some(number);
+            }
+        } else if citation.is_empty() {
+            return None;
+        }
+        self.citations.push(citation);
+        let number = self.citations.len();
+        if let Some(name) = name {
+            self.names.insert(name.to_string(), number);
+        }
+        Some(number)
+    }
+
+    /// One "[N] citation" line per footnote; named refs that were never defined are left out
+    fn render(&self) -> Option<String> {
+        let lines: Vec<String> = self
+            .citations
+            .iter()
+            .enumerate()
+            .filter(|(_, citation)| !citation.is_empty())
+            .map(|(index, citation)| format!("[{}] {}", index + 1, citation))
+            .collect();
+        (!lines.is_empty()).then(|| lines.join("\n"))
+    }
+}
+
+/// The `name` attribute of a ref tag's source (`<ref name="x">...` or `<ref name=x />`)
+fn ref_name(tag: &str) -> Option<&str> {
+    let open_tag = &tag[..tag.find('>').unwrap_or(tag.len())];
+    let value = open_tag.split_once("name")?.1.trim_start().strip_prefix('=')?.trim_start();
+    let name = match value.strip_prefix(['"', '\'']) {
+        Some(quoted) => quoted.split(['"', '\'']).next()?,
+        None => value.split(|c: char| c.is_whitespace() || c == '/').next()?,
+    };
+    let name = name.trim();
+    (!name.is_empty()).then_some(name)
+}
+
+/// Extract plain text from nodes; `wikitext` is the source the node offsets
+/// point into (used for ref names)
+/// Returns None if the output grows past `max_output_bytes`. With
+/// `RefStyle::Footnote`, the collected citations follow as a last paragraph
+pub fn extract_text_from_nodes(nodes: &[Node], wikitext: &str, options: &ParseOptions) -> Option<String> {
+    let mut footnotes = Footnotes::default();
+    let mut text = extract_nodes(nodes, wikitext, options, &mut footnotes)?;
+    if let Some(rendered) = footnotes.render() {
+        if !text.is_empty() {
+            text.push_str("\n\n");
+        }
+        text.push_str(&rendered);
+    }
+    Some(text)
+}
+
+/// `extract_text_from_nodes` for one level of the node tree, sharing the article's footnotes
+fn extract_nodes(nodes: &[Node], wikitext: &str, options: &ParseOptions, footnotes: &mut Footnotes) -> Option<String> {
     let mut text = String::new();
     let mut current_paragraph = String::new();
+    // Start of the open `<blockquote>` in `text` with `block_formatting`
+    let mut quote_start: Option<usize> = None;
 
     for node in nodes {
+        if exceeds_output_limit(&text, &current_paragraph, options) {
+            return None;
+        }
+
         match node {
+            Node::Text { value, .. } if value.contains('&') => {
+                current_paragraph.push_str(&decode_entities(value, options.keep_nbsp));
+            }
             Node::Text { value, .. } => {
                 current_paragraph.push_str(value);
             }
-            Node::Bold { start, end, .. }
-            | Node::Italic { start, end, .. }
-            | Node::BoldItalic { start, end, .. } => {
-                // Extract the text content from the marked range
-                // The markup itself is within this range, so we need to get the inner text
-                let inner_text = &wikitext[*start..*end];
-                // Remove the wiki markup (''' for bold, '' for italic, ''''' for both)
-                let cleaned = inner_text
-                    .trim_start_matches("'''''")
-                    .trim_end_matches("'''''")
-                    .trim_start_matches("'''")
-                    .trim_end_matches("'''")
-                    .trim_start_matches("''")
-                    .trim_end_matches("''");
-                current_paragraph.push_str(cleaned);
+            Node::CharacterEntity { character, .. } => {
+                current_paragraph.push(nbsp_character(*character, options.keep_nbsp));
             }
-            Node::Link { text: link_text, .. } => {
-                // Extract only the display text from links
-                let link_display = extract_text_from_nodes(link_text, wikitext, skip_lists);
+            // Bold/italic nodes are toggles covering only the quote markup; the
+            // formatted text (apostrophes included) arrives as the sibling nodes
+            // between them, so the markers themselves contribute nothing
+            Node::Bold { .. } | Node::Italic { .. } | Node::BoldItalic { .. } => {}
+            Node::Link { target, text: link_text, .. } => {
+                let link_display = extract_nodes(link_text, wikitext, options, footnotes)?;
+                let target = target.trim();
                 // Filter out if it looks like an image description (contains "Файл:" patterns)
-                if !link_display.contains("Файл:") && !link_display.contains("File:") {
-                    current_paragraph.push_str(&link_display);
+                let is_file = |text: &str| text.contains("Файл:") || text.contains("File:");
+                if !is_file(&link_display) && !is_file(target) && !in_dropped_namespace(target, &options.drop_link_namespaces) {
+                    match options.links {
+                        LinkStyle::Text => current_paragraph.push_str(&link_display),
+                        LinkStyle::Target => current_paragraph.push_str(target),
+                        LinkStyle::Wiki if link_display == target => {
+                            current_paragraph.push_str(&format!("[[{}]]", target));
+                        }
+                        LinkStyle::Wiki => current_paragraph.push_str(&format!("[[{}|{}]]", target, link_display)),
+                    }
                 }
             }
             Node::ExternalLink { nodes, .. } => {
                 // Extract text from external links, but filter out bare URLs
-                let link_text = extract_text_from_nodes(nodes, wikitext, skip_lists);
+                let link_text = extract_nodes(nodes, wikitext, options, footnotes)?;
                 // Only include if it's not just a URL
                 if !link_text.starts_with("http://") && !link_text.starts_with("https://") {
                     current_paragraph.push_str(&link_text);
                 }
             }
-            Node::Heading { nodes, .. } => {
+            Node::Heading { level, nodes, .. } => {
                 // Extract text from headings but treat them as separate paragraphs
-                let heading_text = extract_text_from_nodes(nodes, wikitext, skip_lists);
+                let heading_text = extract_nodes(nodes, wikitext, options, footnotes)?;
                 if !heading_text.trim().is_empty() {
-                    if !current_paragraph.is_empty() {
-                        text.push_str(&current_paragraph);
-                        text.push_str("\n\n");
-                        current_paragraph.clear();
-                    }
-                    text.push_str(heading_text.trim());
+                    // Trimmed, so the newline ending the heading line never
+                    // glues onto the paragraph before or after it
+                    end_paragraph(&mut text, &mut current_paragraph);
+                    text.push_str(&format_heading(*level, heading_text.trim(), options.heading_style));
                     text.push_str("\n\n");
                 }
             }
-            Node::ParagraphBreak { .. } => {
-                // Mark paragraph boundary
-                if !current_paragraph.trim().is_empty() {
-                    text.push_str(current_paragraph.trim());
-                    text.push_str("\n\n");
-                    current_paragraph.clear();
+            Node::ParagraphBreak { .. } => end_paragraph(&mut text, &mut current_paragraph),
+            Node::StartTag { name, .. } if options.block_formatting && is_tag(name, "blockquote") => {
+                end_paragraph(&mut text, &mut current_paragraph);
+                quote_start.get_or_insert(text.len());
+            }
+            Node::EndTag { name, .. } if options.block_formatting && is_tag(name, "blockquote") => {
+                end_paragraph(&mut text, &mut current_paragraph);
+                if let Some(start) = quote_start.take() {
+                    quote_lines(&mut text, start);
+                }
+            }
+            Node::StartTag { name, .. } | Node::EndTag { name, .. } if options.html_breaks && is_tag(name, "p") => {
+                end_paragraph(&mut text, &mut current_paragraph);
+            }
+            // `<br>`, `<br/>` and the stray `</br>` browsers also accept
+            Node::StartTag { name, .. } | Node::EndTag { name, .. } if options.html_breaks && is_tag(name, "br") => {
+                let line_end = current_paragraph.trim_end().len();
+                current_paragraph.truncate(line_end);
+                if !current_paragraph.is_empty() && !current_paragraph.ends_with('\n') {
+                    current_paragraph.push('\n');
                 }
             }
-            Node::UnorderedList { items, .. } | Node::OrderedList { items, .. } => {
-                if skip_lists {
-                    // Skip lists entirely when flag is set
-                } else {
+            Node::UnorderedList { items, .. } | Node::OrderedList { items, .. } => match options.list_style {
+                ListStyle::Skip => {}
+                ListStyle::Flatten => {
                     // Extract text from list items
                     for item in items {
-                        let item_text = extract_text_from_nodes(&item.nodes, wikitext, skip_lists);
+                        let item_text = extract_nodes(&item.nodes, wikitext, options, footnotes)?;
                         if !item_text.trim().is_empty() {
                             current_paragraph.push_str(item_text.trim());
                             current_paragraph.push(' ');
                         }
                     }
                 }
-            }
+                ListStyle::Markdown => {
+                    // The list becomes its own paragraph of item lines
+                    let ordered = matches!(node, Node::OrderedList { .. });
+                    let lines = markdown_list(items, ordered, 0, wikitext, options, footnotes)?;
+                    push_list_block(&mut text, &mut current_paragraph, &lines);
+                }
+            },
             Node::DefinitionList { items, .. } => {
-                if skip_lists {
-                    // Skip definition lists entirely when flag is set
-                } else {
-                    // Extract text from definition list items
-                    for item in items {
-                        let item_text = extract_text_from_nodes(&item.nodes, wikitext, skip_lists);
-                        if !item_text.trim().is_empty() {
-                            current_paragraph.push_str(item_text.trim());
-                            current_paragraph.push(' ');
+                // Terms and definitions keep their own lines in both flatten and markdown modes
+                if options.list_style != ListStyle::Skip {
+                    let mut lines = String::new();
+                    for line in definition_lines(items, wikitext, options, footnotes)? {
+                        if options.list_style == ListStyle::Markdown {
+                            lines.push_str(&markdown_list_item(0, false, 0, &line));
+                        } else {
+                            lines.push_str(&line);
+                            lines.push('\n');
                         }
                     }
+                    push_list_block(&mut text, &mut current_paragraph, &lines);
                 }
             }
             Node::Preformatted { nodes, .. } => {
-                current_paragraph.push_str(&extract_text_from_nodes(nodes, wikitext, skip_lists));
+                current_paragraph.push_str(&extract_nodes(nodes, wikitext, options, footnotes)?);
             }
-            Node::Tag { name, nodes, .. } => {
-                // Skip ref tags (citations/references)
-                if name.as_ref() != "ref" {
-                    current_paragraph.push_str(&extract_text_from_nodes(nodes, wikitext, skip_lists));
+            Node::Tag { name, nodes, start, end } if name.as_ref() == "ref" => match options.keep_refs {
+                RefStyle::Drop => {}
+                RefStyle::Inline => {
+                    // Refs follow the word they cite without a space; keep the two apart
+                    let citation = extract_nodes(nodes, wikitext, options, footnotes)?;
+                    if !citation.trim().is_empty() {
+                        if !current_paragraph.is_empty() && !current_paragraph.ends_with(char::is_whitespace) {
+                            current_paragraph.push(' ');
+                        }
+                        current_paragraph.push_str(citation.trim());
+                    }
+                }
+                // Refs nested inside a citation are dropped rather than numbered
+                RefStyle::Footnote if footnotes.in_ref => {}
+                RefStyle::Footnote => {
+                    footnotes.in_ref = true;
+                    let citation = extract_nodes(nodes, wikitext, options, footnotes);
+                    footnotes.in_ref = false;
+                    let citation = collapse_whitespace(&citation?);
+                    let name = wikitext.get(*start..*end).and_then(ref_name);
+                    if let Some(number) = footnotes.add(name, citation) {
+                        current_paragraph.push_str(&format!("[{}]", number));
+                    }
                 }
+            },
+            Node::Tag { name, nodes, .. } if options.block_formatting && is_tag(name, "poem") => {
+                let poem = extract_nodes(nodes, wikitext, options, footnotes)?;
+                push_list_block(&mut text, &mut current_paragraph, &poem_lines(&poem));
+            }
+            Node::Tag { nodes, .. } => {
+                current_paragraph.push_str(&extract_nodes(nodes, wikitext, options, footnotes)?);
             }
-            // Skip templates, tables, images, categories, and other non-text content
-            Node::Template { .. }
-            | Node::Table { .. }
+            Node::Template { name, parameters, .. } => {
+                let name = template_name(name);
+                // Drop-listed templates (maintenance banners etc.) never contribute text
+                if is_dropped_template(&name, &options.drop_templates) {
+                    continue;
+                }
+                // Allow-listed templates are expanded from their parsed arguments
+                if options.expand_templates.iter().any(|allowed| normalize_template_name(allowed) == name) {
+                    let arguments = parameters
+                        .iter()
+                        .filter(|parameter| parameter.name.is_none())
+                        .map(|parameter| extract_nodes(&parameter.value, wikitext, options, footnotes))
+                        .collect::<Option<Vec<_>>>()?;
+                    if let Some(expanded) = expand_template(&name, &arguments, options.language) {
+                        current_paragraph.push_str(&expanded);
+                        continue;
+                    }
+                }
+                // Foreign-language spans keep their text, the code only with `lang_codes`
+                if let Some((code, nodes)) = lang_template(&name, parameters) {
+                    if options.lang_codes {
+                        current_paragraph.push_str(&format!("[{}] ", code));
+                    }
+                    current_paragraph.push_str(extract_nodes(nodes, wikitext, options, footnotes)?.trim());
+                    continue;
+                }
+                // Interwiki link templates wrap a term that belongs in the prose
+                if INTERWIKI_LINK_TEMPLATES.contains(&name.as_str()) {
+                    if let Some(display) = interwiki_display_nodes(parameters) {
+                        current_paragraph.push_str(extract_nodes(display, wikitext, options, footnotes)?.trim());
+                    }
+                }
+                // Tables in the arguments of kept templates (infobox data) render like top-level ones
+                if options.tables != TableStyle::Skip {
+                    for node in parameters.iter().flat_map(|parameter| &parameter.value) {
+                        if let Node::Table { captions, rows, .. } = node {
+                            let lines = table_lines(captions, rows, options.tables, wikitext, options, footnotes)?;
+                            push_list_block(&mut text, &mut current_paragraph, &lines);
+                        }
+                    }
+                }
+                // The rest of other templates is skipped as non-text content
+            }
+            Node::Table { captions, rows, .. } if options.tables != TableStyle::Skip => {
+                let lines = table_lines(captions, rows, options.tables, wikitext, options, footnotes)?;
+                push_list_block(&mut text, &mut current_paragraph, &lines);
+            }
+            // Skip tables (by default), images, categories, and other non-text content
+            Node::Table { .. }
             | Node::Image { .. }
             | Node::Category { .. }
             | Node::StartTag { .. }
@@ -262,8 +1780,7 @@ fn extract_text_from_nodes(nodes: &[Node], wikitext: &str, skip_lists: bool) ->
             | Node::HorizontalDivider { .. }
             | Node::MagicWord { .. }
             | Node::Redirect { .. }
-            | Node::Parameter { .. }
-            | Node::CharacterEntity { .. } => {}
+            | Node::Parameter { .. } => {}
         }
     }
 
@@ -271,6 +1788,141 @@ fn extract_text_from_nodes(nodes: &[Node], wikitext: &str, skip_lists: bool) ->
     if !current_paragraph.trim().is_empty() {
         text.push_str(current_paragraph.trim());
     }
+    // A blockquote left open runs to the end of the nodes
+    if let Some(start) = quote_start {
+        quote_lines(&mut text, start);
+    }
 
-    text
+    if exceeds_output_limit(&text, "", options) {
+        return None;
+    }
+
+    Some(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(value: &'static str) -> Node<'static> {
+        Node::Text { end: 0, start: 0, value }
+    }
+
+    fn extract(nodes: &[Node], wikitext: &str) -> String {
+        extract_text_from_nodes(nodes, wikitext, &ParseOptions::default()).unwrap()
+    }
+
+    fn strings(values: &[&str]) -> Vec<String> {
+        values.iter().map(|value| value.to_string()).collect()
+    }
+
+    #[test]
+    fn ref_names_are_read_from_the_opening_tag() {
+        assert_eq!(ref_name("<ref name=\"a b\">x</ref>"), Some("a b"));
+        assert_eq!(ref_name("<ref name='a'>x</ref>"), Some("a"));
+        assert_eq!(ref_name("<ref name=a />"), Some("a"));
+        assert_eq!(ref_name("<ref>name=a</ref>"), None);
+        assert_eq!(ref_name("<ref name=\"\" />"), None);
+    }
+
+    #[test]
+    fn cc3_dates_expand_to_day_month_year() {
+        let cc3 = |argument: &str, language| expand_template("сс3", &[argument.to_string()], language);
+        assert_eq!(cc3("18.1.1918", Language::Ru).as_deref(), Some("18 января 1918"));
+        assert_eq!(cc3("05.12.1850", Language::En).as_deref(), Some("05 December 1850"));
+        assert_eq!(cc3("18.1.1918", Language::De).as_deref(), Some("18. Januar 1918"));
+        // Out-of-range months stay numeric; malformed dates are not expanded
+        assert_eq!(cc3("1.13.1918", Language::Ru).as_deref(), Some("1.13.1918"));
+        assert_eq!(cc3("18.1", Language::Ru), None);
+        assert_eq!(cc3("18.1.1918.2", Language::Ru), None);
+        assert_eq!(cc3("18.янв.1918", Language::Ru), None);
+        // Leaked into text, the template is expanded by the same rules
+        assert_eq!(expand_common_templates("{{СС3|9.5.1945}}", Language::Ru), "9 мая 1945");
+    }
+
+    #[test]
+    fn articles_over_the_output_limit_are_skipped() {
+        let nodes = [text("Первый абзац."), Node::ParagraphBreak { end: 0, start: 0 }, text("Второй абзац.")];
+        let limited = |max_output_bytes| {
+            let options = ParseOptions { max_output_bytes, ..Default::default() };
+            extract_text_from_nodes(&nodes, "", &options)
+        };
+        let full = "Первый абзац.\n\nВторой абзац.";
+        assert_eq!(limited(0).as_deref(), Some(full));
+        assert_eq!(limited(full.len()).as_deref(), Some(full));
+        assert_eq!(limited(full.len() - 1), None);
+        // The limit counts bytes, so Cyrillic text trips it at half the characters
+        assert!(exceeds_output_limit("абв", "", &ParseOptions { max_output_bytes: 5, ..Default::default() }));
+    }
+
+    #[test]
+    fn leading_disambiguation_sentence_and_list_are_trimmed() {
+        let article = |lead| {
+            vec![
+                text(lead),
+                Node::UnorderedList {
+                    end: 0,
+                    items: vec![
+                        ListItem { end: 0, nodes: vec![text("Меркурий (планета)")], start: 0 },
+                        ListItem { end: 0, nodes: vec![text("Меркурий (бог)")], start: 0 },
+                    ],
+                    start: 0,
+                },
+                Node::ParagraphBreak { end: 0, start: 0 },
+                text("Меркурий — ближайшая к Солнцу планета."),
+            ]
+        };
+        let options = ParseOptions { disambiguation_markers: strings(DEFAULT_DISAMBIGUATION_MARKERS), ..Default::default() };
+        let trimmed = |nodes: &[Node]| extract(trim_leading_disambiguation(nodes, "", &options), "");
+        assert_eq!(trimmed(&article("Меркурий — может означать:")), "Меркурий — ближайшая к Солнцу планета.");
+        // A lead without a marker is ordinary prose and keeps its list
+        let nodes = article("Меркурий — имя нескольких объектов:");
+        assert_eq!(trimmed(&nodes), extract(&nodes, ""));
+        // No markers (the flag is off) leaves the article as is
+        let nodes = article("Меркурий — может означать:");
+        assert_eq!(trim_leading_disambiguation(&nodes, "", &ParseOptions::default()).len(), nodes.len());
+    }
+
+    #[test]
+    fn max_sections_keeps_the_lead_and_the_first_sections() {
+        let heading = |level, title| Node::Heading { end: 0, level, nodes: vec![text(title)], start: 0 };
+        let nodes = [
+            text("Лид."),
+            heading(2, "История"),
+            text("Основание."),
+            heading(3, "Ранние годы"),
+            text("Детство."),
+            heading(2, "География"),
+            text("Реки."),
+        ];
+        let kept = |max_sections| extract(truncate_sections(&nodes, max_sections), "");
+        // The level-3 subsection stays with its level-2 parent
+        assert_eq!(kept(Some(1)), "Лид.\n\nИстория\n\nОснование.\n\nРанние годы\n\nДетство.");
+        assert_eq!(kept(Some(0)), "Лид.");
+        assert_eq!(kept(Some(2)), kept(None));
+    }
+
+    #[test]
+    fn empty_sections_are_dropped() {
+        let section_names = strings(&["Примечания", "Ссылки", "См. также"]);
+        let category_prefixes = strings(&["Категория:"]);
+        let paragraphs = strings(&[
+            "Вступление.",
+            "Примечания",
+            "Ссылки",
+            "Официальный сайт.",
+            "См. также",
+            "Категория:Города",
+        ]);
+        assert_eq!(
+            remove_empty_sections(&paragraphs, &section_names, &category_prefixes),
+            strings(&["Вступление.", "Примечания", "Ссылки", "Официальный сайт."])
+        );
+        // Markdown-prefixed headings match the same names
+        let paragraphs = strings(&["## Примечания", "Текст.", "## Ссылки"]);
+        assert_eq!(
+            remove_empty_sections(&paragraphs, &section_names, &category_prefixes),
+            strings(&["## Примечания", "Текст."])
+        );
+    }
 }
@@ -0,0 +1,261 @@
+//! Bounded worker pool for parsing articles with a per-article timeout
+//!
+//! A fixed number of worker threads pull articles from a shared job channel
+//! and report back over an event channel. The dispatcher never has more jobs
+//! in flight than there are workers, and each job's timeout starts when a
+//! worker picks it up.
+//!
+//! Threads can't be killed in Rust, so a timed-out job is cancelled by
+//! claiming it: a `Timeout` result is recorded, a replacement worker is
+//! spawned to keep the pool at full size, and the stuck worker discards its
+//! result as soon as the parse returns.
+//!
+//! At most as many abandoned workers as the pool size are replaced. Beyond
+//! that the replacement is owed instead: the pool runs short-handed until an
+//! abandoned worker finishes its parse and rejoins the pool to pay the debt,
+//! so the thread count never exceeds twice the pool size.
+//!
+//! Per-article "Processing"/"Done" lines are only logged in verbose mode;
+//! timeout warnings are always printed.
+
+use crate::parser::{ParseResult, ParserWarnings, SkipReason};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+type ParseFn = Arc<dyn Fn(&str) -> (ParseResult, ParserWarnings) + Send + Sync>;
+
+struct Job {
+    id: u64,
+    text: String,
+    /// Set by whoever finishes the job first: the worker or the timeout
+    claimed: Arc<AtomicBool>,
+}
+
+enum Event {
+    Started { id: u64 },
+    Finished { id: u64, result: ParseResult, warnings: ParserWarnings },
+}
+
+/// A job dispatched to a worker and not yet finished or timed out
+struct InFlight {
+    index: usize,
+    label: String,
+    deadline: Option<Instant>,
+    claimed: Arc<AtomicBool>,
+}
+
+/// Workers abandoned by timeouts, shared between the dispatcher and the workers
+#[derive(Default)]
+struct Abandoned {
+    /// Abandoned workers still stuck in a parse
+    running: usize,
+    /// Replacements not spawned because `running` hit the cap
+    owed: usize,
+}
+
+pub struct ParsePool {
+    threads: usize,
+    timeout: Option<Duration>,
+    parse: ParseFn,
+    next_id: AtomicU64,
+    job_tx: Sender<Job>,
+    job_rx: Arc<Mutex<Receiver<Job>>>,
+    event_tx: Sender<Event>,
+    event_rx: Receiver<Event>,
+    abandoned: Arc<Mutex<Abandoned>>,
+    verbose: bool,
+}
+
+impl ParsePool {
+    /// Start a pool of `threads` workers (0 = one per CPU core)
+    /// `timeout_secs` of 0 disables the per-article timeout; `parse` returns
+    /// the parser warnings along with the result, so nothing is parsed twice
+    pub fn new<F>(threads: usize, timeout_secs: u64, parse: F) -> Self
+    where
+        F: Fn(&str) -> (ParseResult, ParserWarnings) + Send + Sync + 'static,
+    {
+        let threads = if threads == 0 {
+            thread::available_parallelism().map(|n| n.get()).unwrap_or(1)
+        } else {
+            threads
+        };
+        let (job_tx, job_rx) = mpsc::channel();
+        let (event_tx, event_rx) = mpsc::channel();
+
+        let pool = ParsePool {
+            threads,
+            timeout: (timeout_secs > 0).then(|| Duration::from_secs(timeout_secs)),
+            parse: Arc::new(parse),
+            next_id: AtomicU64::new(0),
+            job_tx,
+            job_rx: Arc::new(Mutex::new(job_rx)),
+            event_tx,
+            event_rx,
+            abandoned: Arc::new(Mutex::new(Abandoned::default())),
+            verbose: false,
+        };
+        for _ in 0..threads {
+            pool.spawn_worker();
+        }
+        pool
+    }
+
+    /// Log a line when each article starts and finishes parsing
+    pub fn verbose(mut self, verbose: bool) -> Self {
+        self.verbose = verbose;
+        self
+    }
+
+    pub fn threads(&self) -> usize {
+        self.threads
+    }
+
+    /// Parse every non-null text, returning results and parser warnings in input order
+    /// (timed-out and null rows have no warnings)
+    /// `label` describes a row for progress and timeout messages
+    pub fn parse_all<L>(&self, texts: &[Option<&str>], label: L) -> (Vec<Option<ParseResult>>, Vec<ParserWarnings>)
+    where
+        L: Fn(usize) -> String,
+    {
+        let mut results: Vec<Option<ParseResult>> = vec![None; texts.len()];
+        let mut warnings: Vec<ParserWarnings> = vec![Vec::new(); texts.len()];
+        let mut pending = texts
+            .iter()
+            .enumerate()
+            .filter_map(|(index, text)| text.map(|text| (index, text)));
+        let mut in_flight: HashMap<u64, InFlight> = HashMap::new();
+
+        loop {
+            self.cancel_expired(&mut in_flight, &mut results);
+
+            // Keep at most one job per worker in flight
+            while in_flight.len() < self.threads {
+                let Some((index, text)) = pending.next() else {
+                    break;
+                };
+                let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+                let claimed = Arc::new(AtomicBool::new(false));
+                let label = label(index);
+                if self.verbose {
+                    eprintln!("  [{}] Processing {}", index + 1, label);
+                }
+
+                in_flight.insert(id, InFlight { index, label, deadline: None, claimed: Arc::clone(&claimed) });
+                self.job_tx
+                    .send(Job { id, text: text.to_string(), claimed })
+                    .expect("parse pool workers disconnected");
+            }
+
+            if in_flight.is_empty() {
+                break;
+            }
+
+            let next_deadline = in_flight.values().filter_map(|job| job.deadline).min();
+            let event = match next_deadline {
+                Some(deadline) => self
+                    .event_rx
+                    .recv_timeout(deadline.saturating_duration_since(Instant::now()))
+                    .ok(),
+                None => self.event_rx.recv().ok(),
+            };
+
+            match event {
+                Some(Event::Started { id }) => {
+                    if let Some(job) = in_flight.get_mut(&id) {
+                        job.deadline = self.timeout.map(|timeout| Instant::now() + timeout);
+                    }
+                }
+                Some(Event::Finished { id, result, warnings: job_warnings }) => {
+                    // Events for jobs that already timed out are ignored
+                    if let Some(job) = in_flight.remove(&id) {
+                        if self.verbose {
+                            eprintln!("  [{}] Done processing {}", job.index + 1, job.label);
+                        }
+                        results[job.index] = Some(result);
+                        warnings[job.index] = job_warnings;
+                    }
+                }
+                None => {}
+            }
+        }
+
+        (results, warnings)
+    }
+
+    /// Record a timeout for each job past its deadline and replace its worker
+    fn cancel_expired(&self, in_flight: &mut HashMap<u64, InFlight>, results: &mut [Option<ParseResult>]) {
+        let now = Instant::now();
+        let expired: Vec<u64> = in_flight
+            .iter()
+            .filter(|(_, job)| job.deadline.is_some_and(|deadline| deadline <= now))
+            .map(|(id, _)| *id)
+            .collect();
+
+        for id in expired {
+            // Held across the claim so the abandoned worker can't finish before it is counted
+            let mut abandoned = self.abandoned.lock().unwrap();
+            // If the worker claimed the job first, its Finished event is on the way
+            if in_flight[&id].claimed.swap(true, Ordering::SeqCst) {
+                continue;
+            }
+            let job = in_flight.remove(&id).unwrap();
+            let timeout_secs = self.timeout.map(|timeout| timeout.as_secs()).unwrap_or(0);
+            eprintln!(
+                "WARNING: [{}] Parsing {} timed out after {} seconds",
+                job.index + 1,
+                job.label,
+                timeout_secs
+            );
+            results[job.index] = Some(Err(SkipReason::Timeout));
+
+            abandoned.running += 1;
+            if abandoned.running <= self.threads {
+                self.spawn_worker();
+            } else {
+                abandoned.owed += 1;
+                eprintln!(
+                    "WARNING: {} workers are stuck on timed-out articles; not replacing another until one finishes",
+                    abandoned.running
+                );
+            }
+        }
+    }
+
+    fn spawn_worker(&self) {
+        let job_rx = Arc::clone(&self.job_rx);
+        let event_tx = self.event_tx.clone();
+        let parse = Arc::clone(&self.parse);
+        let abandoned = Arc::clone(&self.abandoned);
+
+        thread::spawn(move || loop {
+            let job = match job_rx.lock().unwrap().recv() {
+                Ok(job) => job,
+                // Pool dropped: no more work
+                Err(_) => return,
+            };
+            if event_tx.send(Event::Started { id: job.id }).is_err() {
+                return;
+            }
+
+            let (result, warnings) = parse(&job.text);
+
+            // Timed out while parsing: rejoin the pool if it is owed a worker, otherwise exit
+            if job.claimed.swap(true, Ordering::SeqCst) {
+                let mut abandoned = abandoned.lock().unwrap();
+                abandoned.running -= 1;
+                if abandoned.owed == 0 {
+                    return;
+                }
+                abandoned.owed -= 1;
+                continue;
+            }
+            if event_tx.send(Event::Finished { id: job.id, result, warnings }).is_err() {
+                return;
+            }
+        });
+    }
+}
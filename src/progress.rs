@@ -0,0 +1,20 @@
+//! Row progress bar for the parsing binaries
+//!
+//! Shows rows processed out of the file's total, the row rate and an ETA on a
+//! single stderr line. With `--verbose` the bar is hidden and the per-row log
+//! lines take its place.
+
+use indicatif::{ProgressBar, ProgressStyle};
+
+const TEMPLATE: &str = "{elapsed_precise} [{wide_bar}] {human_pos}/{human_len} rows ({per_sec}, ETA {eta})";
+
+/// Progress bar over `total_rows`, hidden when `verbose`
+pub fn row_progress(total_rows: u64, verbose: bool) -> ProgressBar {
+    if verbose {
+        return ProgressBar::hidden();
+    }
+    let style = ProgressStyle::with_template(TEMPLATE)
+        .expect("progress bar template is valid")
+        .progress_chars("=> ");
+    ProgressBar::new(total_rows).with_style(style)
+}
@@ -0,0 +1,48 @@
+//! Heuristic extraction-quality score for parsed articles
+//!
+//! Combines three signals into a score between 0 and 1:
+//! - alphabetic ratio: share of letters among non-whitespace characters
+//! - prose ratio: share of paragraphs with several words that end in sentence
+//!   punctuation (short paragraphs such as headings are ignored)
+//! - artifact penalty: residual markup (`{{`, `[[`, `|`, `<`, `==`, ...);
+//!   10 artifacts per 1000 characters halve the score
+//!
+//! Cleanly parsed prose scores around 0.8-1.0; articles full of leaked
+//! markup, tables or lists score low and are worth a manual look.
+
+/// Substrings that indicate markup leaked through extraction
+const ARTIFACTS: [&str; 10] = ["{{", "}}", "[[", "]]", "|", "<", ">", "==", "''", "&nbsp;"];
+
+/// Artifacts per 1000 characters at which the score is halved
+const ARTIFACT_HALVING_RATE: f32 = 10.0;
+
+/// Minimum word count for a paragraph to count as prose
+const MIN_PROSE_WORDS: usize = 5;
+
+/// Score the quality of an extracted text (0 = unusable, 1 = clean prose)
+pub fn quality_score(text: &str) -> f32 {
+    let non_whitespace = text.chars().filter(|c| !c.is_whitespace()).count();
+    if non_whitespace == 0 {
+        return 0.0;
+    }
+    let alphabetic = text.chars().filter(|c| c.is_alphabetic()).count();
+    let alpha_ratio = alphabetic as f32 / non_whitespace as f32;
+
+    // Headings and other short paragraphs are neither prose nor noise
+    let long_paragraphs: Vec<&str> = text
+        .split("\n\n")
+        .map(str::trim)
+        .filter(|p| p.split_whitespace().count() >= MIN_PROSE_WORDS)
+        .collect();
+    let prose = long_paragraphs.iter().filter(|p| ends_like_sentence(p)).count();
+    let prose_ratio = prose as f32 / long_paragraphs.len().max(1) as f32;
+
+    let artifacts: usize = ARTIFACTS.iter().map(|artifact| text.matches(artifact).count()).sum();
+    let artifacts_per_kilochar = artifacts as f32 * 1000.0 / text.chars().count() as f32;
+
+    (0.5 * alpha_ratio + 0.5 * prose_ratio) / (1.0 + artifacts_per_kilochar / ARTIFACT_HALVING_RATE)
+}
+
+fn ends_like_sentence(paragraph: &str) -> bool {
+    paragraph.ends_with(['.', '!', '?', '…', '»', ')', '"'])
+}
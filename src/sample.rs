@@ -0,0 +1,79 @@
+//! Capped sample of articles that failed to parse, for building regression fixtures
+//!
+//! Collects the original input of articles that timed out, hit the output size
+//! guard, or produced no text, up to a fixed count, and writes them to a parquet
+//! file with their status so hard cases can be reproduced later.
+
+use crate::parser::ParseResult;
+use crate::storage;
+use anyhow::Result;
+use arrow::array::{ArrayRef, RecordBatch, StringArray};
+use arrow::datatypes::{DataType, Field, Schema};
+use parquet::arrow::ArrowWriter;
+use parquet::file::properties::WriterProperties;
+use std::sync::Arc;
+
+struct SampleRow {
+    page_id: Option<String>,
+    column: String,
+    status: &'static str,
+    input: String,
+}
+
+pub struct FailureSample {
+    limit: usize,
+    rows: Vec<SampleRow>,
+}
+
+impl FailureSample {
+    /// Collect up to `limit` failing inputs (0 = disabled)
+    pub fn new(limit: usize) -> Self {
+        FailureSample { limit, rows: Vec::new() }
+    }
+
+    /// Record the input if its parse result is a skip or empty text and the sample isn't full
+    pub fn record(&mut self, page_id: Option<&str>, column: &str, input: &str, result: &ParseResult) {
+        if self.rows.len() >= self.limit {
+            return;
+        }
+        let status = match result {
+            Err(reason) => reason.as_str(),
+            Ok(text) if text.trim().is_empty() && !input.trim().is_empty() => "empty",
+            Ok(_) => return,
+        };
+
+        self.rows.push(SampleRow {
+            page_id: page_id.map(|id| id.to_string()),
+            column: column.to_string(),
+            status,
+            input: input.to_string(),
+        });
+    }
+
+    /// Write the collected sample to a parquet file (local path or object-storage URL)
+    pub fn write(&self, path: &str) -> Result<()> {
+        println!("Writing {} unparseable input(s) to {}", self.rows.len(), path);
+
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("page_id", DataType::Utf8, true),
+            Field::new("column", DataType::Utf8, true),
+            Field::new("status", DataType::Utf8, true),
+            Field::new("input", DataType::Utf8, true),
+        ]));
+        let columns: Vec<ArrayRef> = vec![
+            Arc::new(StringArray::from_iter(self.rows.iter().map(|row| row.page_id.as_deref()))),
+            Arc::new(StringArray::from_iter_values(self.rows.iter().map(|row| row.column.as_str()))),
+            Arc::new(StringArray::from_iter_values(self.rows.iter().map(|row| row.status))),
+            Arc::new(StringArray::from_iter_values(self.rows.iter().map(|row| row.input.as_str()))),
+        ];
+        let batch = RecordBatch::try_new(Arc::clone(&schema), columns)?;
+
+        let output_file = storage::create_output(path)?;
+        let props = WriterProperties::builder().build();
+        let mut writer = ArrowWriter::try_new(output_file, schema, Some(props))?;
+        writer.write(&batch)?;
+        writer.into_inner()?.finish()?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,71 @@
+//! Output schema coercion for writing batches from mixed sources
+//!
+//! Batches that differ only in field nullability (e.g. a non-nullable
+//! `page_id` in one input, nullable in another) are rejected by
+//! `ArrowWriter::write` and `RecordBatch::try_new`. Relaxing every field to
+//! nullable — as the hand-built output schemas already do for parsed text —
+//! lets all of them be written under one schema.
+
+use anyhow::Result;
+use arrow::array::{ArrayRef, RecordBatch};
+use arrow::compute::cast;
+use arrow::datatypes::{Schema, SchemaRef};
+use std::sync::Arc;
+
+/// Copy of a schema with every top-level field marked nullable
+pub fn nullable_schema(schema: &Schema) -> SchemaRef {
+    let fields: Vec<_> = schema
+        .fields()
+        .iter()
+        .map(|field| field.as_ref().clone().with_nullable(true))
+        .collect();
+    Arc::new(Schema::new_with_metadata(fields, schema.metadata().clone()))
+}
+
+/// How `actual` differs from `expected` in column names, order or data types
+/// (nullability is ignored, as `nullable_schema` relaxes it anyway); `None` if it doesn't
+pub fn mismatch(expected: &Schema, actual: &Schema) -> Option<String> {
+    let mut problems = Vec::new();
+    for field in expected.fields() {
+        match actual.field_with_name(field.name()) {
+            Ok(other) if other.data_type() != field.data_type() => {
+                problems.push(format!("{} is {}, expected {}", field.name(), other.data_type(), field.data_type()))
+            }
+            Ok(_) => {}
+            Err(_) => problems.push(format!("missing column {}", field.name())),
+        }
+    }
+    for field in actual.fields() {
+        if expected.field_with_name(field.name()).is_err() {
+            problems.push(format!("extra column {}", field.name()));
+        }
+    }
+    let names = |schema: &Schema| schema.fields().iter().map(|field| field.name().clone()).collect::<Vec<_>>();
+    if problems.is_empty() && names(expected) != names(actual) {
+        problems.push("columns are in a different order".to_string());
+    }
+    (!problems.is_empty()).then(|| problems.join("; "))
+}
+
+/// Rebuild a batch under the given (relaxed) schema, casting columns whose
+/// data type differs only in nested nullability
+pub fn coerce_batch(batch: &RecordBatch, schema: &SchemaRef) -> Result<RecordBatch> {
+    if batch.schema() == *schema {
+        return Ok(batch.clone());
+    }
+
+    let columns: Vec<ArrayRef> = schema
+        .fields()
+        .iter()
+        .zip(batch.columns())
+        .map(|(field, column)| {
+            if column.data_type() == field.data_type() {
+                Ok(Arc::clone(column))
+            } else {
+                Ok(cast(column, field.data_type())?)
+            }
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(RecordBatch::try_new(Arc::clone(schema), columns)?)
+}
@@ -0,0 +1,60 @@
+//! Section structure of parsed text (`--output-format sections-json`,
+//! `--emit-section-path`)
+//!
+//! Works on text parsed with `HeadingStyle::Markdown`: every paragraph with a
+//! `#` prefix starts a new section, and the paragraphs up to the next heading
+//! belong to it. Paragraphs before the first heading form the lead, which has
+//! no heading or level and is left out when empty.
+
+use crate::parser;
+
+/// One section: its heading (None for the lead) and the paragraphs under it
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Section {
+    pub heading: Option<String>,
+    /// Heading level, 1-6 (None for the lead)
+    pub level: Option<u8>,
+    pub paragraphs: Vec<String>,
+}
+
+/// Split a text with Markdown headings into sections, in order
+pub fn split_sections(text: &str) -> Vec<Section> {
+    let mut sections = vec![Section { heading: None, level: None, paragraphs: Vec::new() }];
+    for para in text.split("\n\n").map(str::trim).filter(|para| !para.is_empty()) {
+        match parser::heading_marker(para) {
+            Some((level, heading)) => sections.push(Section {
+                heading: Some(heading.to_string()),
+                level: Some(level),
+                paragraphs: Vec::new(),
+            }),
+            None => sections.last_mut().expect("lead section").paragraphs.push(para.to_string()),
+        }
+    }
+    if sections[0].paragraphs.is_empty() {
+        sections.remove(0);
+    }
+    sections
+}
+
+/// Separator between the headings of a section path
+pub const PATH_SEPARATOR: &str = " > ";
+
+/// Every non-heading paragraph with the path of headings enclosing it
+/// ("История > Ранние годы"); lead paragraphs have no path
+/// A heading closes every open heading of the same or a deeper level
+pub fn paragraphs_with_paths(text: &str) -> Vec<(Option<String>, String)> {
+    let mut stack: Vec<(u8, String)> = Vec::new();
+    let mut result = Vec::new();
+    for section in split_sections(text) {
+        if let (Some(level), Some(heading)) = (section.level, section.heading) {
+            while stack.last().is_some_and(|(open, _)| *open >= level) {
+                stack.pop();
+            }
+            stack.push((level, heading));
+        }
+        let path = (!stack.is_empty())
+            .then(|| stack.iter().map(|(_, heading)| heading.as_str()).collect::<Vec<_>>().join(PATH_SEPARATOR));
+        result.extend(section.paragraphs.into_iter().map(|para| (path.clone(), para)));
+    }
+    result
+}
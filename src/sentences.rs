@@ -0,0 +1,89 @@
+//! Rule-based sentence segmentation of parsed text (`--segment-sentences`)
+//!
+//! Line and paragraph breaks always end a sentence. Inside a line, a run of
+//! `.`, `!`, `?` or `…` (with any closing quotes or brackets) ends one when
+//! whitespace and a sentence start follow: an uppercase letter, a digit, an
+//! opening quote or a dialogue dash. A period after a single letter
+//! (initials, "т. е.", "т. н.") or after a known abbreviation ("см.", "г.",
+//! "млн.") does not, except for the list endings "т. д.", "т. п.", "др.",
+//! "пр." and "etc.". Periods inside numbers ("3.14", "1.5 млн") are never
+//! followed by whitespace, so decimals stay whole.
+
+/// Abbreviations (lowercase, without the period) a sentence does not end after
+const ABBREVIATIONS: &[&str] = &[
+    "акад", "англ", "букв", "вв", "гг", "гл", "греч", "дер", "доц", "изд", "им", "кв", "коп", "лат", "млн", "млрд", "нем",
+    "обл", "ок", "пер", "пос", "проф", "просп", "род", "рис", "руб", "св", "см", "ср", "ст", "стр", "табл", "тыс", "ул",
+    "франц", "ч", "dr", "jr", "mr", "mrs", "ms", "no", "prof", "sr", "st", "vs",
+];
+
+/// Abbreviations that close a list, so a capital after them starts a new sentence
+const LIST_ENDINGS: &[&str] = &["т. д", "т.д", "т. п", "т.п", "др", "пр", "etc"];
+
+/// Split a text into sentences, trimmed, in order
+pub fn split_sentences(text: &str) -> Vec<String> {
+    let mut sentences = Vec::new();
+    for line in text.lines().map(str::trim).filter(|line| !line.is_empty()) {
+        let chars: Vec<(usize, char)> = line.char_indices().collect();
+        let mut start = 0;
+        let mut i = 0;
+        while i < chars.len() {
+            if !is_terminator(chars[i].1) {
+                i += 1;
+                continue;
+            }
+            let mut end = i + 1;
+            while end < chars.len() && (is_terminator(chars[end].1) || is_closing(chars[end].1)) {
+                end += 1;
+            }
+            let mut next = end;
+            while next < chars.len() && chars[next].1.is_whitespace() {
+                next += 1;
+            }
+            // Terminators must be followed by whitespace and more text on the line
+            if next == end || next == chars.len() {
+                i = end;
+                continue;
+            }
+            let single_period = chars[i].1 == '.' && (i + 1 == end || !is_terminator(chars[i + 1].1));
+            let abbreviated = single_period && is_abbreviation(&line[start..chars[i].0]);
+            if starts_sentence(chars[next].1) && !abbreviated {
+                sentences.push(line[start..chars[end].0].trim().to_string());
+                start = chars[next].0;
+            }
+            i = next;
+        }
+        let rest = line[start..].trim();
+        if !rest.is_empty() {
+            sentences.push(rest.to_string());
+        }
+    }
+    sentences
+}
+
+fn is_terminator(c: char) -> bool {
+    matches!(c, '.' | '!' | '?' | '…')
+}
+
+fn is_closing(c: char) -> bool {
+    matches!(c, '»' | '"' | '”' | '’' | '\'' | ')' | ']')
+}
+
+fn starts_sentence(c: char) -> bool {
+    c.is_uppercase() || c.is_numeric() || matches!(c, '«' | '"' | '„' | '“' | '(' | '[' | '—' | '–')
+}
+
+/// Whether the text before a period ends in a word the period abbreviates
+fn is_abbreviation(before: &str) -> bool {
+    let lowered = before.to_lowercase();
+    if LIST_ENDINGS.iter().any(|ending| {
+        lowered.ends_with(ending) && !lowered[..lowered.len() - ending.len()].ends_with(char::is_alphabetic)
+    }) {
+        return false;
+    }
+    let word = lowered.rsplit(|c: char| !c.is_alphabetic()).next().unwrap_or_default();
+    match word.chars().count() {
+        0 => false,
+        1 => true,
+        _ => ABBREVIATIONS.contains(&word),
+    }
+}
@@ -0,0 +1,62 @@
+//! 64-bit SimHash fingerprints for near-duplicate detection
+//!
+//! Text is lowercased and split into alphanumeric tokens, tokens are grouped
+//! into overlapping word shingles, and each shingle is hashed with FNV-1a.
+//! The result depends only on the input text (no random seeds, no floats), so
+//! fingerprints are stable across runs and machines. Near-duplicate articles
+//! have fingerprints with a small Hamming distance.
+
+/// Number of consecutive tokens per shingle
+const SHINGLE_SIZE: usize = 3;
+
+/// Compute the 64-bit SimHash of a text
+pub fn simhash(text: &str) -> u64 {
+    let lowered = text.to_lowercase();
+    let tokens: Vec<&str> = lowered
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|token| !token.is_empty())
+        .collect();
+
+    if tokens.is_empty() {
+        return 0;
+    }
+
+    let mut weights = [0i64; 64];
+    let window = SHINGLE_SIZE.min(tokens.len());
+
+    for shingle in tokens.windows(window) {
+        let hash = fnv1a(shingle);
+        for (bit, weight) in weights.iter_mut().enumerate() {
+            if hash & (1 << bit) != 0 {
+                *weight += 1;
+            } else {
+                *weight -= 1;
+            }
+        }
+    }
+
+    weights
+        .iter()
+        .enumerate()
+        .filter(|(_, &weight)| weight > 0)
+        .fold(0u64, |acc, (bit, _)| acc | (1 << bit))
+}
+
+/// FNV-1a 64-bit hash of a token shingle (tokens separated by a space)
+fn fnv1a(tokens: &[&str]) -> u64 {
+    const OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+    const PRIME: u64 = 0x100000001b3;
+
+    let mut hash = OFFSET_BASIS;
+    for (i, token) in tokens.iter().enumerate() {
+        if i > 0 {
+            hash ^= u64::from(b' ');
+            hash = hash.wrapping_mul(PRIME);
+        }
+        for byte in token.bytes() {
+            hash ^= u64::from(byte);
+            hash = hash.wrapping_mul(PRIME);
+        }
+    }
+    hash
+}
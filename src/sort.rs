@@ -0,0 +1,50 @@
+//! Sorting output rows by a key column before writing
+//!
+//! Per-batch sorting is cheap: each batch is sorted on its own and written as
+//! its own row group, so every row group is sorted but row groups may overlap.
+//! Global sorting concatenates all output batches into one and sorts that,
+//! which needs a second full in-memory copy of the output.
+//!
+//! Either way the sort order is recorded as `sorting_columns` in the parquet
+//! row group metadata. Keys compare by their Arrow type (string page IDs
+//! sort lexicographically); nulls go last.
+
+use anyhow::Result;
+use arrow::array::RecordBatch;
+use arrow::compute::{concat_batches, sort_to_indices, take_record_batch, SortOptions};
+use arrow::datatypes::Schema;
+use parquet::format::SortingColumn;
+
+const SORT_OPTIONS: SortOptions = SortOptions { descending: false, nulls_first: false };
+
+/// Sort a batch's rows by the given column
+pub fn sort_batch(batch: &RecordBatch, column: &str) -> Result<RecordBatch> {
+    let key = batch
+        .column_by_name(column)
+        .ok_or_else(|| anyhow::anyhow!("Sort column '{}' not found", column))?;
+    let indices = sort_to_indices(key, Some(SORT_OPTIONS), None)?;
+    Ok(take_record_batch(batch, &indices)?)
+}
+
+/// Merge all batches into one and sort it by the given column
+pub fn sort_all(batches: &[RecordBatch], column: &str) -> Result<RecordBatch> {
+    let schema = batches
+        .first()
+        .map(|batch| batch.schema())
+        .ok_or_else(|| anyhow::anyhow!("No batches to sort"))?;
+    let merged = concat_batches(&schema, batches)?;
+    sort_batch(&merged, column)
+}
+
+/// Parquet `sorting_columns` metadata for a sort on the given column
+/// Output schemas are flat, so the top-level index is also the leaf column index
+pub fn sorting_columns(schema: &Schema, column: &str) -> Result<Vec<SortingColumn>> {
+    let (index, _) = schema
+        .column_with_name(column)
+        .ok_or_else(|| anyhow::anyhow!("Sort column '{}' not found", column))?;
+    Ok(vec![SortingColumn {
+        column_idx: index as i32,
+        descending: SORT_OPTIONS.descending,
+        nulls_first: SORT_OPTIONS.nulls_first,
+    }])
+}
@@ -0,0 +1,134 @@
+//! Parse statistics reported by `--dry-run` and written by `--stats-output`
+//!
+//! `ParseStats` counts rows by parse status and sums paragraph and character
+//! counts of the parsed texts, so a dump can be profiled without writing any
+//! output. `RowStats` keeps the same measures per article, in a sidecar table
+//! keyed by page ID for downstream quality filtering.
+
+use crate::status;
+use anyhow::Result;
+use arrow::array::{Array, ArrayRef, RecordBatch, StringArray, UInt32Array, UInt64Array};
+use arrow::datatypes::{Field, Schema};
+use std::fmt;
+use std::sync::Arc;
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ParseStats {
+    /// Rows with a non-null input text
+    pub rows: usize,
+    /// Rows skipped by the output size guard (`--max-output-bytes`)
+    pub too_large: usize,
+    /// Rows skipped by the per-article timeout
+    pub timeouts: usize,
+    /// Rows passed through unparsed by `--since`
+    pub unchanged: usize,
+    /// Rows parsed successfully
+    pub parsed: usize,
+    /// Paragraphs across all parsed texts
+    pub paragraphs: usize,
+    /// Characters across all parsed texts
+    pub chars: usize,
+}
+
+impl ParseStats {
+    /// Add one batch of parsed texts and their `parse_status` values
+    pub fn record(&mut self, texts: &StringArray, statuses: &StringArray) {
+        for i in 0..statuses.len() {
+            if statuses.is_null(i) {
+                continue;
+            }
+            self.rows += 1;
+            match statuses.value(i) {
+                status::OK => {
+                    self.parsed += 1;
+                    if !texts.is_null(i) {
+                        let text = texts.value(i);
+                        self.paragraphs += paragraph_count(text);
+                        self.chars += text.chars().count();
+                    }
+                }
+                status::UNCHANGED => self.unchanged += 1,
+                "timeout" => self.timeouts += 1,
+                "too_large" => self.too_large += 1,
+                _ => {}
+            }
+        }
+    }
+
+    /// Mean paragraph count per parsed row
+    pub fn average_paragraphs(&self) -> f64 {
+        if self.parsed == 0 {
+            0.0
+        } else {
+            self.paragraphs as f64 / self.parsed as f64
+        }
+    }
+}
+
+/// Non-empty paragraphs of a parsed text
+fn paragraph_count(text: &str) -> usize {
+    text.split("\n\n").filter(|p| !p.trim().is_empty()).count()
+}
+
+/// Columns of one batch's `--stats-output` table: the page IDs, then per text
+/// column `input_chars`, `output_chars`, `paragraphs`, `parse_status` and
+/// `parse_warnings`, named by the caller (e.g. `official_input_chars`)
+pub struct RowStats {
+    fields: Vec<Field>,
+    columns: Vec<ArrayRef>,
+}
+
+impl RowStats {
+    /// Start a batch's stats with its page ID column (kept in its source type)
+    pub fn new(page_id_name: &str, page_ids: &ArrayRef) -> Self {
+        RowStats {
+            fields: vec![Field::new(page_id_name, page_ids.data_type().clone(), true)],
+            columns: vec![Arc::clone(page_ids)],
+        }
+    }
+
+    /// Add the metrics of one text column: raw inputs, parsed texts (null when
+    /// skipped), `parse_status` and `parse_warnings` arrays of the same rows
+    pub fn add(
+        &mut self,
+        column_name: impl Fn(&str) -> String,
+        inputs: &[Option<&str>],
+        texts: &[Option<String>],
+        statuses: &ArrayRef,
+        warnings: &ArrayRef,
+    ) {
+        let input_chars = inputs.iter().map(|text| text.map(|text| text.chars().count() as u64));
+        let output_chars = texts.iter().map(|text| text.as_deref().map(|text| text.chars().count() as u64));
+        let paragraphs = texts.iter().map(|text| text.as_deref().map(|text| paragraph_count(text) as u32));
+        let columns: [(&str, ArrayRef); 5] = [
+            ("input_chars", Arc::new(UInt64Array::from_iter(input_chars))),
+            ("output_chars", Arc::new(UInt64Array::from_iter(output_chars))),
+            ("paragraphs", Arc::new(UInt32Array::from_iter(paragraphs))),
+            ("parse_status", Arc::clone(statuses)),
+            ("parse_warnings", Arc::clone(warnings)),
+        ];
+        for (name, column) in columns {
+            self.fields.push(Field::new(column_name(name), column.data_type().clone(), true));
+            self.columns.push(column);
+        }
+    }
+
+    /// The stats table of the batch
+    pub fn finish(self) -> Result<RecordBatch> {
+        Ok(RecordBatch::try_new(Arc::new(Schema::new(self.fields)), self.columns)?)
+    }
+}
+
+impl fmt::Display for ParseStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "  Total rows:                 {}", self.rows)?;
+        writeln!(f, "  Parsed:                     {}", self.parsed)?;
+        writeln!(f, "  Skipped (output too large): {}", self.too_large)?;
+        writeln!(f, "  Timed out:                  {}", self.timeouts)?;
+        if self.unchanged > 0 {
+            writeln!(f, "  Unchanged (--since):        {}", self.unchanged)?;
+        }
+        writeln!(f, "  Average paragraphs:         {:.1}", self.average_paragraphs())?;
+        write!(f, "  Total output chars:         {}", self.chars)
+    }
+}
@@ -0,0 +1,116 @@
+//! Per-row `parse_status` output column
+//!
+//! Articles skipped by the timeout or the output size guard get a null text and
+//! a status naming the reason, so downstream consumers never mistake a
+//! placeholder for article text. `--report-warnings` adds a `parse_warnings`
+//! column counting what `parse_wiki_text` complained about, which tells
+//! malformed input apart from genuinely empty articles. `--flag-skips` adds
+//! `was_skipped`/`was_truncated` booleans for consumers that only filter.
+
+use crate::parser::{self, ParseResult, ParserWarnings, SkipReason};
+use arrow::array::{ArrayRef, BooleanArray, StringArray, UInt32Array};
+use std::sync::Arc;
+
+/// Status of a row whose text was parsed
+pub const OK: &str = "ok";
+/// Status of a row passed through unparsed because it is older than `--since`
+pub const UNCHANGED: &str = "unchanged";
+
+/// Build the status column for a text column's results; null inputs get a null status
+pub fn status_array(results: &[Option<ParseResult>], changed: Option<&BooleanArray>) -> ArrayRef {
+    let statuses = results.iter().enumerate().map(|(i, result)| {
+        result.as_ref().map(|result| match result {
+            Err(reason) => reason.as_str(),
+            Ok(_) if changed.is_some_and(|mask| !mask.value(i)) => UNCHANGED,
+            Ok(_) => OK,
+        })
+    });
+    Arc::new(StringArray::from_iter(statuses))
+}
+
+/// Report articles skipped by the output size guard on stderr, described by `label`
+/// (timeouts are reported by the pool as they happen)
+pub fn report_too_large<L>(results: &[Option<ParseResult>], max_output_bytes: usize, label: L)
+where
+    L: Fn(usize) -> String,
+{
+    for (i, result) in results.iter().enumerate() {
+        if let Some(Err(SkipReason::TooLarge)) = result {
+            eprintln!("WARNING: {} exceeded {} bytes of output, skipped", label(i), max_output_bytes);
+        }
+    }
+}
+
+/// Build the `parse_warnings` column from the warnings the pool collected while
+/// parsing; null, skipped and unchanged rows get null
+/// Rows with warnings are reported on stderr, described by `label`
+pub fn warning_counts<L>(
+    warnings: &[ParserWarnings],
+    results: &[Option<ParseResult>],
+    changed: Option<&BooleanArray>,
+    label: L,
+) -> ArrayRef
+where
+    L: Fn(usize) -> String,
+{
+    count_warnings(warnings, results, changed, Some(&label))
+}
+
+/// `warning_counts` without the stderr report (`--stats-output` without `--report-warnings`)
+pub fn silent_warning_counts(
+    warnings: &[ParserWarnings],
+    results: &[Option<ParseResult>],
+    changed: Option<&BooleanArray>,
+) -> ArrayRef {
+    count_warnings(warnings, results, changed, None)
+}
+
+fn count_warnings(
+    warnings: &[ParserWarnings],
+    results: &[Option<ParseResult>],
+    changed: Option<&BooleanArray>,
+    label: Option<&dyn Fn(usize) -> String>,
+) -> ArrayRef {
+    let counts = warnings.iter().zip(results).enumerate().map(|(i, (warnings, result))| {
+        let parsed = matches!(result, Some(Ok(_))) && changed.is_none_or(|mask| mask.value(i));
+        if !parsed {
+            return None;
+        }
+        let total: usize = warnings.iter().map(|(_, count)| count).sum();
+        if let (true, Some(label)) = (total > 0, label) {
+            let kinds: Vec<String> = warnings.iter().map(|(kind, count)| format!("{} x{}", kind, count)).collect();
+            eprintln!("  {}: {} parser warning(s) ({})", label(i), total, kinds.join(", "));
+        }
+        Some(total as u32)
+    });
+    Arc::new(UInt32Array::from_iter(counts))
+}
+
+/// Build the `was_skipped` column: whether the article was skipped (timeout,
+/// output limit); null inputs get null
+pub fn skipped_array(results: &[Option<ParseResult>]) -> ArrayRef {
+    Arc::new(BooleanArray::from_iter(results.iter().map(|result| result.as_ref().map(Result::is_err))))
+}
+
+/// Cut parsed texts to `max_chars` characters (`parser::truncate_chars`; 0 = no limit)
+/// and build the `was_truncated` column: true where a text was cut, null for null
+/// inputs. Rows unchanged since `--since` keep their source text whole
+pub fn truncate_results(results: &mut [Option<ParseResult>], max_chars: usize, changed: Option<&BooleanArray>) -> ArrayRef {
+    let truncated = results.iter_mut().enumerate().map(|(i, result)| {
+        let result = result.as_mut()?;
+        let parsed = changed.is_none_or(|mask| mask.value(i));
+        match result {
+            Ok(text) if parsed && max_chars > 0 && text.chars().count() > max_chars => {
+                *text = parser::truncate_chars(text, max_chars);
+                Some(true)
+            }
+            _ => Some(false),
+        }
+    });
+    Arc::new(truncated.collect::<BooleanArray>())
+}
+
+/// Output texts for a text column's results; skipped articles become null
+pub fn texts(results: Vec<Option<ParseResult>>) -> Vec<Option<String>> {
+    results.into_iter().map(|result| result.and_then(Result::ok)).collect()
+}
@@ -0,0 +1,365 @@
+//! Local and object-storage file access for parquet input/output
+//!
+//! Paths starting with `s3://` or `gs://` are routed to `object_store`;
+//! everything else is treated as a local filesystem path. Credentials and
+//! region come from the usual environment variables (`AWS_ACCESS_KEY_ID`,
+//! `AWS_REGION`, `GOOGLE_SERVICE_ACCOUNT`, ...).
+//!
+//! Remote input is downloaded into memory and remote output is buffered in
+//! memory and uploaded once the writer is finished, so nothing is staged on
+//! local disk.
+//!
+//! Local output goes to a hidden temporary file next to the target and is
+//! renamed over it only when the writer is finished, so an interrupted or
+//! failed run leaves any previous output untouched instead of half-written.
+
+use anyhow::Result;
+use bytes::Bytes;
+use object_store::aws::AmazonS3Builder;
+use object_store::gcp::GoogleCloudStorageBuilder;
+use object_store::path::Path as ObjectPath;
+use object_store::{ObjectStore, PutPayload};
+use arrow::datatypes::{Schema, SchemaRef};
+use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+use parquet::file::reader::{ChunkReader, Length};
+use std::fs::File;
+use std::future::Future;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use url::Url;
+
+/// Parquet input backed by a local file or an in-memory object download
+pub enum InputFile {
+    Local(File),
+    Remote(Bytes),
+}
+
+impl Length for InputFile {
+    fn len(&self) -> u64 {
+        match self {
+            InputFile::Local(file) => Length::len(file),
+            InputFile::Remote(bytes) => Length::len(bytes),
+        }
+    }
+}
+
+impl ChunkReader for InputFile {
+    type T = Box<dyn Read + Send>;
+
+    fn get_read(&self, start: u64) -> parquet::errors::Result<Self::T> {
+        Ok(match self {
+            InputFile::Local(file) => Box::new(file.get_read(start)?),
+            InputFile::Remote(bytes) => Box::new(bytes.get_read(start)?),
+        })
+    }
+
+    fn get_bytes(&self, start: u64, length: usize) -> parquet::errors::Result<Bytes> {
+        match self {
+            InputFile::Local(file) => file.get_bytes(start, length),
+            InputFile::Remote(bytes) => bytes.get_bytes(start, length),
+        }
+    }
+}
+
+/// Parquet output written to a local file or buffered for upload to object storage
+pub enum OutputFile {
+    Local(LocalOutput),
+    Remote {
+        store: Box<dyn ObjectStore>,
+        path: ObjectPath,
+        buffer: Vec<u8>,
+    },
+}
+
+impl Write for OutputFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            OutputFile::Local(output) => output.file()?.write(buf),
+            OutputFile::Remote { buffer, .. } => buffer.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            OutputFile::Local(output) => output.file()?.flush(),
+            OutputFile::Remote { .. } => Ok(()),
+        }
+    }
+}
+
+impl OutputFile {
+    /// Move local output into place or upload buffered remote output
+    /// Must be called after the parquet writer has been finalized
+    pub fn finish(self) -> Result<()> {
+        match self {
+            OutputFile::Local(output) => output.finish()?,
+            OutputFile::Remote { store, path, buffer } => {
+                println!("Uploading {} bytes to object storage", buffer.len());
+                block_on(store.put(&path, PutPayload::from(buffer)))??;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Distinguishes temporary files of several outputs to the same path in one process
+static TEMP_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// Local output written to `.<name>.<pid>.<n>.tmp` in the target's directory
+/// and renamed to the target by `finish`; dropped unfinished, the temporary
+/// file is removed
+pub struct LocalOutput {
+    file: Option<File>,
+    temp_path: PathBuf,
+    path: PathBuf,
+}
+
+impl LocalOutput {
+    fn create(path: &str) -> Result<Self> {
+        let path = PathBuf::from(path);
+        let name = path
+            .file_name()
+            .ok_or_else(|| anyhow::anyhow!("Output path {} has no file name", path.display()))?
+            .to_string_lossy();
+        let temp_name = format!(
+            ".{}.{}.{}.tmp",
+            name,
+            std::process::id(),
+            TEMP_COUNTER.fetch_add(1, Ordering::Relaxed)
+        );
+        let temp_path = path.with_file_name(temp_name);
+        // create_new: never reuse a temporary file another process is writing
+        let file = File::options()
+            .write(true)
+            .create_new(true)
+            .open(&temp_path)
+            .map_err(|e| anyhow::anyhow!("Could not create {}: {}", temp_path.display(), e))?;
+        Ok(LocalOutput { file: Some(file), temp_path, path })
+    }
+
+    fn file(&mut self) -> io::Result<&mut File> {
+        self.file.as_mut().ok_or_else(|| io::Error::other("output already finished"))
+    }
+
+    /// Sync the temporary file and rename it over the target
+    fn finish(mut self) -> Result<()> {
+        // Still owned while syncing, so a failed flush or sync leaves the cleanup to `Drop`
+        if let Some(file) = self.file.as_mut() {
+            file.flush()?;
+            file.sync_all()?;
+        }
+        // Closed before renaming; Windows cannot rename an open file
+        drop(self.file.take());
+        if let Err(e) = std::fs::rename(&self.temp_path, &self.path) {
+            let _ = std::fs::remove_file(&self.temp_path);
+            anyhow::bail!("Could not move {} to {}: {}", self.temp_path.display(), self.path.display(), e);
+        }
+        Ok(())
+    }
+}
+
+impl Drop for LocalOutput {
+    fn drop(&mut self) {
+        if self.file.take().is_some() {
+            let _ = std::fs::remove_file(&self.temp_path);
+        }
+    }
+}
+
+/// Open a parquet input from a local path or an `s3://`/`gs://` URL
+pub fn open_input(path: &str) -> Result<InputFile> {
+    match object_store_url(path)? {
+        Some(url) => {
+            let (store, object_path) = open_store(&url)?;
+            let bytes = block_on(async {
+                let result = store.get(&object_path).await?;
+                result.bytes().await
+            })??;
+            Ok(InputFile::Remote(bytes))
+        }
+        None => Ok(InputFile::Local(File::open(path)?)),
+    }
+}
+
+/// Create a parquet output at a local path or an `s3://`/`gs://` URL
+/// Nothing appears at the path until `OutputFile::finish`
+pub fn create_output(path: &str) -> Result<OutputFile> {
+    match object_store_url(path)? {
+        Some(url) => {
+            let (store, path) = open_store(&url)?;
+            Ok(OutputFile::Remote { store, path, buffer: Vec::new() })
+        }
+        None => Ok(OutputFile::Local(LocalOutput::create(path)?)),
+    }
+}
+
+/// Expand `--input` values into input paths
+/// Local paths with `*` or `?` in their file name match the files of their
+/// directory, in name order; other values, object-storage URLs included,
+/// are used as they are. A pattern matching nothing is an error.
+pub fn expand_inputs(patterns: &[String]) -> Result<Vec<String>> {
+    let mut inputs = Vec::new();
+    for pattern in patterns {
+        let path = Path::new(pattern);
+        let name = path.file_name().and_then(|name| name.to_str()).unwrap_or_default();
+        if object_store_url(pattern)?.is_some() || !name.contains(['*', '?']) {
+            inputs.push(pattern.clone());
+            continue;
+        }
+        let dir = path.parent().filter(|dir| !dir.as_os_str().is_empty()).unwrap_or(Path::new("."));
+        let mut matches: Vec<String> = std::fs::read_dir(dir)
+            .map_err(|e| anyhow::anyhow!("Could not list {} for --input {}: {}", dir.display(), pattern, e))?
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.path().is_file())
+            .filter(|entry| entry.file_name().to_str().is_some_and(|file| wildcard_match(name, file)))
+            .map(|entry| path.with_file_name(entry.file_name()).to_string_lossy().into_owned())
+            .collect();
+        if matches.is_empty() {
+            anyhow::bail!("--input {} did not match any file", pattern);
+        }
+        matches.sort();
+        inputs.extend(matches);
+    }
+    Ok(inputs)
+}
+
+/// Match a file name against a pattern where `*` stands for any run of characters and `?` for one
+fn wildcard_match(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    // Position after the last `*` and the name position it was tried at
+    let (mut p, mut n, mut star) = (0, 0, None);
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p + 1, n));
+                p += 1;
+            }
+            Some(c) if *c == '?' || *c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match star {
+                Some((after_star, tried)) => {
+                    p = after_star;
+                    n = tried + 1;
+                    star = Some((after_star, tried + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+/// Schema and row count of every input; all inputs must share the first one's schema
+pub fn check_inputs(inputs: &[String]) -> Result<(SchemaRef, Vec<u64>)> {
+    let mut schema: Option<SchemaRef> = None;
+    let mut rows = Vec::with_capacity(inputs.len());
+    for input in inputs {
+        let builder = ParquetRecordBatchReaderBuilder::try_new(open_input(input)?)?;
+        rows.push(builder.metadata().file_metadata().num_rows() as u64);
+        match &schema {
+            None => schema = Some(builder.schema().clone()),
+            Some(expected) => {
+                if let Some(problem) = crate::schema::mismatch(expected, builder.schema()) {
+                    anyhow::bail!("Input {} does not have the schema of {}: {}", input, inputs[0], problem);
+                }
+            }
+        }
+    }
+    let schema = schema.ok_or_else(|| anyhow::anyhow!("No input files given"))?;
+    Ok((schema, rows))
+}
+
+/// Path of the output for `input` in `dir`: the input's file name with `extension`
+/// Local directories are created; object-storage prefixes need no creating
+pub fn output_in_dir(dir: &str, input: &str, extension: &str) -> Result<String> {
+    let stem = Path::new(input)
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .ok_or_else(|| anyhow::anyhow!("Cannot name an output after input {}", input))?;
+    if object_store_url(dir)?.is_none() {
+        std::fs::create_dir_all(dir)?;
+    }
+    Ok(format!("{}/{}.{}", dir.trim_end_matches('/'), stem, extension))
+}
+
+/// Whether two local paths or object-storage URLs name the same file
+/// Local paths are compared after resolving `.`, `..` and symlinks; a path
+/// that does not exist yet cannot be an existing input
+pub fn same_location(a: &str, b: &str) -> Result<bool> {
+    Ok(match (object_store_url(a)?, object_store_url(b)?) {
+        (None, None) => match (std::fs::canonicalize(a), std::fs::canonicalize(b)) {
+            (Ok(a), Ok(b)) => a == b,
+            _ => false,
+        },
+        (Some(a), Some(b)) => a == b,
+        _ => false,
+    })
+}
+
+/// Limit a reader to the first `sample` rows, returning it with the row count to expect
+/// The limit is pushed into the reader, so row groups past it are never decoded
+pub fn with_sample(
+    builder: ParquetRecordBatchReaderBuilder<InputFile>,
+    sample: Option<usize>,
+) -> (ParquetRecordBatchReaderBuilder<InputFile>, u64) {
+    let total_rows = builder.metadata().file_metadata().num_rows() as u64;
+    match sample {
+        Some(limit) => (builder.with_limit(limit), total_rows.min(limit as u64)),
+        None => (builder, total_rows),
+    }
+}
+
+/// Re-open a written parquet output and check its row count and schema
+/// Guards against truncated or corrupt output going unnoticed on long runs
+pub fn verify_output(path: &str, expected_rows: usize, expected_schema: &Schema) -> Result<()> {
+    println!("Verifying output file: {}", path);
+
+    let builder = ParquetRecordBatchReaderBuilder::try_new(open_input(path)?)?;
+    let rows = builder.metadata().file_metadata().num_rows();
+    if rows != expected_rows as i64 {
+        anyhow::bail!("Output verification failed: {} has {} rows, expected {}", path, rows, expected_rows);
+    }
+    if builder.schema().fields() != expected_schema.fields() {
+        anyhow::bail!(
+            "Output verification failed: {} schema does not match the written schema\n  written: {:?}\n  read:    {:?}",
+            path,
+            expected_schema.fields(),
+            builder.schema().fields()
+        );
+    }
+
+    println!("Output verified: {} rows", rows);
+    Ok(())
+}
+
+/// Parse the path as an object-storage URL if it uses a supported scheme
+fn object_store_url(path: &str) -> Result<Option<Url>> {
+    if path.starts_with("s3://") || path.starts_with("gs://") {
+        Ok(Some(Url::parse(path)?))
+    } else {
+        Ok(None)
+    }
+}
+
+/// Build the object store backend for a URL, configured from the environment
+fn open_store(url: &Url) -> Result<(Box<dyn ObjectStore>, ObjectPath)> {
+    let store: Box<dyn ObjectStore> = match url.scheme() {
+        "s3" => Box::new(AmazonS3Builder::from_env().with_url(url.as_str()).build()?),
+        "gs" => Box::new(GoogleCloudStorageBuilder::from_env().with_url(url.as_str()).build()?),
+        scheme => anyhow::bail!("Unsupported storage scheme: {}", scheme),
+    };
+    let path = ObjectPath::from_url_path(url.path())?;
+    Ok((store, path))
+}
+
+/// Run an object-store future to completion on a single-threaded runtime
+fn block_on<F: Future>(future: F) -> Result<F::Output> {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?;
+    Ok(runtime.block_on(future))
+}
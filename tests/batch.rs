@@ -0,0 +1,49 @@
+//! The in-memory batch API parses text columns and keeps everything else
+
+use std::sync::Arc;
+
+use arrow::array::{Array, ArrayRef, Int64Array, RecordBatch, StringArray};
+use wikitext_parser::batch::{process_record_batch, BatchOptions, BatchProcessor};
+
+fn names(batch: &RecordBatch) -> Vec<String> {
+    batch.schema().fields().iter().map(|field| field.name().clone()).collect()
+}
+
+fn strings(batch: &RecordBatch, name: &str) -> Vec<Option<String>> {
+    let column = batch.column_by_name(name).unwrap().as_any().downcast_ref::<StringArray>().unwrap();
+    column.iter().map(|value| value.map(str::to_string)).collect()
+}
+
+#[test]
+fn text_column_is_replaced_by_parsed_text_and_status() {
+    let batch = RecordBatch::try_from_iter(vec![
+        ("page_id", Arc::new(Int64Array::from(vec![1, 2])) as ArrayRef),
+        ("text", Arc::new(StringArray::from(vec![Some("'''Москва''' — [[столица]]."), None])) as ArrayRef),
+    ])
+    .unwrap();
+
+    let parsed = process_record_batch(&batch, &BatchOptions::default()).unwrap();
+    assert_eq!(names(&parsed), vec!["page_id", "text_parsed", "parse_status"]);
+    assert_eq!(strings(&parsed, "text_parsed"), vec![Some("Москва — столица.".to_string()), None]);
+    assert_eq!(strings(&parsed, "parse_status"), vec![Some("ok".to_string()), None]);
+    assert_eq!(parsed.column(0).len(), 2);
+}
+
+#[test]
+fn several_text_columns_get_their_own_status() {
+    let batch = RecordBatch::try_from_iter(vec![
+        ("official", Arc::new(StringArray::from(vec!["a"])) as ArrayRef),
+        ("clone", Arc::new(StringArray::from(vec!["b"])) as ArrayRef),
+    ])
+    .unwrap();
+    let processor = BatchProcessor::new(BatchOptions {
+        text_columns: vec!["official".to_string(), "clone".to_string()],
+        threads: 1,
+        ..Default::default()
+    });
+    let parsed = processor.process(&batch).unwrap();
+    assert_eq!(names(&parsed), vec!["official_parsed", "official_parse_status", "clone_parsed", "clone_parse_status"]);
+
+    let missing = BatchProcessor::new(BatchOptions { text_columns: vec!["body".to_string()], ..Default::default() });
+    assert!(missing.process(&batch).is_err());
+}
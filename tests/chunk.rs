@@ -0,0 +1,47 @@
+//! Splitting parsed text into overlapping chunks and paragraph rows (`--explode-paragraphs`)
+
+use wikitext_parser::chunk::{chunk_text, paragraphs, ChunkOptions};
+
+#[test]
+fn chunks_hold_whole_words_within_the_size() {
+    let text = "один два три четыре пять шесть";
+    let chunks = chunk_text(text, ChunkOptions { size: 13, overlap: 0 });
+    assert_eq!(chunks, vec!["один два три", "четыре пять", "шесть"]);
+    // Sizes count characters, not bytes
+    assert!(chunks.iter().all(|chunk| chunk.chars().count() <= 13));
+    // The text between words, paragraph breaks included, is kept
+    assert_eq!(chunk_text("Один.\n\nДва.", ChunkOptions { size: 20, overlap: 0 }), vec!["Один.\n\nДва."]);
+    assert!(chunk_text(" \n ", ChunkOptions { size: 20, overlap: 0 }).is_empty());
+}
+
+#[test]
+fn chunks_overlap_by_the_trailing_words_that_fit() {
+    let text = "один два три четыре пять шесть";
+    // "три" fits in the 5-character overlap, "четыре" does not
+    let chunks = chunk_text(text, ChunkOptions { size: 13, overlap: 5 });
+    assert_eq!(chunks, vec!["один два три", "три четыре", "пять шесть"]);
+}
+
+#[test]
+fn words_longer_than_the_size_become_their_own_chunk() {
+    let chunks = chunk_text("а сверхдлинноеслово б", ChunkOptions { size: 5, overlap: 3 });
+    assert_eq!(chunks, vec!["а", "сверхдлинноеслово", "б"]);
+    // A paragraph longer than the size is split at its words
+    let chunks = chunk_text("Длинный абзац без переносов строк.", ChunkOptions { size: 14, overlap: 0 });
+    assert_eq!(chunks, vec!["Длинный абзац", "без переносов", "строк."]);
+}
+
+#[test]
+fn five_paragraphs_become_five_pieces() {
+    let text = "Первый.\n\nВторой.\n\nТретий\nс переносом.\n\n  \n\nЧетвёртый.\n\nПятый.";
+    assert_eq!(
+        paragraphs(text),
+        vec!["Первый.", "Второй.", "Третий\nс переносом.", "Четвёртый.", "Пятый."]
+    );
+}
+
+#[test]
+fn empty_text_has_no_paragraphs() {
+    assert!(paragraphs("").is_empty());
+    assert!(paragraphs("\n\n \n\n").is_empty());
+}
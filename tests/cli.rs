@@ -0,0 +1,26 @@
+//! Flags shared by both binaries
+
+use arrow::array::{Array, StringArray};
+use wikitext_parser::cli::ExtractArgs;
+
+#[test]
+fn extract_flags_build_one_column_per_requested_field() {
+    let extract = ExtractArgs {
+        extract_sortkey: true,
+        extract_display_title: false,
+        extract_categories: false,
+        extract_redirect: true,
+    };
+    assert_eq!(extract.suffixes(), vec!["sortkey", "redirect_target"]);
+
+    let inputs = [Some("Текст.\n\n{{DEFAULTSORT:Пушкин, Александр}}"), Some("#REDIRECT [[Ленин]]"), None];
+    let columns = extract.columns(&inputs);
+    let values = |index: usize| {
+        let column = columns[index].1.as_any().downcast_ref::<StringArray>().unwrap();
+        column.iter().map(|value| value.map(str::to_string)).collect::<Vec<_>>()
+    };
+    assert_eq!(columns[0].0, "sortkey");
+    assert_eq!(values(0), vec![Some("Пушкин, Александр".to_string()), None, None]);
+    assert_eq!(columns[1].0, "redirect_target");
+    assert_eq!(values(1), vec![None, Some("Ленин".to_string()), None]);
+}
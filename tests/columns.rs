@@ -0,0 +1,124 @@
+//! Column detection finds renamed input columns and honours explicit names
+
+use arrow::datatypes::{DataType, Field, Schema};
+use wikitext_parser::columns::{
+    detect_clone_text_column, detect_clone_title_column, detect_official_text_column, detect_pageid_column,
+    detect_parsed_text_columns, detect_text_column, detect_title_column, resolve_column,
+};
+
+fn schema(names: &[&str]) -> Schema {
+    Schema::new(names.iter().map(|name| Field::new(*name, DataType::Utf8, true)).collect::<Vec<_>>())
+}
+
+#[test]
+fn dual_text_columns_are_found_under_other_names() {
+    let schema = schema(&["pageid", "title", "official_wikitext", "clone_wikitext", "clone_title"]);
+    assert_eq!(detect_pageid_column(&schema).as_deref(), Some("pageid"));
+    assert_eq!(detect_title_column(&schema).as_deref(), Some("title"));
+    assert_eq!(detect_official_text_column(&schema).as_deref(), Some("official_wikitext"));
+    assert_eq!(detect_clone_text_column(&schema).as_deref(), Some("clone_wikitext"));
+    assert_eq!(detect_clone_title_column(&schema).as_deref(), Some("clone_title"));
+}
+
+#[test]
+fn exact_names_win_over_keyword_matches() {
+    let schema = schema(&["text_length", "content"]);
+    assert_eq!(detect_text_column(&schema).as_deref(), Some("content"));
+}
+
+#[test]
+fn explicit_columns_must_exist() {
+    let schema = schema(&["id", "body"]);
+    let resolved = resolve_column(&schema, Some("id"), "page ID", detect_pageid_column).unwrap();
+    assert_eq!(resolved.as_deref(), Some("id"));
+    assert!(resolve_column(&schema, Some("page_id"), "page ID", detect_pageid_column).is_err());
+    assert_eq!(resolve_column(&schema, None, "page ID", detect_pageid_column).unwrap(), None);
+}
+
+#[test]
+fn parsed_text_columns_of_either_binary() {
+    let dual = schema(&["page_id", "official_text_paragraphs", "official_parse_status", "clone_text_paragraphs"]);
+    assert_eq!(detect_parsed_text_columns(&dual), vec!["official_text_paragraphs", "clone_text_paragraphs"]);
+    let single = schema(&["pageid", "text_parsed", "parse_status"]);
+    assert_eq!(detect_parsed_text_columns(&single), vec!["text_parsed"]);
+}
+
+#[test]
+fn integer_ids_are_stringified() {
+    use arrow::array::{Array, ArrayRef, Float64Array, Int32Array, Int64Array};
+    use std::sync::Arc;
+    use wikitext_parser::columns::string_values;
+
+    let ids: ArrayRef = Arc::new(Int64Array::from(vec![Some(158785), None]));
+    let strings = string_values(&ids, "page_id").unwrap();
+    assert_eq!(strings.value(0), "158785");
+    assert!(strings.is_null(1));
+    let ids: ArrayRef = Arc::new(Int32Array::from(vec![7]));
+    assert_eq!(string_values(&ids, "pageid").unwrap().value(0), "7");
+    let floats: ArrayRef = Arc::new(Float64Array::from(vec![1.5]));
+    assert!(string_values(&floats, "page_id").is_err());
+}
+
+#[test]
+fn schema_check_reports_every_problem_at_once() {
+    use wikitext_parser::columns::{ColumnKind, SchemaCheck};
+
+    let schema = Schema::new(vec![
+        Field::new("page_id", DataType::Float64, true),
+        Field::new("text", DataType::Utf8, true),
+    ]);
+    let mut check = SchemaCheck::new(&schema);
+    let page_id = check.resolve(None, "page ID", detect_pageid_column);
+    check.check_type(page_id.as_deref(), "page ID", ColumnKind::Id);
+    check.check_type(Some("text"), "text", ColumnKind::Text);
+    let clone = check.resolve(Some("clone_text"), "clone text", detect_clone_text_column);
+    check.require(clone.as_deref(), "clone text", "--clone-text-column");
+    check.require(None, "timestamp", "--timestamp-column");
+
+    let message = check.finish().unwrap_err().to_string();
+    assert!(message.contains("page ID column 'page_id' has type Float64"));
+    assert_eq!(message.matches("clone text column").count(), 1);
+    assert!(message.contains("timestamp column could not be auto-detected (use --timestamp-column)"));
+    assert!(message.ends_with("Available columns: page_id (Float64), text (Utf8)"));
+
+    let mut check = SchemaCheck::new(&schema);
+    check.check_type(Some("text"), "text", ColumnKind::Text);
+    assert!(check.finish().is_ok());
+}
+
+#[test]
+fn schema_check_reports_every_missing_included_column() {
+    use wikitext_parser::columns::SchemaCheck;
+
+    let schema = Schema::new(vec![
+        Field::new("page_id", DataType::Int64, true),
+        Field::new("wikidata_id", DataType::Utf8, true),
+    ]);
+    let mut check = SchemaCheck::new(&schema);
+    for col in ["wikidata_id", "revision_id", "page_rank"] {
+        check.require_existing(col, "included");
+    }
+    let message = check.finish().unwrap_err().to_string();
+    assert!(message.contains("included column 'revision_id' not found"));
+    assert!(message.contains("included column 'page_rank' not found"));
+    assert!(!message.contains("'wikidata_id' not found"));
+}
+
+#[test]
+fn included_columns_must_not_reuse_output_names() {
+    use wikitext_parser::columns::SchemaCheck;
+
+    let schema = Schema::new(vec![
+        Field::new("options_hash", DataType::Utf8, true),
+        Field::new("wikidata_id", DataType::Utf8, true),
+    ]);
+    let outputs = vec!["page_id".to_string(), "options_hash".to_string()];
+    let mut check = SchemaCheck::new(&schema);
+    for col in ["options_hash", "wikidata_id"] {
+        check.require_existing(col, "included");
+        check.require_unique(col, "included", &outputs);
+    }
+    let message = check.finish().unwrap_err().to_string();
+    assert!(message.contains("included column 'options_hash' would duplicate an output column"));
+    assert!(!message.contains("'wikidata_id'"));
+}
@@ -0,0 +1,33 @@
+//! Per-language parser configurations
+
+use wikitext_parser::configuration::configuration;
+use wikitext_parser::parse_wiki_text::Node;
+use wikitext_parser::Language;
+
+#[test]
+fn russian_categories_and_files_are_namespaced_nodes() {
+    let output = configuration(Language::Ru).parse("Текст.\n\n[[Файл:Москва.jpg|мини|Кремль]]\n[[Категория:Города]]");
+    assert!(output.nodes.iter().any(|node| matches!(node, Node::Image { target, .. } if *target == "Файл:Москва.jpg")));
+    assert!(output.nodes.iter().any(|node| matches!(node, Node::Category { target, .. } if target == "Категория:Города")));
+    assert!(!output.nodes.iter().any(|node| matches!(node, Node::Link { .. })));
+}
+
+#[test]
+fn english_names_work_in_every_edition() {
+    for language in [Language::Ru, Language::En, Language::De] {
+        let output = configuration(language).parse("[[Category:Cities]]");
+        assert!(matches!(output.nodes.as_slice(), [Node::Category { .. }]), "{:?}", language);
+    }
+}
+
+#[test]
+fn russian_link_trail_joins_cyrillic_endings() {
+    let output = configuration(Language::Ru).parse("[[город]]а");
+    match output.nodes.as_slice() {
+        [Node::Link { target, end, .. }] => {
+            assert_eq!(*target, "город");
+            assert_eq!(*end, "[[город]]а".len());
+        }
+        nodes => panic!("unexpected nodes: {:?}", nodes),
+    }
+}
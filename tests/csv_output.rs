@@ -0,0 +1,38 @@
+//! CSV output quotes paragraph breaks and commas so rows survive a round trip
+
+use std::sync::Arc;
+
+use arrow::array::{ArrayRef, Int64Array, RecordBatch, StringArray};
+use wikitext_parser::csv_output::CsvWriter;
+
+#[test]
+fn paragraph_newlines_and_commas_round_trip() {
+    let text = "Москва, столица \"России\".\n\nВторой абзац.";
+    let batch = RecordBatch::try_from_iter(vec![
+        ("page_id", Arc::new(Int64Array::from(vec![1, 2])) as ArrayRef),
+        ("title", Arc::new(StringArray::from(vec![Some("Москва"), None])) as ArrayRef),
+        ("content_parsed", Arc::new(StringArray::from(vec![Some(text), None])) as ArrayRef),
+        ("timestamp", Arc::new(StringArray::from(vec!["2024-01-01", "2024-01-02"])) as ArrayRef),
+    ])
+    .unwrap();
+    let columns = vec![
+        ("page_id".to_string(), "page_id".to_string()),
+        ("title".to_string(), "title".to_string()),
+        ("content_parsed".to_string(), "content".to_string()),
+    ];
+
+    let path = std::env::temp_dir().join(format!("wikitext_parser_csv_{}.csv", std::process::id()));
+    let mut writer = CsvWriter::create(path.to_str().unwrap(), &columns).unwrap();
+    writer.write(&batch).unwrap();
+    writer.finish().unwrap();
+
+    let mut reader = csv::Reader::from_path(&path).unwrap();
+    assert_eq!(reader.headers().unwrap(), vec!["page_id", "title", "content"]);
+    let rows: Vec<csv::StringRecord> = reader.records().collect::<Result<_, _>>().unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(rows.len(), 2);
+    assert_eq!(rows[0], vec!["1", "Москва", text]);
+    // Null values are empty fields
+    assert_eq!(rows[1], vec!["2", "", ""]);
+}
@@ -0,0 +1,94 @@
+//! Binary text columns are decoded (raw, gzip or zstd) and wide string columns cast into Utf8 before parsing
+
+use arrow::array::{Array, ArrayRef, BinaryArray, Int64Array, LargeStringArray, RecordBatch, StringArray, StringViewArray};
+use arrow::datatypes::DataType;
+use std::io::Write;
+use std::sync::Arc;
+use wikitext_parser::decompress::{decode_text_columns, decode_value, Decompress, OnInvalid};
+
+const TEXT: &str = "'''Москва''' — столица России.";
+
+fn gzip(text: &str) -> Vec<u8> {
+    let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    encoder.write_all(text.as_bytes()).unwrap();
+    encoder.finish().unwrap()
+}
+
+fn zstd(text: &str) -> Vec<u8> {
+    zstd::stream::encode_all(text.as_bytes(), 0).unwrap()
+}
+
+#[test]
+fn auto_detects_gzip_zstd_and_raw_text() {
+    assert_eq!(decode_value(&gzip(TEXT), Decompress::Auto).unwrap(), TEXT);
+    assert_eq!(decode_value(&zstd(TEXT), Decompress::Auto).unwrap(), TEXT);
+    assert_eq!(decode_value(TEXT.as_bytes(), Decompress::Auto).unwrap(), TEXT);
+}
+
+#[test]
+fn forced_mode_rejects_other_encodings() {
+    assert_eq!(decode_value(&zstd(TEXT), Decompress::Zstd).unwrap(), TEXT);
+    assert!(decode_value(&zstd(TEXT), Decompress::Gzip).is_err());
+    assert!(decode_value(&gzip(TEXT), Decompress::None).is_err());
+}
+
+#[test]
+fn binary_column_becomes_utf8_with_bad_rows_null() {
+    let gzipped = gzip(TEXT);
+    let zstded = zstd(TEXT);
+    let texts = BinaryArray::from(vec![
+        Some(gzipped.as_slice()),
+        Some(zstded.as_slice()),
+        Some(&[0xff, 0xfe][..]),
+        None,
+    ]);
+    let ids: ArrayRef = Arc::new(Int64Array::from(vec![1, 2, 3, 4]));
+    let batch = RecordBatch::try_from_iter(vec![("page_id", ids), ("text", Arc::new(texts) as ArrayRef)]).unwrap();
+
+    let decoded = decode_text_columns(&batch, &["text"], Decompress::Auto, OnInvalid::Skip).unwrap();
+    assert_eq!(decoded.schema().field_with_name("text").unwrap().data_type(), &DataType::Utf8);
+    assert_eq!(decoded.schema().field_with_name("page_id").unwrap().data_type(), &DataType::Int64);
+
+    let text = decoded.column_by_name("text").unwrap();
+    let text = text.as_any().downcast_ref::<StringArray>().unwrap();
+    assert_eq!(text.iter().collect::<Vec<_>>(), vec![Some(TEXT), Some(TEXT), None, None]);
+}
+
+#[test]
+fn string_columns_are_left_alone() {
+    let texts: ArrayRef = Arc::new(StringArray::from(vec![TEXT]));
+    let batch = RecordBatch::try_from_iter(vec![("text", texts.clone())]).unwrap();
+    let decoded = decode_text_columns(&batch, &["text"], Decompress::Gzip, OnInvalid::Error).unwrap();
+    assert_eq!(decoded.column(0).as_ref(), texts.as_ref());
+}
+
+#[test]
+fn invalid_utf8_is_replaced_or_rejected_per_policy() {
+    // Mojibake byte and an encoded lone surrogate (U+D800) around valid text
+    let bytes = [b"ok \xff ".as_slice(), "\u{439}".as_bytes(), b" \xed\xa0\x80"].concat();
+    let texts: ArrayRef = Arc::new(BinaryArray::from(vec![Some(bytes.as_slice()), Some(TEXT.as_bytes())]));
+    let batch = RecordBatch::try_from_iter(vec![("text", texts)]).unwrap();
+
+    let replaced = decode_text_columns(&batch, &["text"], Decompress::None, OnInvalid::Replace).unwrap();
+    let text = replaced.column(0).as_any().downcast_ref::<StringArray>().unwrap();
+    assert_eq!(text.value(0), "ok \u{fffd} й \u{fffd}\u{fffd}\u{fffd}");
+    assert_eq!(text.value(1), TEXT);
+
+    let error = decode_text_columns(&batch, &["text"], Decompress::None, OnInvalid::Error).unwrap_err();
+    assert!(error.to_string().contains("text row 1"), "{}", error);
+}
+
+#[test]
+fn large_and_view_string_columns_become_utf8() {
+    let large: ArrayRef = Arc::new(LargeStringArray::from(vec![Some(TEXT), None]));
+    let view: ArrayRef = Arc::new(StringViewArray::from(vec![Some(TEXT), None]));
+    let batch = RecordBatch::try_from_iter(vec![("text", large), ("content", view)]).unwrap();
+
+    let decoded = decode_text_columns(&batch, &["text", "content"], Decompress::Auto, OnInvalid::Skip).unwrap();
+    for column in ["text", "content"] {
+        assert_eq!(decoded.schema().field_with_name(column).unwrap().data_type(), &DataType::Utf8);
+        let texts = decoded.column_by_name(column).unwrap();
+        let texts = texts.as_any().downcast_ref::<StringArray>().unwrap();
+        assert_eq!(texts.iter().collect::<Vec<_>>(), vec![Some(TEXT), None]);
+    }
+}
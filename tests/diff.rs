@@ -0,0 +1,31 @@
+//! Unified line diffs of parsed texts
+
+use wikitext_parser::diff::unified_diff;
+
+#[test]
+fn equal_texts_have_no_diff() {
+    assert_eq!(unified_diff("Один.\n\nДва.", "Один.\n\nДва.", 3), "");
+}
+
+#[test]
+fn changes_are_grouped_into_hunks_with_context() {
+    let old = "a\nb\nc\nd\ne\nf\ng\nh\ni\nj";
+    let new = "a\nB\nc\nd\ne\nf\ng\nh\ni\nj\nk";
+    assert_eq!(
+        unified_diff(old, new, 1),
+        "@@ -1,3 +1,3 @@\n a\n-b\n+B\n c\n@@ -10 +10,2 @@\n j\n+k\n"
+    );
+    // Changes within two contexts of each other share a hunk
+    assert_eq!(
+        unified_diff("a\nb\nc\nd", "a\nB\nc\nD", 1),
+        "@@ -1,4 +1,4 @@\n a\n-b\n+B\n c\n-d\n+D\n"
+    );
+}
+
+#[test]
+fn added_and_removed_lines_are_aligned_around_common_ones() {
+    assert_eq!(
+        unified_diff("Первый.\nВторой.\nТретий.", "Нулевой.\nПервый.\nТретий.", 0),
+        "@@ -0,0 +1 @@\n+Нулевой.\n@@ -2 +2,0 @@\n-Второй.\n"
+    );
+}
@@ -0,0 +1,20 @@
+//! List rendering on the HTML input path
+
+use wikitext_parser::html::parse_html;
+use wikitext_parser::{ListStyle, ParseOptions};
+
+const HTML: &str = "<p>Вступление:</p><ul><li>a<ol><li>b</li><li>c</li></ol></li><li>d</li></ul><p>После.</p>";
+
+fn parse(style: ListStyle) -> String {
+    parse_html(HTML, &ParseOptions { list_style: style, ..Default::default() })
+}
+
+#[test]
+fn markdown_lists_keep_items_and_nesting() {
+    assert_eq!(parse(ListStyle::Markdown), "Вступление:\n\n- a\n  1. b\n  2. c\n- d\n\nПосле.");
+}
+
+#[test]
+fn skipped_lists_leave_surrounding_paragraphs() {
+    assert_eq!(parse(ListStyle::Skip), "Вступление:\n\nПосле.");
+}
@@ -0,0 +1,57 @@
+//! `--since`: only rows modified after a timestamp are reparsed
+
+use std::sync::Arc;
+
+use arrow::array::{Array, ArrayRef, BooleanArray, Int64Array, RecordBatch, StringArray};
+use wikitext_parser::incremental::{inputs_to_parse, parse_since, pass_through_unchanged, select_changed, UnchangedAction};
+
+/// Page 1 predates --since, page 2 follows it, page 3 has no timestamp (counts as changed)
+fn batch() -> RecordBatch {
+    RecordBatch::try_from_iter(vec![
+        ("page_id", Arc::new(Int64Array::from(vec![1, 2, 3])) as ArrayRef),
+        (
+            "timestamp",
+            Arc::new(StringArray::from(vec![Some("2024-01-01T00:00:00Z"), Some("2024-06-01T12:00:00+02:00"), None])) as ArrayRef,
+        ),
+        ("text", Arc::new(StringArray::from(vec!["'''Старая'''", "'''Новая'''", "'''Без даты'''"])) as ArrayRef),
+    ])
+    .unwrap()
+}
+
+fn page_ids(batch: &RecordBatch) -> Vec<i64> {
+    let column = batch.column_by_name("page_id").unwrap().as_any().downcast_ref::<Int64Array>().unwrap();
+    column.values().to_vec()
+}
+
+#[test]
+fn omit_drops_rows_older_than_since() {
+    let since = parse_since("2024-05-01T00:00:00Z").unwrap();
+    let (selected, changed) = select_changed(&batch(), "timestamp", since, UnchangedAction::Omit).unwrap();
+    assert_eq!(page_ids(&selected), vec![2, 3]);
+    assert!(changed.is_none());
+}
+
+#[test]
+fn passthrough_keeps_unchanged_rows_with_their_source_text() {
+    let since = parse_since("2024-05-01T00:00:00Z").unwrap();
+    let (selected, changed) = select_changed(&batch(), "timestamp", since, UnchangedAction::Passthrough).unwrap();
+    assert_eq!(page_ids(&selected), vec![1, 2, 3]);
+    let changed = changed.unwrap();
+    assert_eq!(changed, BooleanArray::from(vec![false, true, true]));
+
+    // Unchanged rows are not sent to the parser and get their source text back
+    let inputs = vec![Some("'''Старая'''"), Some("'''Новая'''"), Some("'''Без даты'''")];
+    assert_eq!(inputs_to_parse(&inputs, Some(&changed)), vec![None, Some("'''Новая'''"), Some("'''Без даты'''")]);
+    let mut results = vec![None, Some(Ok("Новая".to_string())), Some(Ok("Без даты".to_string()))];
+    pass_through_unchanged(&mut results, &inputs, Some(&changed));
+    assert_eq!(
+        results,
+        vec![Some(Ok("'''Старая'''".to_string())), Some(Ok("Новая".to_string())), Some(Ok("Без даты".to_string()))]
+    );
+}
+
+#[test]
+fn missing_timestamp_column_is_an_error() {
+    assert!(select_changed(&batch(), "updated", 0, UnchangedAction::Omit).is_err());
+    assert!(parse_since("вчера").is_err());
+}
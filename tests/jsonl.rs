@@ -0,0 +1,96 @@
+//! JSON Lines output writes one valid object per row with explicit nulls
+
+use std::io::{BufRead, BufReader};
+use std::sync::Arc;
+
+use arrow::array::{Array, ArrayRef, RecordBatch, StringArray};
+use arrow::datatypes::{DataType, Field, Schema};
+use arrow::json::ReaderBuilder;
+use wikitext_parser::jsonl::JsonlWriter;
+
+#[test]
+fn writes_one_object_per_row_keyed_by_input_columns() {
+    let batch = RecordBatch::try_from_iter(vec![
+        ("pageid", Arc::new(StringArray::from(vec!["1", "2"])) as ArrayRef),
+        ("title", Arc::new(StringArray::from(vec!["Москва", "Пустая"])) as ArrayRef),
+        ("content_parsed", Arc::new(StringArray::from(vec![Some("Столица \"России\".\n\nВторой абзац."), None])) as ArrayRef),
+        ("timestamp", Arc::new(StringArray::from(vec!["2024-01-01", "2024-01-02"])) as ArrayRef),
+    ])
+    .unwrap();
+    let columns = vec![
+        ("pageid".to_string(), "pageid".to_string()),
+        ("title".to_string(), "title".to_string()),
+        ("content_parsed".to_string(), "content".to_string()),
+    ];
+
+    let path = std::env::temp_dir().join(format!("wikitext_parser_jsonl_{}.jsonl", std::process::id()));
+    let path_str = path.to_str().unwrap();
+    let mut writer = JsonlWriter::create(path_str, &columns).unwrap();
+    writer.write(&batch).unwrap();
+    writer.finish().unwrap();
+
+    let lines: Vec<String> = BufReader::new(std::fs::File::open(&path).unwrap())
+        .lines()
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(lines.len(), 2);
+    assert!(lines[1].contains("\"content\":null"));
+    assert!(!lines[0].contains("timestamp"));
+
+    // Every line must parse back as JSON with the expected keys and values
+    let schema = Arc::new(Schema::new(vec![
+        Field::new("pageid", DataType::Utf8, true),
+        Field::new("title", DataType::Utf8, true),
+        Field::new("content", DataType::Utf8, true),
+    ]));
+    let mut reader = ReaderBuilder::new(schema)
+        .with_strict_mode(true)
+        .build(BufReader::new(std::fs::File::open(&path).unwrap()))
+        .unwrap();
+    let read = reader.next().unwrap().unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    let content = read.column_by_name("content").unwrap().as_any().downcast_ref::<StringArray>().unwrap();
+    assert_eq!(content.value(0), "Столица \"России\".\n\nВторой абзац.");
+    assert!(content.is_null(1));
+}
+
+#[test]
+fn sections_json_nests_paragraphs_under_their_headings() {
+    let text = "Лид статьи.\n\n## История\n\nПервый абзац.\n\nВторой абзац.\n\n### Древность\n\nТретий абзац.";
+    let batch = RecordBatch::try_from_iter(vec![
+        ("pageid", Arc::new(StringArray::from(vec!["1", "2"])) as ArrayRef),
+        ("content_parsed", Arc::new(StringArray::from(vec![Some(text), None])) as ArrayRef),
+    ])
+    .unwrap();
+    let columns = vec![
+        ("pageid".to_string(), "pageid".to_string()),
+        ("content_parsed".to_string(), "content".to_string()),
+    ];
+
+    let path = std::env::temp_dir().join(format!("wikitext_parser_sections_{}.jsonl", std::process::id()));
+    let path_str = path.to_str().unwrap();
+    let mut writer = JsonlWriter::create(path_str, &columns)
+        .unwrap()
+        .with_section_columns(&["content_parsed".to_string()]);
+    writer.write(&batch).unwrap();
+    writer.finish().unwrap();
+    let lines: Vec<String> = BufReader::new(std::fs::File::open(&path).unwrap())
+        .lines()
+        .collect::<Result<_, _>>()
+        .unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(
+        lines,
+        vec![
+            concat!(
+                r#"{"pageid":"1","content":["#,
+                r#"{"heading":null,"level":null,"paragraphs":["Лид статьи."]},"#,
+                r#"{"heading":"История","level":2,"paragraphs":["Первый абзац.","Второй абзац."]},"#,
+                r#"{"heading":"Древность","level":3,"paragraphs":["Третий абзац."]}]}"#
+            ),
+            r#"{"pageid":"2","content":null}"#,
+        ]
+    );
+}
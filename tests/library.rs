@@ -0,0 +1,48 @@
+//! The parser is usable as a library straight from the crate root
+
+use wikitext_parser::{
+    parse_wikitext, parser_warnings, try_parse_wikitext, try_parse_wikitext_with_warnings, Language, ListStyle,
+    ParseOptions, SkipReason,
+};
+
+#[test]
+fn parses_paragraphs_and_link_text() {
+    let wikitext = "Москва — столица [[Россия|России]].\n\nВторой абзац.";
+    let text = parse_wikitext(wikitext, &ParseOptions::default());
+    assert_eq!(text, "Москва — столица России.\n\nВторой абзац.");
+}
+
+#[test]
+fn heading_is_its_own_paragraph() {
+    assert_eq!(parse_wikitext("== Раздел ==\nТекст раздела.", &ParseOptions::default()), "Раздел\n\nТекст раздела.");
+    assert_eq!(
+        parse_wikitext("Вступление.\n== Раздел ==\nТекст.", &ParseOptions::default()),
+        "Вступление.\n\nРаздел\n\nТекст."
+    );
+}
+
+#[test]
+fn skip_lists_drops_list_items() {
+    let wikitext = "Вступление.\n\n* первый пункт\n* второй пункт\n";
+    let options = ParseOptions { list_style: ListStyle::Skip, ..Default::default() };
+    assert_eq!(parse_wikitext(wikitext, &options), "Вступление.");
+}
+
+#[test]
+fn oversized_article_is_skipped_with_a_reason() {
+    let wikitext = "Первый абзац статьи.\n\nВторой абзац статьи.";
+    let options = ParseOptions { max_output_bytes: 10, ..Default::default() };
+    assert_eq!(try_parse_wikitext(wikitext, &options), Err(SkipReason::TooLarge));
+    assert_eq!(parse_wikitext(wikitext, &options), "");
+}
+
+#[test]
+fn parser_warnings_are_counted_by_kind() {
+    assert!(parser_warnings("Обычный абзац.", Language::Ru).is_empty());
+    let warnings = parser_warnings("Текст <blah>тег</blah>.", Language::Ru);
+    assert!(warnings.iter().any(|(kind, count)| kind == "UnrecognizedTagName" && *count > 0), "{:?}", warnings);
+    // The same counts come back from the parse itself, without parsing twice
+    let (result, counted) = try_parse_wikitext_with_warnings("Текст <blah>тег</blah>.", &ParseOptions::default());
+    assert!(result.is_ok());
+    assert_eq!(counted, warnings);
+}
@@ -0,0 +1,71 @@
+//! --compression/--compression-level map to parquet codecs and output shrinks with zstd
+
+use arrow::array::{ArrayRef, RecordBatch, StringArray};
+use parquet::basic::{Compression, ZstdLevel};
+use std::sync::Arc;
+use wikitext_parser::jsonl::OutputFormat;
+use wikitext_parser::output::{BatchWriter, OutputCompression};
+
+#[test]
+fn codecs_and_levels() {
+    assert_eq!(OutputCompression::None.codec(None).unwrap(), Compression::UNCOMPRESSED);
+    assert_eq!(OutputCompression::Lz4.codec(None).unwrap(), Compression::LZ4_RAW);
+    assert_eq!(OutputCompression::Zstd.codec(Some(9)).unwrap(), Compression::ZSTD(ZstdLevel::try_new(9).unwrap()));
+    assert!(OutputCompression::Zstd.codec(Some(30)).is_err());
+    assert!(OutputCompression::Gzip.codec(Some(-1)).is_err());
+    assert!(OutputCompression::Snappy.codec(Some(1)).is_err());
+}
+
+fn write(path: &std::path::Path, compression: OutputCompression) -> u64 {
+    let texts: ArrayRef = Arc::new(StringArray::from(vec!["Москва — столица России. ".repeat(200); 50]));
+    let batch = RecordBatch::try_from_iter(vec![("text_parsed", texts)]).unwrap();
+    let path = path.to_str().unwrap();
+    let mut writer = BatchWriter::create(
+        path,
+        OutputFormat::Parquet,
+        &batch.schema(),
+        None,
+        false,
+        &[],
+        compression.codec(None).unwrap(),
+    )
+    .unwrap();
+    writer.write(&batch).unwrap();
+    writer.finish().unwrap();
+    std::fs::metadata(path).unwrap().len()
+}
+
+#[test]
+fn zstd_output_is_smaller() {
+    let dir = std::env::temp_dir();
+    let plain = dir.join(format!("wikitext_output_plain_{}.parquet", std::process::id()));
+    let zstd = dir.join(format!("wikitext_output_zstd_{}.parquet", std::process::id()));
+
+    let plain_size = write(&plain, OutputCompression::None);
+    let zstd_size = write(&zstd, OutputCompression::Zstd);
+    std::fs::remove_file(&plain).unwrap();
+    std::fs::remove_file(&zstd).unwrap();
+
+    assert!(zstd_size < plain_size, "zstd {} >= uncompressed {}", zstd_size, plain_size);
+}
+
+#[test]
+fn section_columns_only_apply_to_sections_json() {
+    let texts: ArrayRef = Arc::new(StringArray::from(vec!["Лид.\n\n## Раздел\n\nТекст."]));
+    let batch = RecordBatch::try_from_iter(vec![("text_parsed", texts)]).unwrap();
+    let columns = vec![("text_parsed".to_string(), "text".to_string())];
+    let written = |format: OutputFormat| {
+        let path = std::env::temp_dir().join(format!("wikitext_output_{:?}_{}.jsonl", format, std::process::id()));
+        let path = path.to_str().unwrap();
+        let mut writer = BatchWriter::create(path, format, &batch.schema(), None, false, &columns, Compression::UNCOMPRESSED)
+            .unwrap()
+            .with_section_columns(&["text_parsed".to_string()]);
+        writer.write(&batch).unwrap();
+        writer.finish().unwrap();
+        let content = std::fs::read_to_string(path).unwrap();
+        std::fs::remove_file(path).unwrap();
+        content
+    };
+    assert_eq!(written(OutputFormat::Jsonl), "{\"text\":\"Лид.\\n\\n## Раздел\\n\\nТекст.\"}\n");
+    assert!(written(OutputFormat::SectionsJson).starts_with("{\"text\":[{\"heading\":null,"));
+}
@@ -0,0 +1,37 @@
+//! --page-ids keeps only the listed pages, whatever the ID column type
+
+use std::sync::Arc;
+
+use arrow::array::{Array, ArrayRef, Int64Array, RecordBatch, StringArray};
+use wikitext_parser::page_filter::PageIdFilter;
+
+fn batch(ids: ArrayRef) -> RecordBatch {
+    let texts = Arc::new(StringArray::from(vec!["a", "b", "c"]));
+    RecordBatch::try_from_iter(vec![("page_id", ids), ("text", texts as ArrayRef)]).unwrap()
+}
+
+fn texts(batch: &RecordBatch) -> Vec<String> {
+    let texts = batch.column_by_name("text").unwrap().as_any().downcast_ref::<StringArray>().unwrap();
+    texts.iter().map(|text| text.unwrap().to_string()).collect()
+}
+
+#[test]
+fn integer_and_string_ids_match_the_same_filter() {
+    let filter = PageIdFilter::new(["42", " 7 "]);
+    let integers = batch(Arc::new(Int64Array::from(vec![Some(7), None, Some(42)])));
+    assert_eq!(texts(&filter.select(&integers, "page_id").unwrap()), vec!["a", "c"]);
+    let strings = batch(Arc::new(StringArray::from(vec![Some("42"), Some("43"), None])));
+    assert_eq!(texts(&filter.select(&strings, "page_id").unwrap()), vec!["a"]);
+}
+
+#[test]
+fn ids_file_is_combined_with_the_list() {
+    let path = std::env::temp_dir().join("wikitext_parser_page_ids.txt");
+    std::fs::write(&path, "1\n\n2\n").unwrap();
+    let list = vec!["3".to_string()];
+    let filter = PageIdFilter::load(Some(&list), path.to_str()).unwrap().unwrap();
+    assert_eq!(filter.len(), 3);
+
+    assert!(PageIdFilter::load(None, None).unwrap().is_none());
+    assert!(PageIdFilter::load(Some(&[String::new()]), None).is_err());
+}
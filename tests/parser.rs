@@ -0,0 +1,855 @@
+//! Regression tests for wikitext extraction
+
+use std::collections::HashSet;
+
+use wikitext_parser::parse_wiki_text::{
+    DefinitionListItem, DefinitionListItemType, ListItem, Node, Parameter, TableCell, TableCellType, TableRow,
+};
+use wikitext_parser::{
+    decode_entities, expand_common_templates, extract_categories, extract_display_title, extract_redirect_target,
+    extract_sortkey, extract_text_from_nodes, postprocess_text, remove_dropped_templates, remove_leaked_templates,
+};
+use wikitext_parser::{
+    DashStyle, HeadingStyle, Language, LinkStyle, ListStyle, ParagraphDedup, ParseOptions, RefStyle, TableStyle,
+    UnicodeForm, DEFAULT_DROP_LINK_NAMESPACES, DEFAULT_DROP_TEMPLATES, DEFAULT_EXPAND_TEMPLATES,
+};
+
+#[test]
+fn emphasis_markers_are_never_sliced_from_the_wikitext() {
+    // The markers contribute nothing, so offsets inside "Ж" (two bytes long)
+    // or past the end of the wikitext cannot panic or leak quotes
+    let wikitext = "Жук '''жёлтый'''";
+    let nodes = [
+        Node::Text { end: 5, start: 0, value: "Жук " },
+        Node::Bold { end: 3, start: 1 },
+        Node::Text { end: 20, start: 8, value: "жёлтый" },
+        Node::BoldItalic { end: 200, start: 100 },
+        Node::Italic { end: 2, start: 1 },
+    ];
+    let text = extract_text_from_nodes(&nodes, wikitext, &ParseOptions::default());
+    assert_eq!(text.as_deref(), Some("Жук жёлтый"));
+}
+
+/// Wikitext built from `parts` plus its inline nodes: runs of two, three or
+/// five apostrophes become Italic/Bold/BoldItalic nodes covering just the
+/// markup (as parse_wiki_text emits them), everything else a Text node
+fn inline(parts: &[&'static str]) -> (String, Vec<Node<'static>>) {
+    let mut wikitext = String::new();
+    let mut nodes = Vec::new();
+    for &part in parts {
+        let start = wikitext.len();
+        wikitext.push_str(part);
+        let end = wikitext.len();
+        nodes.push(match part {
+            "''" => Node::Italic { end, start },
+            "'''" => Node::Bold { end, start },
+            "'''''" => Node::BoldItalic { end, start },
+            _ => Node::Text { end, start, value: part },
+        });
+    }
+    (wikitext, nodes)
+}
+
+fn extract_inline(parts: &[&'static str]) -> String {
+    let (wikitext, nodes) = inline(parts);
+    extract_text_from_nodes(&nodes, &wikitext, &ParseOptions::default()).unwrap()
+}
+
+#[test]
+fn bold_and_italic_markers_leave_only_their_text() {
+    assert_eq!(extract_inline(&["'''", "Москва", "'''", " — ", "''", "столица", "''", " и ", "'''''", "город", "'''''", "."]), "Москва — столица и город.");
+}
+
+#[test]
+fn apostrophes_inside_bold_text_are_kept() {
+    assert_eq!(extract_inline(&["'''", "O'Brien", "'''", " и ", "'''", "д'Артаньян'", "'''"]), "O'Brien и д'Артаньян'");
+}
+
+#[test]
+fn italic_nested_in_bold_keeps_both_texts() {
+    assert_eq!(extract_inline(&["'''", "bold ", "''", "italic", "''", " ", "'''", "text"]), "bold italic text");
+}
+
+#[test]
+fn empty_sections_use_the_configured_names() {
+    let options = ParseOptions {
+        section_names: vec!["See also".to_string(), "References".to_string()],
+        category_prefixes: vec!["Category:".to_string()],
+        ..Default::default()
+    };
+    let text = "Intro.\n\nReferences\n\nSmith 2001.\n\nSee also\n\nCategory:Cities";
+    assert_eq!(
+        postprocess_text(text, &HashSet::new(), &options),
+        "Intro.\n\nReferences\n\nSmith 2001."
+    );
+    // Without names nothing is structural
+    let text = "Intro.\n\nSee also";
+    assert_eq!(postprocess_text(text, &HashSet::new(), &ParseOptions::default()), text);
+}
+
+#[test]
+fn empty_section_lookahead_skips_headings_before_content() {
+    let options = ParseOptions {
+        section_names: vec!["Section A".to_string(), "Section B".to_string()],
+        ..Default::default()
+    };
+    let text = "Section A\n\nSection B\n\nText";
+    assert_eq!(postprocess_text(text, &HashSet::new(), &options), text);
+    // Headings with nothing after them are dropped, however many there are
+    assert_eq!(postprocess_text("Text\n\nSection A\n\nSection B", &HashSet::new(), &options), "Text");
+    assert_eq!(postprocess_text("Section A", &HashSet::new(), &options), "");
+}
+
+#[test]
+fn russian_date_and_number_templates() {
+    let text = "Родился {{СС3|18.1.1918}}, в {{год|1918}} году, тираж {{num|5000}}.";
+    assert_eq!(
+        expand_common_templates(text, Language::Ru),
+        "Родился 18 января 1918, в 1918 году, тираж 5000."
+    );
+    assert_eq!(expand_common_templates("{{СС3|1.13.1918}}", Language::Ru), "1.13.1918");
+}
+
+#[test]
+fn english_date_templates() {
+    assert_eq!(expand_common_templates("{{Birth date|1918|1|18}}", Language::En), "18 January 1918");
+    assert_eq!(
+        expand_common_templates("{{birth date and age|1918|01|18|df=y}}", Language::En),
+        "18 January 1918"
+    );
+    assert_eq!(
+        expand_common_templates("{{Death date and age|1990|5|3|1918|1|18}}", Language::En),
+        "3 May 1990"
+    );
+    assert_eq!(expand_common_templates("{{Start date|1918|1}}", Language::En), "January 1918");
+    assert_eq!(expand_common_templates("{{End date|1918}}", Language::En), "1918");
+    assert_eq!(expand_common_templates("{{nowrap|10 km}}", Language::En), "10 km");
+}
+
+#[test]
+fn leaked_convert_templates_keep_the_measurement() {
+    assert_eq!(expand_common_templates("Трасса {{convert|10|km|mi}}.", Language::Ru), "Трасса 10 km.");
+    assert_eq!(expand_common_templates("{{Convert|5|-|10|km|mi|abbr=on}}", Language::En), "5–10 km");
+    assert_eq!(expand_common_templates("{{конвертация|3,5|км|миль}}", Language::Ru), "3,5 км");
+    let expand: Vec<String> = vec!["convert".into(), "конвертация".into()];
+    assert_eq!(
+        remove_leaked_templates("Длина {{конвертация|120|м|ft}}{{nav}}, высота {{convert|x}}.", &expand, Language::Ru),
+        "Длина 120 м, высота ."
+    );
+}
+
+#[test]
+fn month_names_follow_the_language() {
+    assert_eq!(expand_common_templates("{{Birth date|1918|3|18}}", Language::De), "18. März 1918");
+    assert_eq!(expand_common_templates("{{Start date|1918|3}}", Language::Ru), "март 1918");
+    assert_eq!(expand_common_templates("{{СС3|18.3.1918}}", Language::En), "18 March 1918");
+}
+
+fn text(value: &str) -> Node<'_> {
+    Node::Text { end: 0, start: 0, value }
+}
+
+fn item(nodes: Vec<Node<'_>>) -> ListItem<'_> {
+    ListItem { end: 0, nodes, start: 0 }
+}
+
+/// "Вступление:" followed by
+/// ```text
+/// * a
+/// *# b
+/// *# c
+/// * d
+/// ```
+fn nested_list() -> Vec<Node<'static>> {
+    vec![
+        text("Вступление:"),
+        Node::UnorderedList {
+            end: 0,
+            items: vec![
+                item(vec![
+                    text("a"),
+                    Node::OrderedList { end: 0, items: vec![item(vec![text("b")]), item(vec![text("c")])], start: 0 },
+                ]),
+                item(vec![text(" d ")]),
+            ],
+            start: 0,
+        },
+        text("После."),
+    ]
+}
+
+fn extract_lists(style: ListStyle) -> String {
+    let options = ParseOptions { list_style: style, ..Default::default() };
+    extract_text_from_nodes(&nested_list(), "", &options).unwrap()
+}
+
+#[test]
+fn flattened_lists_join_the_surrounding_paragraph() {
+    // Item text is joined as is: only whitespace inside the items separates them
+    assert_eq!(extract_lists(ListStyle::Flatten), "Вступление:ab c d После.");
+}
+
+#[test]
+fn markdown_lists_keep_items_and_nesting() {
+    assert_eq!(extract_lists(ListStyle::Markdown), "Вступление:\n\n- a\n  1. b\n  2. c\n- d\n\nПосле.");
+}
+
+#[test]
+fn skip_list_style_removes_nested_lists() {
+    assert_eq!(extract_lists(ListStyle::Skip), "Вступление:После.");
+}
+
+fn dashes(text: &str, style: DashStyle) -> String {
+    let options = ParseOptions { normalize_dashes: Some(style), ..Default::default() };
+    postprocess_text(text, &HashSet::new(), &options)
+}
+
+#[test]
+fn standalone_dashes_follow_the_russian_spaced_em_dash() {
+    let text = "Москва - столица России, война 1941–1945 годов – северо-запад − юг";
+    assert_eq!(dashes(text, DashStyle::Em), "Москва — столица России, война 1941–1945 годов — северо-запад — юг");
+    assert_eq!(dashes("Иван — имя", DashStyle::Hyphen), "Иван - имя");
+}
+
+#[test]
+fn normalize_dashes_keeps_markdown_list_markers() {
+    let text = extract_lists(ListStyle::Markdown);
+    assert_eq!(dashes(&text, DashStyle::Em), text);
+    assert_eq!(dashes("- Москва - столица\n  - Тверь - город", DashStyle::Em), "- Москва — столица\n  - Тверь — город");
+}
+
+fn definition_item(type_: DefinitionListItemType, nodes: Vec<Node<'static>>) -> DefinitionListItem<'static> {
+    DefinitionListItem { end: 0, nodes, start: 0, type_ }
+}
+
+/// A glossary with one term per edge case, then a separate definition-only list:
+/// ```text
+/// ; Альфа : первая буква
+/// ; Бета
+/// : вторая буква
+/// : число 2
+/// ; Гамма
+/// : без термина
+/// Конец.
+/// ```
+fn glossary() -> Vec<Node<'static>> {
+    use DefinitionListItemType::{Details, Term};
+    vec![
+        text("Глоссарий."),
+        Node::DefinitionList {
+            end: 0,
+            items: vec![
+                definition_item(Term, vec![text("Альфа")]),
+                definition_item(Details, vec![text(" первая буква")]),
+                definition_item(Term, vec![text("Бета")]),
+                definition_item(Details, vec![text("вторая буква")]),
+                definition_item(Details, vec![text("число 2")]),
+                definition_item(Term, vec![text("Гамма")]),
+            ],
+            start: 0,
+        },
+        Node::DefinitionList { end: 0, items: vec![definition_item(Details, vec![text("без термина")])], start: 0 },
+        text("Конец."),
+    ]
+}
+
+fn extract_glossary(style: ListStyle) -> String {
+    let options = ParseOptions { list_style: style, ..Default::default() };
+    extract_text_from_nodes(&glossary(), "", &options).unwrap()
+}
+
+#[test]
+fn definition_lists_render_terms_with_their_definitions() {
+    assert_eq!(
+        extract_glossary(ListStyle::Flatten),
+        "Глоссарий.\n\nАльфа: первая буква\nБета: вторая буква; число 2\nГамма\n\nбез термина\n\nКонец."
+    );
+    assert_eq!(
+        extract_glossary(ListStyle::Markdown),
+        "Глоссарий.\n\n- Альфа: первая буква\n- Бета: вторая буква; число 2\n- Гамма\n\n- без термина\n\nКонец."
+    );
+    assert_eq!(extract_glossary(ListStyle::Skip), "Глоссарий.Конец.");
+}
+
+fn headings(style: HeadingStyle) -> String {
+    let nodes: Vec<Node> = (2..=6)
+        .map(|level| Node::Heading { end: 0, level, nodes: vec![text("Раздел")], start: 0 })
+        .collect();
+    let options = ParseOptions { heading_style: style, ..Default::default() };
+    extract_text_from_nodes(&nodes, "", &options).unwrap()
+}
+
+#[test]
+fn markdown_headings_are_prefixed_by_level() {
+    assert_eq!(
+        headings(HeadingStyle::Markdown),
+        "## Раздел\n\n### Раздел\n\n#### Раздел\n\n##### Раздел\n\n###### Раздел\n\n"
+    );
+    assert_eq!(headings(HeadingStyle::Plain), "Раздел\n\n".repeat(5));
+}
+
+#[test]
+fn headings_are_separate_paragraphs_whatever_surrounds_them() {
+    let heading = |title| Node::Heading { end: 0, level: 2, nodes: vec![text(title)], start: 0 };
+    let nodes = vec![
+        text("Вступление \n"),
+        heading("Раздел"),
+        text("\nТекст раздела."),
+        heading(" Второй "),
+        text(" \n"),
+        heading("Третий"),
+        text("Сразу текст"),
+    ];
+    assert_eq!(
+        extract_text_from_nodes(&nodes, "", &ParseOptions::default()).unwrap(),
+        "Вступление\n\nРаздел\n\nТекст раздела.\n\nВторой\n\nТретий\n\nСразу текст"
+    );
+}
+
+#[test]
+fn repeated_headings_collapse_only_when_consecutive() {
+    let options = ParseOptions { collapse_repeated_headings: true, ..Default::default() };
+    let headings: HashSet<String> = ["История".to_string()].into();
+    // The heading node and a leaked plain copy of it (Markdown and plain count as identical)
+    let text = "Лид.\n\n## История\n\nИстория\n\nТекст.";
+    assert_eq!(postprocess_text(text, &headings, &options), "Лид.\n\n## История\n\nТекст.");
+    let text = "История\n\nИстория\n\nТекст.";
+    assert_eq!(postprocess_text(text, &headings, &options), "История\n\nТекст.");
+    // Prose between two identical headings keeps both
+    let text = "История\n\nПервый раздел.\n\nИстория\n\nВторой раздел.";
+    assert_eq!(postprocess_text(text, &headings, &options), text);
+    // Off by default
+    let text = "История\n\nИстория";
+    assert_eq!(postprocess_text(text, &headings, &ParseOptions::default()), text);
+}
+
+#[test]
+fn markdown_headings_still_match_section_names() {
+    let options = ParseOptions {
+        heading_style: HeadingStyle::Markdown,
+        section_names: vec!["Примечания".to_string(), "Ссылки".to_string()],
+        ..Default::default()
+    };
+    let text = "Вступление.\n\n## Примечания\n\n## Ссылки";
+    assert_eq!(postprocess_text(text, &HashSet::new(), &options), "Вступление.");
+}
+
+/// A `<ref>` tag node covering `source` within `wikitext`
+fn ref_tag<'a>(wikitext: &'a str, source: &str, nodes: Vec<Node<'a>>) -> Node<'a> {
+    let start = wikitext.find(source).unwrap();
+    Node::Tag { end: start + source.len(), name: "ref".into(), nodes, start }
+}
+
+fn extract_refs(style: RefStyle) -> String {
+    let wikitext = "Факт<ref name=\"a\">Иванов, 2001.</ref> и ещё<ref>Петров<ref>вложенная</ref></ref>, снова<ref name=a />.";
+    let nodes = vec![
+        text("Факт"),
+        ref_tag(wikitext, "<ref name=\"a\">Иванов, 2001.</ref>", vec![text("Иванов, 2001.")]),
+        text(" и ещё"),
+        ref_tag(
+            wikitext,
+            "<ref>Петров<ref>вложенная</ref></ref>",
+            vec![text("Петров"), ref_tag(wikitext, "<ref>вложенная</ref>", vec![text(" вложенная")])],
+        ),
+        text(", снова"),
+        ref_tag(wikitext, "<ref name=a />", vec![]),
+        text("."),
+    ];
+    let options = ParseOptions { keep_refs: style, ..Default::default() };
+    extract_text_from_nodes(&nodes, wikitext, &options).unwrap()
+}
+
+#[test]
+fn refs_are_dropped_inlined_or_numbered_as_footnotes() {
+    assert_eq!(extract_refs(RefStyle::Drop), "Факт и ещё, снова.");
+    assert_eq!(extract_refs(RefStyle::Inline), "Факт Иванов, 2001. и ещё Петров вложенная, снова.");
+    // The named ref keeps its first number; the nested ref is dropped
+    assert_eq!(
+        extract_refs(RefStyle::Footnote),
+        "Факт[1] и ещё[2], снова[1].\n\n[1] Иванов, 2001.\n[2] Петров"
+    );
+}
+
+/// A template node with positional arguments, plus named ones as `name=value`
+fn template(name: &'static str, arguments: Vec<Vec<Node<'static>>>) -> Node<'static> {
+    let parameters = arguments
+        .into_iter()
+        .map(|value| match value.as_slice() {
+            [Node::Text { value: argument, .. }] if argument.contains('=') => {
+                let (name, value) = argument.split_once('=').unwrap();
+                Parameter { end: 0, name: Some(vec![text(name)]), start: 0, value: vec![text(value)] }
+            }
+            _ => Parameter { end: 0, name: None, start: 0, value },
+        })
+        .collect();
+    Node::Template { end: 0, name: vec![text(name)], parameters, start: 0 }
+}
+
+fn expand(nodes: Vec<Node<'static>>, language: Language) -> String {
+    let options = ParseOptions {
+        expand_templates: DEFAULT_EXPAND_TEMPLATES.iter().map(|name| name.to_string()).collect(),
+        language,
+        ..Default::default()
+    };
+    extract_text_from_nodes(&nodes, "", &options).unwrap()
+}
+
+#[test]
+fn date_and_number_templates_expand_from_the_node_tree() {
+    let nodes = vec![
+        text("Родился "),
+        template("СС3", vec![vec![text("18.1.1918")]]),
+        text(", в "),
+        template("год", vec![vec![text("1918")]]),
+        text(" году, тираж "),
+        template("num", vec![vec![text("5000")]]),
+        text("."),
+    ];
+    assert_eq!(expand(nodes, Language::Ru), "Родился 18 января 1918, в 1918 году, тираж 5000.");
+
+    let nodes = vec![
+        text("Born "),
+        template("Birth date and age", vec![vec![text("1918")], vec![text("1")], vec![text("18")], vec![text("df=y")]]),
+        text(", ran "),
+        template("convert", vec![vec![text("5")], vec![text("km")], vec![text("mi")]]),
+        text(" and "),
+        template("convert", vec![vec![text("5")], vec![text("-")], vec![text("10")], vec![text("km")]]),
+        text(", swam "),
+        template("Конвертация", vec![vec![text("2")], vec![text("km")], vec![text("mi")]]),
+        text("."),
+    ];
+    assert_eq!(expand(nodes, Language::En), "Born 18 January 1918, ran 5 km and 5–10 km, swam 2 km.");
+}
+
+#[test]
+fn lang_templates_keep_their_text() {
+    let italic = || Node::Italic { end: 0, start: 0 };
+    let nodes = || {
+        vec![
+            text("Девиз: "),
+            template("lang", vec![vec![text("la")], vec![italic(), text("Carpe diem"), italic()]]),
+            text(", по-английски "),
+            template("Lang-en", vec![vec![text("Seize the day")]]),
+            text("."),
+        ]
+    };
+    let options = ParseOptions::default();
+    assert_eq!(extract_text_from_nodes(&nodes(), "", &options).unwrap(), "Девиз: Carpe diem, по-английски Seize the day.");
+    let options = ParseOptions { lang_codes: true, ..Default::default() };
+    assert_eq!(
+        extract_text_from_nodes(&nodes(), "", &options).unwrap(),
+        "Девиз: [la] Carpe diem, по-английски [en] Seize the day."
+    );
+
+    // Leaked into the flattened text
+    assert_eq!(
+        expand_common_templates("{{lang|en|Hello}} и {{lang-de|Guten Tag}}", Language::Ru),
+        "Hello и Guten Tag"
+    );
+}
+
+#[test]
+fn template_arguments_keep_nested_markup_text() {
+    let link = Node::Link { end: 0, start: 0, target: "Москва", text: vec![text("Москве")] };
+    let nodes = vec![text("В "), template("nowrap", vec![vec![link]]), text(".")];
+    assert_eq!(expand(nodes, Language::Ru), "В Москве.");
+
+    // Not allow-listed, or arguments that don't fit: skipped like other templates
+    let options = ParseOptions::default();
+    let nodes = vec![text("A"), template("num", vec![vec![text("5")]]), text("B")];
+    assert_eq!(extract_text_from_nodes(&nodes, "", &options).unwrap(), "AB");
+    let nodes = vec![text("A"), template("СС3", vec![vec![text("вчера")]]), text("B")];
+    assert_eq!(expand(nodes, Language::Ru), "AB");
+}
+
+#[test]
+fn maintenance_banners_are_dropped_with_their_arguments() {
+    let defaults: Vec<String> = DEFAULT_DROP_TEMPLATES.iter().map(|name| name.to_string()).collect();
+    let leaked = "{{Нет источников|дата=2020-01-01}}Москва — столица.{{Geo-stub}}";
+    assert_eq!(remove_dropped_templates(leaked, &defaults), "Москва — столица.");
+    // "заготовка*" matches by prefix, whatever follows
+    assert_eq!(remove_dropped_templates("А{{Заготовка статьи о городе|регион=Тверь}}Б", &defaults), "АБ");
+    // --drop-templates "" leaves no patterns, which disables the filter
+    assert_eq!(remove_dropped_templates(leaked, &[]), leaked);
+
+    // In the node tree a drop-listed template goes before any expansion
+    let options = ParseOptions {
+        drop_templates: vec!["nowrap".to_string()],
+        expand_templates: vec!["nowrap".to_string()],
+        ..Default::default()
+    };
+    let nodes = vec![text("A"), template("nowrap", vec![vec![text("B")]]), text("C")];
+    assert_eq!(extract_text_from_nodes(&nodes, "", &options).unwrap(), "AC");
+    let options = ParseOptions { drop_templates: defaults, ..Default::default() };
+    let nodes = vec![template("Нет источников", vec![vec![text("дата=2020")]]), text("Текст.")];
+    assert_eq!(extract_text_from_nodes(&nodes, "", &options).unwrap(), "Текст.");
+}
+
+#[test]
+fn sortkey_templates_leave_the_text_for_the_sortkey_column() {
+    let wikitext = "Пушкин — поэт.\n\n{{DEFAULTSORT:Пушкин, Александр}}";
+    // The sort key never reaches the body text, leaked or in the node tree
+    let leaked = postprocess_text(wikitext, &HashSet::new(), &ParseOptions::default());
+    assert_eq!(leaked, "Пушкин — поэт.");
+    let nodes = vec![text("Пушкин — поэт."), template("DEFAULTSORT:Пушкин, Александр", Vec::new())];
+    assert_eq!(extract_text_from_nodes(&nodes, "", &ParseOptions::default()).unwrap(), "Пушкин — поэт.");
+
+    // ...but --extract-sortkey reads it from the source text
+    assert_eq!(extract_sortkey(wikitext).as_deref(), Some("Пушкин, Александр"));
+    assert_eq!(extract_sortkey("{{сортировка|Ленин}}").as_deref(), Some("Ленин"));
+    assert_eq!(extract_sortkey("Без ключа."), None);
+}
+
+#[test]
+fn interwiki_link_templates_keep_the_display_text() {
+    let options = ParseOptions::default();
+    let extract = |nodes: Vec<Node<'static>>| extract_text_from_nodes(&nodes, "", &options).unwrap();
+    // {{не переведено 5|Иванов, Пётр|Пётр Иванов|en|Peter Ivanov}}
+    let arguments = |values: [&'static str; 4]| values.into_iter().map(|value| vec![text(value)]).collect();
+    let iw = template("не переведено 5", arguments(["Иванов, Пётр", "Пётр Иванов", "en", "Peter Ivanov"]));
+    let nodes = vec![text("Физик "), iw, text(" родился в Твери.")];
+    assert_eq!(extract(nodes), "Физик Пётр Иванов родился в Твери.");
+    // A blank display argument falls back to the Russian title, never the foreign one
+    let iw = template("нп5", arguments(["Гёдель, Курт", " ", "de", "Kurt Gödel"]));
+    assert_eq!(extract(vec![iw]), "Гёдель, Курт");
+}
+
+fn link<'a>(target: &'a str, nodes: Vec<Node<'a>>) -> Node<'a> {
+    Node::Link { end: 0, start: 0, target, text: nodes }
+}
+
+fn extract_links(style: LinkStyle) -> String {
+    let nodes = vec![
+        link("Moscow", vec![text("the capital")]),
+        text(" and "),
+        link("Saint Petersburg", vec![text("Saint Petersburg")]),
+        text("."),
+        link("File:Kremlin.jpg", vec![text("Kremlin")]),
+    ];
+    let options = ParseOptions { links: style, ..Default::default() };
+    extract_text_from_nodes(&nodes, "", &options).unwrap()
+}
+
+#[test]
+fn piped_links_keep_text_target_or_wiki_markup() {
+    assert_eq!(extract_links(LinkStyle::Text), "the capital and Saint Petersburg.");
+    assert_eq!(extract_links(LinkStyle::Target), "Moscow and Saint Petersburg.");
+    assert_eq!(extract_links(LinkStyle::Wiki), "[[Moscow|the capital]] and [[Saint Petersburg]].");
+}
+
+#[test]
+fn named_and_numeric_entities_are_decoded() {
+    assert_eq!(decode_entities("A&nbsp;B", false), "A B");
+    assert_eq!(decode_entities("A&nbsp;B", true), "A\u{a0}B");
+    assert_eq!(decode_entities("&#1040;&#x411;&#X412; &mdash; &laquo;Г&raquo;", false), "АБВ — «Г»");
+    // Unknown, unterminated and invalid entities stay as written
+    assert_eq!(decode_entities("R&D, &foo; &amp &#0; &#xZZ;", false), "R&D, &foo; &amp &#0; &#xZZ;");
+}
+
+#[test]
+fn character_entity_nodes_keep_words_apart() {
+    let nodes = [
+        text("A"),
+        Node::CharacterEntity { character: '\u{a0}', end: 0, start: 0 },
+        text("B&#1040;"),
+    ];
+    let text = extract_text_from_nodes(&nodes, "", &ParseOptions::default());
+    assert_eq!(text.as_deref(), Some("A BА"));
+}
+
+#[test]
+fn short_paragraphs_are_dropped_by_character_count() {
+    let options = ParseOptions { min_paragraph_chars: 10, ..Default::default() };
+    // "Шаблон" is 12 bytes but 6 characters; "Девять букв" is 11 characters
+    let text = "Шаблон\n\nДевять букв\n\nabc\n\nДесятьбукв";
+    assert_eq!(postprocess_text(text, &HashSet::new(), &options), "Девять букв\n\nДесятьбукв");
+    assert_eq!(postprocess_text(text, &HashSet::new(), &ParseOptions::default()), text);
+}
+
+#[test]
+fn leaked_templates_are_removed_unless_allowlisted() {
+    let allowed = vec!["nowrap".to_string(), "num".to_string()];
+    let text = "Итого {{nowrap|{{num|5000}} км}}{{Нет АИ|2020}}, где f(x) = {x}{{незакрытый";
+    assert_eq!(
+        remove_leaked_templates(text, &allowed, Language::Ru),
+        "Итого 5000 км, где f(x) = {x}{{незакрытый"
+    );
+    // Without the allowlist every template goes
+    assert_eq!(remove_leaked_templates("a{{nowrap|b}}c", &[], Language::Ru), "ac");
+}
+
+#[test]
+fn deeply_nested_templates_are_removed_and_unmatched_braces_kept() {
+    let nested = (0..12).fold("глубина".to_string(), |inner, level| format!("{{{{уровень{}|{}}}}}", level, inner));
+    assert_eq!(remove_leaked_templates(&format!("До {}после.", nested), &[], Language::Ru), "До после.");
+
+    // Stray closing braces and an unclosed template around a real one
+    let text = "Множество }} и {{ a {{шаблон|{{вложенный}}}} b, {x}.";
+    assert_eq!(remove_leaked_templates(text, &[], Language::Ru), "Множество }} и {{ a  b, {x}.");
+}
+
+fn tag(name: &'static str, end_tag: bool) -> Node<'static> {
+    match end_tag {
+        false => Node::StartTag { end: 0, name: name.into(), start: 0 },
+        true => Node::EndTag { end: 0, name: name.into(), start: 0 },
+    }
+}
+
+#[test]
+fn br_and_p_tags_break_lines_and_paragraphs() {
+    let nodes = vec![
+        text("Строка один "),
+        tag("br", false),
+        text("строка два"),
+        tag("BR", false),
+        text("строка три"),
+        tag("p", false),
+        text("Абзац"),
+        tag("p", true),
+        text("Хвост."),
+    ];
+    let options = ParseOptions { html_breaks: true, ..Default::default() };
+    assert_eq!(
+        extract_text_from_nodes(&nodes, "", &options).unwrap(),
+        "Строка один\nстрока два\nстрока три\n\nАбзац\n\nХвост."
+    );
+    assert_eq!(
+        extract_text_from_nodes(&nodes, "", &ParseOptions::default()).unwrap(),
+        "Строка один строка двастрока триАбзацХвост."
+    );
+}
+
+#[test]
+fn block_formatting_keeps_poem_lines_and_quotes_blockquotes() {
+    let poem = Node::Tag {
+        end: 0,
+        name: "poem".into(),
+        nodes: vec![text("\nЯ помню чудное мгновенье:\n  Передо мной явилась ты,\nКак мимолетное виденье,\n")],
+        start: 0,
+    };
+    let nodes = vec![
+        text("Стихи:"),
+        poem,
+        tag("blockquote", false),
+        text("Цитата один"),
+        tag("br", false),
+        text("цитата два"),
+        tag("blockquote", true),
+        text("Конец."),
+    ];
+    let options = ParseOptions { html_breaks: true, block_formatting: true, ..Default::default() };
+    assert_eq!(
+        extract_text_from_nodes(&nodes, "", &options).unwrap(),
+        "Стихи:\n\nЯ помню чудное мгновенье:\nПередо мной явилась ты,\nКак мимолетное виденье,\n\n\
+         > Цитата один\n> цитата два\n\nКонец."
+    );
+}
+
+#[test]
+fn repeated_paragraphs_are_dropped_in_first_occurrence_order() {
+    let text = "Первый.\n\nВторой  абзац.\n\nПервый.\n\nТретий.\n\nВторой абзац.\n\nПервый.";
+    let dedup = |dedup_paragraphs| {
+        let options = ParseOptions { dedup_paragraphs, ..Default::default() };
+        postprocess_text(text, &HashSet::new(), &options)
+    };
+    assert_eq!(dedup(None), text);
+    assert_eq!(
+        dedup(Some(ParagraphDedup::Exact)),
+        "Первый.\n\nВторой  абзац.\n\nТретий.\n\nВторой абзац."
+    );
+    // Whitespace-only differences count as repeats
+    assert_eq!(dedup(Some(ParagraphDedup::Whitespace)), "Первый.\n\nВторой  абзац.\n\nТретий.");
+}
+
+#[test]
+fn links_into_dropped_namespaces_lose_their_text() {
+    let nodes = vec![
+        link("Москва", vec![text("Москва")]),
+        text(" "),
+        link("Категория:Города", vec![text("Категория:Города")]),
+        link(":категория:Столицы", vec![text("Столицы")]),
+        link("Template:Infobox", vec![text("Infobox")]),
+        link("wikt:столица", vec![text("столица")]),
+        link("Москва: история", vec![text("история")]),
+    ];
+    let extract = |namespaces: &[&str]| {
+        let options = ParseOptions {
+            drop_link_namespaces: namespaces.iter().map(|n| n.to_string()).collect(),
+            ..Default::default()
+        };
+        extract_text_from_nodes(&nodes, "", &options).unwrap()
+    };
+    assert_eq!(extract(DEFAULT_DROP_LINK_NAMESPACES), "Москва история");
+    assert_eq!(extract(&["Категория"]), "Москва Infoboxстолицаистория");
+    assert_eq!(extract(&[]), "Москва Категория:ГородаСтолицыInfoboxстолицаистория");
+}
+
+fn cell<'a>(type_: TableCellType, attributes: Option<&'a str>, value: &'a str) -> TableCell<'a> {
+    TableCell { attributes: attributes.map(|a| vec![text(a)]), content: vec![text(value)], end: 0, start: 0, type_ }
+}
+
+fn row(cells: Vec<TableCell<'_>>) -> TableRow<'_> {
+    TableRow { attributes: vec![], cells, end: 0, start: 0 }
+}
+
+/// A 2x2 table under a header row spanning both columns:
+/// ```text
+/// {|
+/// ! colspan="2" | Города
+/// |-
+/// | Москва || 13 млн
+/// |-
+/// | Тверь || 0,4 млн
+/// |}
+/// ```
+fn cities_table(header_cells: bool) -> Node<'static> {
+    let header_type = if header_cells { TableCellType::Heading } else { TableCellType::Ordinary };
+    Node::Table {
+        attributes: vec![],
+        captions: vec![],
+        end: 0,
+        rows: vec![
+            row(vec![cell(header_type, Some("colspan=\"2\" "), "Города")]),
+            row(vec![cell(TableCellType::Ordinary, None, "Москва"), cell(TableCellType::Ordinary, None, " 13 млн ")]),
+            row(vec![cell(TableCellType::Ordinary, None, "Тверь"), cell(TableCellType::Ordinary, None, "0,4 млн")]),
+        ],
+        start: 0,
+    }
+}
+
+fn extract_table(style: TableStyle, header_cells: bool) -> String {
+    let nodes = vec![text("До."), cities_table(header_cells), text("После.")];
+    let options = ParseOptions { tables: style, ..Default::default() };
+    extract_text_from_nodes(&nodes, "", &options).unwrap()
+}
+
+#[test]
+fn tables_are_skipped_by_default() {
+    assert_eq!(extract_table(TableStyle::Skip, true), "До.После.");
+}
+
+#[test]
+fn text_tables_put_one_row_per_line() {
+    assert_eq!(
+        extract_table(TableStyle::Text, true),
+        "До.\n\nГорода\nМосква\t13 млн\nТверь\t0,4 млн\n\nПосле."
+    );
+}
+
+#[test]
+fn markdown_tables_pad_colspan_and_use_header_rows() {
+    assert_eq!(
+        extract_table(TableStyle::Markdown, true),
+        "До.\n\n| Города |  |\n| --- | --- |\n| Москва | 13 млн |\n| Тверь | 0,4 млн |\n\nПосле."
+    );
+    // Without header cells every row stays in the body under a blank header
+    assert_eq!(
+        extract_table(TableStyle::Markdown, false),
+        "До.\n\n|  |  |\n| --- | --- |\n| Города |  |\n| Москва | 13 млн |\n| Тверь | 0,4 млн |\n\nПосле."
+    );
+}
+
+#[test]
+fn tables_inside_kept_templates_are_extracted() {
+    let extract = |name: &'static str, style: TableStyle| {
+        let infobox = template(name, vec![vec![text("название=Москва")], vec![cities_table(true)]]);
+        let nodes = vec![text("До."), infobox, text("После.")];
+        let options = ParseOptions { tables: style, drop_templates: vec!["навигация*".to_string()], ..Default::default() };
+        extract_text_from_nodes(&nodes, "", &options).unwrap()
+    };
+    assert_eq!(extract("Карточка города", TableStyle::Text), "До.\n\nГорода\nМосква\t13 млн\nТверь\t0,4 млн\n\nПосле.");
+    assert_eq!(extract("Карточка города", TableStyle::Skip), "До.После.");
+    // A dropped template takes its table with it
+    assert_eq!(extract("Навигация по городам", TableStyle::Text), "До.После.");
+}
+
+#[test]
+fn normalize_whitespace_keeps_paragraphs_and_indentation() {
+    let text = "word  word\u{a0}\tend  \n\nСписок:\n\n- a \n  1. b\n   \n- d";
+    let options = ParseOptions { normalize_whitespace: true, ..Default::default() };
+    assert_eq!(
+        postprocess_text(text, &HashSet::new(), &options),
+        "word word end\n\nСписок:\n\n- a\n  1. b\n- d"
+    );
+    let unchanged = postprocess_text(text, &HashSet::new(), &ParseOptions::default());
+    assert!(unchanged.starts_with("word  word"));
+}
+
+#[test]
+fn display_title_is_extracted_without_markup() {
+    assert_eq!(extract_display_title("{{DISPLAYTITLE:Foo}}\nТекст.").as_deref(), Some("Foo"));
+    assert_eq!(
+        extract_display_title("{{displaytitle: <i>Титаник</i> (фильм)|noerror}}").as_deref(),
+        Some("Титаник (фильм)")
+    );
+    assert_eq!(extract_display_title("{{ПОКАЗАТЬ_ЗАГОЛОВОК:''iPhone''}}").as_deref(), Some("iPhone"));
+    assert_eq!(extract_display_title("{{DISPLAYTITLE:}} {{DEFAULTSORT:Foo}}"), None);
+}
+
+#[test]
+fn categories_are_listed_once_in_order_without_sort_keys() {
+    let wikitext = "Текст [[:Категория:Ссылка]].\n[[Категория:Города России|Москва]]\n[[Category:Capitals_in_Europe]]\n\
+                    [[категория: Города России ]]\n[[Kategorie:Hauptstadt]]";
+    assert_eq!(
+        extract_categories(wikitext).as_deref(),
+        Some("Города России\nCapitals in Europe\nHauptstadt")
+    );
+    assert_eq!(extract_categories("[[Файл:Moscow.jpg]] [[Россия]]"), None);
+}
+
+#[test]
+fn redirect_pages_yield_their_target() {
+    assert_eq!(extract_redirect_target("#REDIRECT [[Target]]").as_deref(), Some("Target"));
+    assert_eq!(
+        extract_redirect_target("  #перенаправление [[Москва_(город)#История|Москва]]\n{{R from move}}").as_deref(),
+        Some("Москва (город)#История")
+    );
+    assert_eq!(extract_redirect_target("#WEITERLEITUNG: [[Berlin]]").as_deref(), Some("Berlin"));
+    // Only at the start of the page
+    assert_eq!(extract_redirect_target("Текст.\n#REDIRECT [[Target]]"), None);
+    assert_eq!(extract_redirect_target("'''Москва''' — столица."), None);
+}
+
+fn truncated(text: &str, max_output_chars: usize) -> String {
+    let options = ParseOptions { max_output_chars, ..Default::default() };
+    postprocess_text(text, &HashSet::new(), &options)
+}
+
+#[test]
+fn max_output_chars_cuts_at_sentence_paragraph_or_word_boundaries() {
+    let text = "Один два три. Четыре пять шесть семь.";
+    assert_eq!(truncated(text, 20), "Один два три. …");
+    assert_eq!(truncated("Один два три четыре пять шесть", 12), "Один два…");
+    assert_eq!(truncated("Заголовок\n\nТекст статьи идёт дальше", 16), "Заголовок…");
+    // A sentence end too early in the text loses to the last word boundary
+    assert_eq!(truncated("Да. Это очень длинное предложение без точки", 30), "Да. Это очень длинное…");
+    assert_eq!(truncated("Антидисэстаблишментарианство", 10), "Антидисэс…");
+}
+
+#[test]
+fn max_output_chars_never_exceeds_the_limit() {
+    let text = "Первый абзац. Ещё предложение!\n\nВторой абзац с текстом";
+    let length = text.chars().count();
+    assert_eq!(truncated(text, length), text);
+    assert_eq!(truncated(text, 0), text);
+    for max in 1..length {
+        let result = truncated(text, max);
+        assert!(result.chars().count() <= max, "{} > {}: {:?}", result.chars().count(), max, result);
+        assert!(result.ends_with('…'));
+    }
+}
+
+#[test]
+fn normalize_unicode_and_lowercase_apply_to_the_final_text() {
+    let normalized = |text: &str, normalize_unicode, lowercase| {
+        let options = ParseOptions { normalize_unicode, lowercase, ..Default::default() };
+        postprocess_text(text, &HashSet::new(), &options)
+    };
+    let decomposed = "Ёлка и е\u{0308}ж";
+    assert_eq!(normalized(decomposed, UnicodeForm::None, false), decomposed);
+    assert_eq!(normalized(decomposed, UnicodeForm::Nfc, false), "Ёлка и ёж");
+    assert_eq!(normalized("\u{FB01}лм ＡＢ", UnicodeForm::Nfc, false), "\u{FB01}лм ＡＢ");
+    assert_eq!(normalized("\u{FB01}лм ＡＢ", UnicodeForm::Nfkc, false), "fiлм AB");
+    assert_eq!(normalized("ЁЛКА В МОСКВЕ, Київ", UnicodeForm::None, true), "ёлка в москве, київ");
+    assert_eq!(normalized("Е\u{0308}Ж", UnicodeForm::Nfc, true), "ёж");
+}
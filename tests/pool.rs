@@ -0,0 +1,53 @@
+//! Timed-out articles don't pile up abandoned threads
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+use wikitext_parser::pool::ParsePool;
+use wikitext_parser::SkipReason;
+
+#[test]
+fn abandoned_workers_are_capped_and_rejoin_the_pool() {
+    let active = Arc::new(AtomicUsize::new(0));
+    let peak = Arc::new(AtomicUsize::new(0));
+    let (active_in_pool, peak_in_pool) = (Arc::clone(&active), Arc::clone(&peak));
+
+    // One worker, 1s timeout; "slow" articles take 3s, so each one times out
+    let pool = ParsePool::new(1, 1, move |text| {
+        let now = active_in_pool.fetch_add(1, Ordering::SeqCst) + 1;
+        peak_in_pool.fetch_max(now, Ordering::SeqCst);
+        if text == "slow" {
+            thread::sleep(Duration::from_secs(3));
+        }
+        active_in_pool.fetch_sub(1, Ordering::SeqCst);
+        (Ok(text.to_uppercase()), Vec::new())
+    });
+
+    let texts = [Some("slow"), Some("slow"), Some("slow"), Some("fast")];
+    let (results, _) = pool.parse_all(&texts, |i| format!("row {}", i));
+
+    assert_eq!(
+        results,
+        vec![
+            Some(Err(SkipReason::Timeout)),
+            Some(Err(SkipReason::Timeout)),
+            Some(Err(SkipReason::Timeout)),
+            Some(Ok("FAST".to_string())),
+        ]
+    );
+    // The pool worker plus at most one abandoned one were ever parsing at once
+    assert_eq!(peak.load(Ordering::SeqCst), 2);
+}
+
+#[test]
+fn parser_warnings_come_back_with_their_rows() {
+    let pool = ParsePool::new(2, 0, |text| {
+        let warnings = if text.contains('<') { vec![("UnrecognizedTagName".to_string(), 1)] } else { Vec::new() };
+        (Ok(text.to_string()), warnings)
+    });
+
+    let (results, warnings) = pool.parse_all(&[Some("a"), None, Some("<b>")], |i| format!("row {}", i));
+    assert_eq!(results, vec![Some(Ok("a".to_string())), None, Some(Ok("<b>".to_string()))]);
+    assert_eq!(warnings, vec![Vec::new(), Vec::new(), vec![("UnrecognizedTagName".to_string(), 1)]]);
+}
@@ -0,0 +1,30 @@
+//! Extraction-quality scores of parsed text (`--quality-score`)
+
+use wikitext_parser::quality::quality_score;
+
+#[test]
+fn scores_stay_between_zero_and_one() {
+    let texts = [
+        "",
+        "   \n\n  ",
+        "Москва — столица России и крупнейший по численности населения город страны.",
+        "{{{{}}}}[[[[]]]]||||<<<<>>>>====''''",
+        "12345 67890 | 1 | 2 | 3",
+        "История\n\nГород основан в 1147 году князем Юрием Долгоруким на берегу реки.",
+        "а",
+    ];
+    for text in texts {
+        let score = quality_score(text);
+        assert!((0.0..=1.0).contains(&score), "{:?} scored {}", text, score);
+    }
+}
+
+#[test]
+fn clean_prose_outscores_leaked_markup() {
+    let prose = "Москва — столица России и крупнейший по численности населения город страны.\n\n\
+                 Город основан в 1147 году князем Юрием Долгоруким на берегу реки Москвы.";
+    let markup = "{{Карточка города|название=Москва|страна=Россия}}\n\n| Население || 13 010 112 |}";
+    assert!(quality_score(prose) >= 0.8, "{}", quality_score(prose));
+    assert!(quality_score(markup) < quality_score(prose) / 2.0, "{}", quality_score(markup));
+    assert_eq!(quality_score(""), 0.0);
+}
@@ -0,0 +1,37 @@
+//! Splitting parsed text with Markdown headings into sections
+
+use wikitext_parser::sections::{paragraphs_with_paths, split_sections, Section};
+
+#[test]
+fn split_sections_starts_a_section_at_each_markdown_heading() {
+    let sections = split_sections("## Пустой\n\n## Раздел\n\nТекст.\n\n#хэштег в начале абзаца");
+    assert_eq!(
+        sections,
+        vec![
+            Section { heading: Some("Пустой".to_string()), level: Some(2), paragraphs: vec![] },
+            Section {
+                heading: Some("Раздел".to_string()),
+                level: Some(2),
+                paragraphs: vec!["Текст.".to_string(), "#хэштег в начале абзаца".to_string()],
+            },
+        ]
+    );
+    assert!(split_sections("").is_empty());
+}
+
+#[test]
+fn section_paths_follow_the_heading_stack() {
+    let text = "Лид.\n\n## История\n\nНачало.\n\n### Ранние годы\n\nДетство.\n\n#### Школа\n\n### Зрелость\n\nРабота.\n\n## Память\n\nМузей.";
+    let path = |path: &str, para: &str| (Some(path.to_string()), para.to_string());
+    assert_eq!(
+        paragraphs_with_paths(text),
+        vec![
+            (None, "Лид.".to_string()),
+            path("История", "Начало."),
+            path("История > Ранние годы", "Детство."),
+            // The empty level-4 section and its level-3 parent are closed by the next level-3 heading
+            path("История > Зрелость", "Работа."),
+            path("Память", "Музей."),
+        ]
+    );
+}
@@ -0,0 +1,30 @@
+//! Sentence segmentation (`--segment-sentences`)
+
+use wikitext_parser::sentences::split_sentences;
+
+#[test]
+fn abbreviations_initials_and_decimals_do_not_end_sentences() {
+    let text = "А. С. Пушкин родился в 1799 г. в Москве, т. е. в Немецкой слободе. Население — 1,5 млн. чел., \
+                площадь 3.14 км². См. также ниже! Были яблоки, груши и т. д. Потом «Онегин»… Конец?";
+    assert_eq!(
+        split_sentences(text),
+        vec![
+            "А. С. Пушкин родился в 1799 г. в Москве, т. е. в Немецкой слободе.",
+            "Население — 1,5 млн. чел., площадь 3.14 км².",
+            "См. также ниже!",
+            "Были яблоки, груши и т. д.",
+            "Потом «Онегин»…",
+            "Конец?",
+        ]
+    );
+}
+
+#[test]
+fn line_breaks_end_sentences_and_quotes_stay_with_them() {
+    let text = "Он сказал: «Пора.» Все ушли\n\nЗаголовок\n- пункт списка. «Цитата» — конец.";
+    assert_eq!(
+        split_sentences(text),
+        vec!["Он сказал: «Пора.»", "Все ушли", "Заголовок", "- пункт списка.", "«Цитата» — конец."]
+    );
+    assert!(split_sentences(" \n\n ").is_empty());
+}
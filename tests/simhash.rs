@@ -0,0 +1,33 @@
+//! SimHash fingerprints of parsed text (`--emit-simhash`)
+
+use wikitext_parser::simhash::simhash;
+
+const ARTICLE: &str = "Москва — столица России, город федерального значения, административный центр Центрального \
+     федерального округа и центр Московской области, в состав которой не входит. Крупнейший по численности \
+     населения город России и её субъект, самый населённый из городов, полностью расположенных в Европе, \
+     входит в десятку городов мира по численности населения, крупнейший русскоязычный город в мире. Центр \
+     Московской городской агломерации. Историческая столица Великого княжества Московского, Русского царства, \
+     Российской империи в 1728—1730 годах, Советской России и СССР. Город-герой.";
+
+fn distance(a: &str, b: &str) -> u32 {
+    (simhash(a) ^ simhash(b)).count_ones()
+}
+
+#[test]
+fn near_duplicates_are_within_a_few_bits() {
+    // Case and punctuation do not change the tokens
+    assert_eq!(simhash(ARTICLE), simhash(&ARTICLE.to_uppercase().replace(',', "")));
+    // One edited word moves only a few bits
+    let edited = ARTICLE.replace("Крупнейший", "Наибольший");
+    assert!(distance(ARTICLE, &edited) <= 8, "distance {}", distance(ARTICLE, &edited));
+    // An unrelated article lands far away
+    let other = "Пётр Ильич Чайковский — русский композитор, дирижёр, педагог, музыкальный критик и общественный \
+         деятель. Автор опер, балетов, симфоний, концертов и более ста романсов, один из самых исполняемых композиторов.";
+    assert!(distance(ARTICLE, other) > 16, "distance {}", distance(ARTICLE, other));
+}
+
+#[test]
+fn texts_without_words_hash_to_zero() {
+    assert_eq!(simhash(""), 0);
+    assert_eq!(simhash(" — , . "), 0);
+}
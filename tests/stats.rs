@@ -0,0 +1,60 @@
+//! Dry-run statistics count rows by status and measure the parsed texts
+
+use arrow::array::StringArray;
+use wikitext_parser::stats::ParseStats;
+
+#[test]
+fn counts_statuses_paragraphs_and_chars() {
+    let texts = StringArray::from(vec![Some("Первый.\n\nВторой."), None, None, None, Some("Один")]);
+    let statuses = StringArray::from(vec![Some("ok"), Some("timeout"), Some("too_large"), None, Some("ok")]);
+
+    let mut stats = ParseStats::default();
+    stats.record(&texts, &statuses);
+
+    assert_eq!(
+        stats,
+        ParseStats { rows: 4, too_large: 1, timeouts: 1, unchanged: 0, parsed: 2, paragraphs: 3, chars: 20 }
+    );
+    assert_eq!(stats.average_paragraphs(), 1.5);
+}
+
+#[test]
+fn row_stats_measure_each_article() {
+    use arrow::array::{Array, ArrayRef, Int64Array, UInt32Array, UInt64Array};
+    use std::sync::Arc;
+    use wikitext_parser::stats::RowStats;
+
+    let page_ids: ArrayRef = Arc::new(Int64Array::from(vec![7, 8, 9]));
+    let inputs = [Some("'''Один'''.\n\nДва."), Some("{{огромный шаблон}}"), None];
+    let texts = [Some("Один.\n\nДва.".to_string()), None, None];
+    let statuses: ArrayRef = Arc::new(StringArray::from(vec![Some("ok"), Some("timeout"), None]));
+    let warnings: ArrayRef = Arc::new(UInt32Array::from(vec![Some(0), None, None]));
+
+    let mut row_stats = RowStats::new("page_id", &page_ids);
+    row_stats.add(|name| format!("official_{}", name), &inputs, &texts, &statuses, &warnings);
+    let batch = row_stats.finish().unwrap();
+
+    let schema = batch.schema();
+    let names: Vec<&str> = schema.fields().iter().map(|field| field.name().as_str()).collect();
+    assert_eq!(
+        names,
+        [
+            "page_id",
+            "official_input_chars",
+            "official_output_chars",
+            "official_paragraphs",
+            "official_parse_status",
+            "official_parse_warnings"
+        ]
+    );
+    let column = |name: &str| Arc::clone(batch.column_by_name(name).unwrap());
+    let input_chars = column("official_input_chars");
+    let input_chars = input_chars.as_any().downcast_ref::<UInt64Array>().unwrap();
+    assert_eq!(input_chars.iter().collect::<Vec<_>>(), vec![Some(17), Some(19), None]);
+    let output_chars = column("official_output_chars");
+    let output_chars = output_chars.as_any().downcast_ref::<UInt64Array>().unwrap();
+    assert_eq!(output_chars.iter().collect::<Vec<_>>(), vec![Some(11), None, None]);
+    let paragraphs = column("official_paragraphs");
+    let paragraphs = paragraphs.as_any().downcast_ref::<UInt32Array>().unwrap();
+    assert_eq!(paragraphs.iter().collect::<Vec<_>>(), vec![Some(2), None, None]);
+}
@@ -0,0 +1,74 @@
+//! Skipped articles get a null text and a parse_status naming the reason
+
+use arrow::array::{Array, BooleanArray, StringArray, UInt32Array};
+use wikitext_parser::status::{skipped_array, status_array, texts, truncate_results, warning_counts};
+use wikitext_parser::SkipReason;
+
+#[test]
+fn skipped_rows_have_null_text_and_a_reason() {
+    let results = vec![
+        Some(Ok("Текст.".to_string())),
+        Some(Err(SkipReason::Timeout)),
+        Some(Err(SkipReason::TooLarge)),
+        None,
+        Some(Ok("Исходник".to_string())),
+    ];
+    let changed = BooleanArray::from(vec![true, true, true, true, false]);
+
+    let statuses = status_array(&results, Some(&changed));
+    let statuses = statuses.as_any().downcast_ref::<StringArray>().unwrap();
+    let statuses: Vec<Option<&str>> = statuses.iter().collect();
+    assert_eq!(statuses, vec![Some("ok"), Some("timeout"), Some("too_large"), None, Some("unchanged")]);
+
+    assert_eq!(
+        texts(results),
+        vec![Some("Текст.".to_string()), None, None, None, Some("Исходник".to_string())]
+    );
+}
+
+#[test]
+fn warning_counts_cover_only_parsed_rows() {
+    let warnings = vec![
+        vec![("InvalidCharacter".to_string(), 1), ("UnrecognizedTagName".to_string(), 2)],
+        Vec::new(),
+        Vec::new(),
+        Vec::new(),
+    ];
+    let results = vec![
+        Some(Ok("Текст.".to_string())),
+        Some(Err(SkipReason::Timeout)),
+        None,
+        Some(Ok("Исходник".to_string())),
+    ];
+    let changed = BooleanArray::from(vec![true, true, true, false]);
+
+    let counts = warning_counts(&warnings, &results, Some(&changed), |i| format!("row {}", i));
+    let counts = counts.as_any().downcast_ref::<UInt32Array>().unwrap();
+    assert_eq!(counts.iter().collect::<Vec<_>>(), vec![Some(3), None, None, None]);
+}
+
+#[test]
+fn skip_flags_mark_skipped_and_truncated_rows() {
+    let long = "Первое предложение. Второе предложение подлиннее.".to_string();
+    let mut results = vec![
+        Some(Ok(long.clone())),
+        Some(Ok("Коротко.".to_string())),
+        Some(Err(SkipReason::Timeout)),
+        None,
+        Some(Ok(long.clone())),
+    ];
+    let changed = BooleanArray::from(vec![true, true, true, true, false]);
+
+    let truncated = truncate_results(&mut results, 30, Some(&changed));
+    let truncated = truncated.as_any().downcast_ref::<BooleanArray>().unwrap();
+    assert_eq!(truncated.iter().collect::<Vec<_>>(), vec![Some(true), Some(false), Some(false), None, Some(false)]);
+
+    let skipped = skipped_array(&results);
+    let skipped = skipped.as_any().downcast_ref::<BooleanArray>().unwrap();
+    assert_eq!(skipped.iter().collect::<Vec<_>>(), vec![Some(false), Some(false), Some(true), None, Some(false)]);
+
+    // Unchanged passthrough rows keep their source text whole
+    let texts = texts(results);
+    assert_eq!(texts[0].as_deref(), Some("Первое предложение. …"));
+    assert_eq!(texts[4].as_deref(), Some(long.as_str()));
+}
@@ -0,0 +1,111 @@
+//! Several --input files: glob expansion and one schema across inputs; atomic local output
+
+use arrow::array::{ArrayRef, Int64Array, RecordBatch, StringArray};
+use arrow::datatypes::{DataType, Field, Schema};
+use parquet::arrow::ArrowWriter;
+use std::fs::File;
+use std::sync::Arc;
+use wikitext_parser::{schema, storage};
+
+fn write_parquet(path: &std::path::Path, page_ids: ArrayRef) {
+    let texts: ArrayRef = Arc::new(StringArray::from(vec!["Текст."; page_ids.len()]));
+    let batch = RecordBatch::try_from_iter(vec![("page_id", page_ids), ("text", texts)]).unwrap();
+    let mut writer = ArrowWriter::try_new(File::create(path).unwrap(), batch.schema(), None).unwrap();
+    writer.write(&batch).unwrap();
+    writer.close().unwrap();
+}
+
+#[test]
+fn globs_expand_in_name_order_and_schemas_must_match() {
+    let dir = std::env::temp_dir().join(format!("wikitext_inputs_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    for (name, rows) in [("part-001.parquet", 2), ("part-000.parquet", 3), ("other.parquet", 1)] {
+        write_parquet(&dir.join(name), Arc::new(Int64Array::from_iter_values(0..rows)));
+    }
+    let pattern = dir.join("part-*.parquet").to_string_lossy().into_owned();
+
+    let inputs = storage::expand_inputs(&[pattern]).unwrap();
+    let names: Vec<String> =
+        inputs.iter().map(|input| input.rsplit('/').next().unwrap().to_string()).collect();
+    assert_eq!(names, vec!["part-000.parquet", "part-001.parquet"]);
+    let (_, rows) = storage::check_inputs(&inputs).unwrap();
+    assert_eq!(rows, vec![3, 2]);
+
+    let missing = dir.join("none-?.parquet").to_string_lossy().into_owned();
+    assert!(storage::expand_inputs(&[missing]).is_err());
+
+    // A shard with string page IDs does not fit the others
+    write_parquet(&dir.join("part-002.parquet"), Arc::new(StringArray::from(vec!["7"])));
+    let inputs = storage::expand_inputs(&[dir.join("part-*").to_string_lossy().into_owned()]).unwrap();
+    let error = storage::check_inputs(&inputs).unwrap_err().to_string();
+    assert!(error.contains("part-002.parquet") && error.contains("page_id is Utf8, expected Int64"), "{}", error);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn schema_mismatches_name_every_difference() {
+    let expected = Schema::new(vec![
+        Field::new("page_id", DataType::Int64, false),
+        Field::new("text", DataType::Utf8, true),
+    ]);
+    // Nullability alone does not count
+    let relaxed = Schema::new(vec![
+        Field::new("page_id", DataType::Int64, true),
+        Field::new("text", DataType::Utf8, true),
+    ]);
+    assert_eq!(schema::mismatch(&expected, &relaxed), None);
+
+    let other = Schema::new(vec![
+        Field::new("page_id", DataType::Utf8, true),
+        Field::new("title", DataType::Utf8, true),
+    ]);
+    assert_eq!(
+        schema::mismatch(&expected, &other).as_deref(),
+        Some("page_id is Utf8, expected Int64; missing column text; extra column title")
+    );
+}
+
+#[test]
+fn local_output_replaces_the_target_only_when_finished() {
+    use std::io::Write;
+
+    let dir = std::env::temp_dir().join(format!("wikitext_atomic_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let target = dir.join("out.jsonl");
+    std::fs::write(&target, "old\n").unwrap();
+    let path = target.to_string_lossy().into_owned();
+
+    // An unfinished output (a failed run) is discarded and the old file stays
+    let mut output = storage::create_output(&path).unwrap();
+    output.write_all(b"half").unwrap();
+    assert_eq!(std::fs::read_to_string(&target).unwrap(), "old\n");
+    drop(output);
+    assert_eq!(std::fs::read_to_string(&target).unwrap(), "old\n");
+    assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+
+    let mut output = storage::create_output(&path).unwrap();
+    output.write_all(b"new\n").unwrap();
+    output.finish().unwrap();
+    assert_eq!(std::fs::read_to_string(&target).unwrap(), "new\n");
+    assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn outputs_are_matched_against_inputs_by_resolved_path() {
+    let dir = std::env::temp_dir().join(format!("wikitext_parser_same_{}", std::process::id()));
+    std::fs::create_dir_all(dir.join("sub")).unwrap();
+    let input = dir.join("shard.parquet");
+    write_parquet(&input, Arc::new(Int64Array::from(vec![1])));
+
+    let input = input.to_str().unwrap();
+    let roundabout = format!("{}/sub/../shard.parquet", dir.display());
+    assert!(storage::same_location(&roundabout, input).unwrap());
+    // An output that does not exist yet is never an input
+    assert!(!storage::same_location(&format!("{}/shard.jsonl", dir.display()), input).unwrap());
+    assert!(storage::same_location("s3://bucket/a.parquet", "s3://bucket/a.parquet").unwrap());
+    assert!(!storage::same_location("s3://bucket/a.parquet", input).unwrap());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}